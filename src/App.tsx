@@ -163,11 +163,14 @@ function App() {
     try {
       const path = await findLauncherInstancesPath();
       // Check for manifest-based updates
-      const updateInfo = await invoke("check_manifest_updates", {
-        manifestUrl: manifestUrl,
-        instanceBase: path,
-      });
-      setStatusMessage(`${updateInfo}`);
+      const updateInfo = await invoke<{ summary: string }>(
+        "check_manifest_updates",
+        {
+          manifestUrl: manifestUrl,
+          instanceBase: path,
+        },
+      );
+      setStatusMessage(updateInfo.summary);
     } catch (error) {
       setStatusMessage(`Error checking for updates: ${error}`);
     }
@@ -181,7 +184,7 @@ function App() {
 
     try {
       const path = await findLauncherInstancesPath();
-      const result = await invoke("download_from_manifest", {
+      const result = await invoke("update_instance", {
         manifestUrl: manifestUrl,
         instanceBase: path,
       });
@@ -0,0 +1,294 @@
+// Headless front end for servers and CI: the same installs as the app, with JSON on stdout.
+// Logs and progress go to stderr so the output can be piped straight into jq.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use story_core::{
+    check_instance_updates, clean_instance, export_mrpack, install_curseforge_modpack,
+    install_from_manifest, install_mrpack, install_server_from_manifest, repair_instance_files,
+    update_from_manifest, verify_instance, LauncherContext, ProgressSink,
+};
+
+// Must match "identifier" in tauri.conf.json so the CLI shares the app's settings and caches
+const APP_IDENTIFIER: &str = "com.canefe.story-launcher.app";
+
+const USAGE: &str = "Usage: story-cli <command> [options]
+
+Commands:
+  install <manifest-url>        Install or update the Story instance from a manifest
+  update <manifest-url>         Install again, moving unpinned mods to their newest versions
+  install-server <manifest-url> Lay out a dedicated server for a manifest in --server-dir
+  check-updates <manifest-url>  Compare the installed instance with a manifest
+  verify                        Re-hash every managed file of the instance
+  repair                        Re-fetch missing or modified files
+  clean                         Delete mods and packs the launcher didn't install
+  export <output.mrpack>        Export the Story instance as a Modrinth modpack
+  import <mrpack-path-or-url>   Install an .mrpack file or URL into the Story instance
+  import-curseforge <zip>       Install a CurseForge modpack zip (path or URL) into the Story instance
+
+Options:
+  --instances <dir>   Instances folder containing Story/ (required except for install-server)
+  --server-dir <dir>  install-server: the server directory (required there)
+  --accept-eula       install-server: accept the Minecraft EULA (https://aka.ms/MinecraftEULA)
+  --data-dir <dir>    Launcher data directory (default: the app's, or $STORY_LAUNCHER_DATA_DIR)
+  --offline           install/install-server/import*/repair from the local caches only
+  --remove-extra      repair: also delete mods and packs the launcher didn't install
+  --dry-run           clean: only list what would be deleted
+  --pack-version <v>  export: version of the exported pack (default: the installed one)
+
+Exit status is 0 on success, 1 when the command fails or verify finds problems, 2 on bad usage.";
+
+const COMMANDS: [&str; 10] = [
+    "install",
+    "update",
+    "install-server",
+    "check-updates",
+    "verify",
+    "repair",
+    "clean",
+    "export",
+    "import",
+    "import-curseforge",
+];
+
+struct Args {
+    command: String,
+    // The command's one positional argument: a manifest URL, export's output path or
+    // the pack to import
+    target: Option<String>,
+    instances: Option<String>,
+    server_dir: Option<String>,
+    data_dir: Option<PathBuf>,
+    offline: bool,
+    accept_eula: bool,
+    remove_extra: bool,
+    dry_run: bool,
+    pack_version: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut command = None;
+    let mut parsed = Args {
+        command: String::new(),
+        target: None,
+        instances: None,
+        server_dir: None,
+        data_dir: None,
+        offline: false,
+        accept_eula: false,
+        remove_extra: false,
+        dry_run: false,
+        pack_version: None,
+    };
+
+    // Options may come before or after the command
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--instances" => parsed.instances = Some(value("--instances")?),
+            "--server-dir" => parsed.server_dir = Some(value("--server-dir")?),
+            "--data-dir" => parsed.data_dir = Some(PathBuf::from(value("--data-dir")?)),
+            "--offline" => parsed.offline = true,
+            "--accept-eula" => parsed.accept_eula = true,
+            "--remove-extra" => parsed.remove_extra = true,
+            "--dry-run" => parsed.dry_run = true,
+            "--pack-version" => parsed.pack_version = Some(value("--pack-version")?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            _ if command.is_none() => command = Some(arg),
+            _ if parsed.target.is_none() => parsed.target = Some(arg),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+
+    parsed.command = command.ok_or("Missing command")?;
+    if !COMMANDS.contains(&parsed.command.as_str()) {
+        return Err(format!("Unknown command {}", parsed.command));
+    }
+    let target = match parsed.command.as_str() {
+        "install" | "update" | "install-server" | "check-updates" => Some("a manifest URL"),
+        "export" => Some("an output path"),
+        "import" => Some("an .mrpack path or URL"),
+        "import-curseforge" => Some("a CurseForge modpack path or URL"),
+        _ => None,
+    };
+    match (target, &parsed.target) {
+        (Some(target), None) => return Err(format!("{} needs {}", parsed.command, target)),
+        (None, Some(arg)) => return Err(format!("Unexpected argument {}", arg)),
+        _ => {}
+    }
+    if parsed.command == "update" && parsed.offline {
+        return Err("update needs the network and can't run --offline".to_string());
+    }
+    if parsed.command == "install-server" {
+        if parsed.server_dir.is_none() {
+            return Err("install-server needs --server-dir".to_string());
+        }
+    } else if parsed.instances.is_none() {
+        return Err("--instances is required".to_string());
+    }
+    Ok(parsed)
+}
+
+// Where the app keeps its data (Tauri's app_data_dir)
+fn default_data_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+    };
+    if let Some(dir) = env_dir("STORY_LAUNCHER_DATA_DIR") {
+        return Some(dir);
+    }
+    let data_dir = if cfg!(windows) {
+        env_dir("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME")?.join("Library/Application Support")
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| Some(env_dir("HOME")?.join(".local/share")))?
+    };
+    Some(data_dir.join(APP_IDENTIFIER))
+}
+
+// Progress as one line per event on stderr
+struct StderrProgress;
+
+impl ProgressSink for StderrProgress {
+    fn progress(&self, _event: &str, payload: serde_json::Value) {
+        if let Some(filename) = payload["filename"].as_str() {
+            eprintln!(
+                "[{:>3}%] {}",
+                payload["percent"].as_u64().unwrap_or(0),
+                filename
+            );
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Output<T: Serialize> {
+    command: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+async fn run(args: &Args) -> Result<(serde_json::Value, bool), String> {
+    let data_dir = args
+        .data_dir
+        .clone()
+        .or_else(default_data_dir)
+        .ok_or("Could not determine the launcher data directory, pass --data-dir")?;
+    let ctx = LauncherContext::new(data_dir, Arc::new(StderrProgress))?;
+    let instances = args.instances.clone().unwrap_or_default();
+    let manifest_url = args.target.clone().unwrap_or_default();
+    let to_json = |value: Result<serde_json::Value, serde_json::Error>| {
+        value.map_err(|e| format!("Failed to serialize output: {}", e))
+    };
+
+    match args.command.as_str() {
+        "install" => {
+            let message =
+                install_from_manifest(&ctx, manifest_url, instances, args.offline).await?;
+            Ok((serde_json::json!({ "message": message }), true))
+        }
+        "update" => {
+            let message = update_from_manifest(&ctx, manifest_url, instances).await?;
+            Ok((serde_json::json!({ "message": message }), true))
+        }
+        "install-server" => {
+            let server_dir = args.server_dir.clone().unwrap_or_default();
+            let report = install_server_from_manifest(
+                &ctx,
+                manifest_url,
+                server_dir,
+                args.accept_eula,
+                args.offline,
+            )
+            .await?;
+            Ok((to_json(serde_json::to_value(&report))?, true))
+        }
+        "check-updates" => {
+            let diff = check_instance_updates(&ctx, manifest_url, instances).await?;
+            Ok((to_json(serde_json::to_value(&diff))?, true))
+        }
+        "verify" => {
+            let report = verify_instance(&instances).await?;
+            let clean = report.missing.is_empty() && report.modified.is_empty();
+            Ok((to_json(serde_json::to_value(&report))?, clean))
+        }
+        "repair" => {
+            let report =
+                repair_instance_files(&ctx, &instances, args.remove_extra, args.offline).await?;
+            let repaired = report.failed.is_empty();
+            Ok((to_json(serde_json::to_value(&report))?, repaired))
+        }
+        "clean" => {
+            let report = clean_instance(&instances, args.dry_run)?;
+            let cleaned = report.failed.is_empty();
+            Ok((to_json(serde_json::to_value(&report))?, cleaned))
+        }
+        "export" => {
+            let output_path = PathBuf::from(args.target.clone().unwrap_or_default());
+            let report =
+                export_mrpack(&ctx, &instances, &output_path, args.pack_version.clone()).await?;
+            Ok((to_json(serde_json::to_value(&report))?, true))
+        }
+        "import" => {
+            let source = args.target.clone().unwrap_or_default();
+            let message = install_mrpack(&ctx, source, instances, args.offline).await?;
+            Ok((serde_json::json!({ "message": message }), true))
+        }
+        "import-curseforge" => {
+            let source = args.target.clone().unwrap_or_default();
+            let message = install_curseforge_modpack(&ctx, source, instances, args.offline).await?;
+            Ok((serde_json::json!({ "message": message }), true))
+        }
+        command => unreachable!("parse_args accepted unknown command {}", command),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let (output, success) = match run(&args).await {
+        Ok((result, success)) => (
+            Output {
+                command: args.command.clone(),
+                ok: success,
+                result: Some(result),
+                error: None,
+            },
+            success,
+        ),
+        Err(e) => (
+            Output {
+                command: args.command.clone(),
+                ok: false,
+                result: None,
+                error: Some(e),
+            },
+            false,
+        ),
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&output).unwrap_or_default()
+    );
+    std::process::exit(if success { 0 } else { 1 });
+}
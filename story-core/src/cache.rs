@@ -0,0 +1,715 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::install::LauncherContext;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct FileHashRegistry {
+    pub files: HashMap<String, FileInfo>, // URL -> file info
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct FileInfo {
+    pub hash: String,
+    pub last_modified: String,
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+pub async fn gc_blob_store(
+    ctx: &LauncherContext,
+    max_bytes: Option<u64>,
+) -> Result<BlobGcReport, String> {
+    let store = ctx.blob_store();
+    let max_bytes = max_bytes.unwrap_or(ctx.settings().blob_cache_max_bytes);
+
+    tokio::task::spawn_blocking(move || store.gc(max_bytes))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+// Location of the cached copy of a downloaded zip, named after the last URL path segment
+pub fn cached_zip_path(cache_dir: &Path, download_url: &str) -> PathBuf {
+    let url_parts: Vec<&str> = download_url
+        .split('?')
+        .next()
+        .unwrap_or(download_url)
+        .split('/')
+        .collect();
+
+    // Extract the actual filename from the URL, with better fallback handling
+    let filename = match url_parts.last() {
+        Some(name) if !name.is_empty() => (*name).to_string(),
+        _ => {
+            // If we can't determine a filename from the URL, generate one from the hash of the URL
+            let hash = sha256_hex(download_url.as_bytes());
+            format!("download-{}.zip", &hash[0..8])
+        }
+    };
+
+    cache_dir.join(filename)
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct BlobIndex {
+    pub blobs: HashMap<String, BlobEntry>, // sha512 -> entry
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BlobEntry {
+    pub size: u64,
+    pub last_used: i64, // unix timestamp, used for LRU eviction
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlobCacheStats {
+    pub blobs: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlobGcReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+// Content-addressed store shared by every instance. Files are stored once under
+// their sha512 and hardlinked (or copied) into instances.
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(root: PathBuf) -> Self {
+        BlobStore { root }
+    }
+
+    pub fn blob_path(&self, sha512: &str) -> PathBuf {
+        let sha512 = sha512.to_lowercase();
+        let prefix = sha512.get(0..2).unwrap_or("00").to_string();
+        self.root.join(prefix).join(sha512)
+    }
+
+    pub fn contains(&self, sha512: &str) -> bool {
+        self.blob_path(sha512).is_file()
+    }
+
+    // A file edited in place also changes the blob it is hardlinked to. Drop such a blob
+    // so it is fetched again; returns true if one was removed.
+    pub fn evict_if_corrupt(&self, sha512: &str) -> bool {
+        let blob_path = self.blob_path(sha512);
+        let intact = match sha512_file(&blob_path) {
+            Ok(actual) => actual.eq_ignore_ascii_case(sha512),
+            Err(_) => return false,
+        };
+        if !intact {
+            eprintln!(
+                "⚠️ Blob {} was modified, dropping it",
+                &sha512[..sha512.len().min(12)]
+            );
+            let _ = std::fs::remove_file(&blob_path);
+        }
+        !intact
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join("index.json")
+    }
+
+    // Hold the returned file while reading and rewriting the index. It is an OS file lock,
+    // so the launcher and story-cli sharing a data directory wait for each other too.
+    fn lock_index(&self) -> Result<File, String> {
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create blob directory: {}", e))?;
+        let lock_file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.root.join("index.lock"))
+            .map_err(|e| format!("Failed to open blob index lock: {}", e))?;
+        lock_file
+            .lock()
+            .map_err(|e| format!("Failed to lock blob index: {}", e))?;
+        Ok(lock_file)
+    }
+
+    fn load_index(&self) -> BlobIndex {
+        std::fs::read_to_string(self.index_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_index(&self, index: &BlobIndex) -> Result<(), String> {
+        let index_json = serde_json::to_string(index)
+            .map_err(|e| format!("Failed to serialize blob index: {}", e))?;
+        std::fs::write(self.index_path(), index_json)
+            .map_err(|e| format!("Failed to write blob index: {}", e))
+    }
+
+    fn touch(&self, sha512: &str, size: u64) -> Result<(), String> {
+        let _lock = self.lock_index()?;
+        let mut index = self.load_index();
+        index.blobs.insert(
+            sha512.to_lowercase(),
+            BlobEntry {
+                size,
+                last_used: chrono::Utc::now().timestamp(),
+            },
+        );
+        self.save_index(&index)
+    }
+
+    // Store bytes under their sha512 and return the hash
+    pub fn insert_bytes(&self, bytes: &[u8]) -> Result<String, String> {
+        let temp_path = self.temp_path()?;
+        std::fs::write(&temp_path, bytes).map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            format!("Failed to write blob: {}", e)
+        })?;
+        self.commit(&temp_path, &sha512_hex(bytes), bytes.len() as u64)
+    }
+
+    // Stream a download into the store, hashing it as it is written, so large files never
+    // sit in memory. Content that doesn't match `expected_sha512` is refused.
+    pub(crate) async fn insert_response(
+        &self,
+        mut response: reqwest::Response,
+        expected_sha512: Option<&str>,
+    ) -> Result<String, String> {
+        let temp_path = self.temp_path()?;
+        let mut file =
+            File::create(&temp_path).map_err(|e| format!("Failed to write blob: {}", e))?;
+        let mut hasher = Sha512::new();
+        let mut size: u64 = 0;
+        let written: Result<(), String> = async {
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| format!("Failed to read response: {}", e))?
+            {
+                file.write_all(&chunk)
+                    .map_err(|e| format!("Failed to write blob: {}", e))?;
+                hasher.update(&chunk);
+                size += chunk.len() as u64;
+            }
+            file.flush()
+                .map_err(|e| format!("Failed to write blob: {}", e))
+        }
+        .await;
+        drop(file);
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        let actual = format!("{:x}", hasher.finalize());
+        if let Some(expected) = expected_sha512 {
+            if !actual.eq_ignore_ascii_case(expected) {
+                let _ = std::fs::remove_file(&temp_path);
+                return Err(format!(
+                    "Hash mismatch: expected sha512 {}, got {}",
+                    expected, actual
+                ));
+            }
+        }
+        self.commit(&temp_path, &actual, size)
+    }
+
+    // Content is written to a temp file first so a crash never leaves a truncated blob behind
+    fn temp_path(&self) -> Result<PathBuf, String> {
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create blob directory: {}", e))?;
+        Ok(self
+            .root
+            .join(format!("incoming-{}.tmp", uuid::Uuid::new_v4())))
+    }
+
+    // Move a fully written temp file into place under its sha512
+    fn commit(&self, temp_path: &Path, sha512: &str, size: u64) -> Result<String, String> {
+        let blob_path = self.blob_path(sha512);
+        if blob_path.is_file() {
+            let _ = std::fs::remove_file(temp_path);
+        } else {
+            let parent = blob_path.parent().ok_or("Invalid blob path")?;
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create blob directory: {}", e))?;
+            std::fs::rename(temp_path, &blob_path).map_err(|e| {
+                let _ = std::fs::remove_file(temp_path);
+                format!("Failed to move blob into place: {}", e)
+            })?;
+            eprintln!("🗄️ Stored blob {} ({} bytes)", &sha512[..12], size);
+        }
+
+        self.touch(sha512, size)?;
+        Ok(sha512.to_string())
+    }
+
+    // Like insert_bytes, but refuses content that doesn't match the expected sha512
+    pub fn insert_bytes_checked(
+        &self,
+        bytes: &[u8],
+        expected_sha512: &str,
+    ) -> Result<String, String> {
+        let actual = sha512_hex(bytes);
+        if !actual.eq_ignore_ascii_case(expected_sha512) {
+            return Err(format!(
+                "Hash mismatch: expected sha512 {}, got {}",
+                expected_sha512, actual
+            ));
+        }
+        self.insert_bytes(bytes)
+    }
+
+    // Place a stored blob at `dest`, preferring a hardlink and falling back to a copy.
+    // Returns Ok(false) if the blob isn't in the store.
+    pub fn link_to(&self, sha512: &str, dest: &Path) -> Result<bool, String> {
+        self.place(sha512, dest, true)
+    }
+
+    // Like link_to, but always a copy, for files players are expected to edit
+    pub fn copy_to(&self, sha512: &str, dest: &Path) -> Result<bool, String> {
+        self.place(sha512, dest, false)
+    }
+
+    fn place(&self, sha512: &str, dest: &Path, link: bool) -> Result<bool, String> {
+        let blob_path = self.blob_path(sha512);
+        let size = match std::fs::metadata(&blob_path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => return Ok(false),
+        };
+
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create parent directory {}: {}",
+                    parent.display(),
+                    e
+                )
+            })?;
+        }
+
+        // Never write through an existing file: it may itself be a hardlink to a blob
+        if dest.exists() {
+            std::fs::remove_file(dest)
+                .map_err(|e| format!("Failed to replace {}: {}", dest.display(), e))?;
+        }
+
+        if !link || std::fs::hard_link(&blob_path, dest).is_err() {
+            std::fs::copy(&blob_path, dest)
+                .map_err(|e| format!("Failed to copy blob to {}: {}", dest.display(), e))?;
+        }
+
+        self.touch(sha512, size)?;
+        Ok(true)
+    }
+
+    pub fn stats(&self, max_bytes: u64) -> BlobCacheStats {
+        let index = self.load_index();
+        BlobCacheStats {
+            blobs: index.blobs.len(),
+            total_bytes: index.blobs.values().map(|b| b.size).sum(),
+            max_bytes,
+        }
+    }
+
+    // Evict least recently used blobs until the store fits in `max_bytes`
+    pub fn gc(&self, max_bytes: u64) -> Result<BlobGcReport, String> {
+        let _lock = self.lock_index()?;
+        let mut index = self.load_index();
+
+        // Drop entries whose file was removed behind our back
+        index
+            .blobs
+            .retain(|sha512, _| self.blob_path(sha512).is_file());
+
+        let mut entries: Vec<(String, BlobEntry)> = index
+            .blobs
+            .iter()
+            .map(|(sha512, entry)| (sha512.clone(), entry.clone()))
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.last_used);
+
+        let mut total_bytes: u64 = entries.iter().map(|(_, entry)| entry.size).sum();
+        let mut removed = 0;
+        let mut freed_bytes = 0;
+
+        for (sha512, entry) in entries {
+            if total_bytes <= max_bytes {
+                break;
+            }
+            match std::fs::remove_file(self.blob_path(&sha512)) {
+                Ok(_) => {
+                    index.blobs.remove(&sha512);
+                    total_bytes -= entry.size;
+                    freed_bytes += entry.size;
+                    removed += 1;
+                }
+                Err(e) => eprintln!("Failed to evict blob {}: {}", sha512, e),
+            }
+        }
+
+        self.save_index(&index)?;
+        eprintln!(
+            "🧹 Blob cache GC removed {} blobs ({} bytes freed, {} bytes remaining)",
+            removed, freed_bytes, total_bytes
+        );
+
+        Ok(BlobGcReport {
+            removed,
+            freed_bytes,
+            remaining_bytes: total_bytes,
+        })
+    }
+}
+
+pub fn sha512_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha512::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+// Fetch a file into the blob store, skipping the network when the hash is already stored.
+// Tries each URL in order and returns the sha512 of the stored content.
+pub(crate) async fn fetch_into_blob_store(
+    client: &reqwest::Client,
+    store: &BlobStore,
+    urls: &[String],
+    sha512: Option<&str>,
+    offline: bool,
+) -> Result<String, String> {
+    // Blobs are hardlinked into instances, so a file edited there changes the blob too.
+    // Re-hash a hit before handing it to another install.
+    if let Some(sha512) = sha512 {
+        if store.contains(sha512) && !store.evict_if_corrupt(sha512) {
+            eprintln!("♻️ Blob cache hit for {}", &sha512[..sha512.len().min(12)]);
+            return Ok(sha512.to_lowercase());
+        }
+    }
+
+    if offline {
+        return Err(match sha512 {
+            Some(sha512) => format!("sha512 {} is not in the local blob cache", sha512),
+            None => "no sha512 to look up in the local blob cache".to_string(),
+        });
+    }
+
+    let mut last_error = "No download URLs provided".to_string();
+    for url in urls {
+        let response = match client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Failed to download from {}: {}", url, e);
+                last_error = format!("Failed to download from {}: {}", url, e);
+                continue;
+            }
+        };
+
+        match store.insert_response(response, sha512).await {
+            Ok(hash) => return Ok(hash),
+            Err(e) => {
+                eprintln!("Rejected download from {}: {}", url, e);
+                last_error = format!("{} ({})", e, url);
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Compare a downloaded zip against the sha256/size pinned for it in the manifest
+pub fn check_pinned_zip(
+    actual_sha256: &str,
+    actual_size: u64,
+    expected_sha256: Option<&str>,
+    expected_size: Option<u64>,
+) -> Result<(), String> {
+    if let Some(expected) = expected_size {
+        if expected != actual_size {
+            return Err(format!(
+                "size mismatch: expected {} bytes, got {}",
+                expected, actual_size
+            ));
+        }
+    }
+    if let Some(expected) = expected_sha256 {
+        if !expected.eq_ignore_ascii_case(actual_sha256) {
+            return Err(format!(
+                "sha256 mismatch: expected {}, got {}",
+                expected, actual_sha256
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Local copies of manifests and Modrinth API responses, keyed by URL, so an instance
+// can be rebuilt without network. Also collects everything that couldn't be fetched.
+#[derive(Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    pub offline: bool,
+    missing: Arc<Mutex<Vec<String>>>,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, offline: bool) -> Self {
+        ResponseCache {
+            dir,
+            offline,
+            missing: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", sha256_hex(url.as_bytes())))
+    }
+
+    pub fn load(&self, url: &str) -> Option<String> {
+        std::fs::read_to_string(self.entry_path(url)).ok()
+    }
+
+    pub fn store(&self, url: &str, body: &str) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create response cache directory: {}", e))?;
+        std::fs::write(self.entry_path(url), body)
+            .map_err(|e| format!("Failed to write cached response: {}", e))
+    }
+
+    // Fetch a URL as text. Online, successful responses are cached; offline, only the cache is used.
+    pub async fn get_text(&self, client: &reqwest::Client, url: &str) -> Result<String, String> {
+        if self.offline {
+            eprintln!("📴 Offline mode, reading cached response for {}", url);
+            return self
+                .load(url)
+                .ok_or_else(|| format!("No cached response for {} (offline mode)", url));
+        }
+
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let body = response.text().await.map_err(|e| e.to_string())?;
+
+        if let Err(e) = self.store(url, &body) {
+            eprintln!("Warning: Failed to cache response for {}: {}", url, e);
+        }
+        Ok(body)
+    }
+
+    // Like get_text, but a 404 (or, offline, no cached copy) is Ok(None) rather than an error
+    pub async fn get_optional_text(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<Option<String>, String> {
+        if self.offline {
+            return Ok(self.load(url));
+        }
+
+        let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            let _ = std::fs::remove_file(self.entry_path(url));
+            return Ok(None);
+        }
+        let body = response
+            .error_for_status()
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Err(e) = self.store(url, &body) {
+            eprintln!("Warning: Failed to cache response for {}: {}", url, e);
+        }
+        Ok(Some(body))
+    }
+
+    // Cache key of a POST request: its URL and JSON body
+    pub fn post_key(url: &str, body: &serde_json::Value) -> String {
+        format!("POST {} {}", url, body)
+    }
+
+    // Like get_text, for a POST with a JSON body
+    pub async fn post_text(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        body: &serde_json::Value,
+    ) -> Result<String, String> {
+        let key = Self::post_key(url, body);
+        if self.offline {
+            eprintln!("📴 Offline mode, reading cached response for POST {}", url);
+            return self
+                .load(&key)
+                .ok_or_else(|| format!("No cached response for POST {} (offline mode)", url));
+        }
+
+        let response = client
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let body = response.text().await.map_err(|e| e.to_string())?;
+
+        if let Err(e) = self.store(&key, &body) {
+            eprintln!("Warning: Failed to cache response for POST {}: {}", url, e);
+        }
+        Ok(body)
+    }
+
+    // The sha512 a download URL turned out to have, for sources that publish other hashes.
+    // With it a reinstall, offline included, finds the file in the blob store.
+    pub fn load_sha512(&self, url: &str) -> Option<String> {
+        self.load(&format!("sha512 {}", url))
+    }
+
+    pub fn store_sha512(&self, url: &str, sha512: &str) {
+        if let Err(e) = self.store(&format!("sha512 {}", url), sha512) {
+            eprintln!("Warning: Failed to cache sha512 of {}: {}", url, e);
+        }
+    }
+
+    pub fn record_missing(&self, item: String) {
+        eprintln!("❌ Missing: {}", item);
+        if let Ok(mut missing) = self.missing.lock() {
+            missing.push(item);
+        }
+    }
+
+    pub fn missing(&self) -> Vec<String> {
+        self.missing
+            .lock()
+            .map(|missing| missing.clone())
+            .unwrap_or_default()
+    }
+
+    // In offline mode an install that couldn't find everything locally is an error
+    pub fn ensure_complete(&self) -> Result<(), String> {
+        let missing = self.missing();
+        if self.offline && !missing.is_empty() {
+            return Err(format!(
+                "Offline install incomplete, {} item(s) missing from the local cache:\n - {}",
+                missing.len(),
+                missing.join("\n - ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn sha512_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha512::new();
+    let mut buffer = [0u8; 65536];
+    loop {
+        let n = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Cache validators and size a server reports for a file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteValidators {
+    pub last_modified: String,
+    pub etag: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl RemoteValidators {
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let header = |name: reqwest::header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(str::to_string)
+        };
+        RemoteValidators {
+            last_modified: header(reqwest::header::LAST_MODIFIED).unwrap_or_default(),
+            etag: header(reqwest::header::ETAG),
+            size: header(reqwest::header::CONTENT_LENGTH).and_then(|len| len.parse().ok()),
+        }
+    }
+
+    // Why the remote file differs from the copy we downloaded, if it does.
+    // ETag wins when both sides have one; Last-Modified is the fallback.
+    pub fn changed_since(&self, downloaded: Option<&FileInfo>) -> Option<String> {
+        let info = match downloaded {
+            Some(info) => info,
+            None => return Some("not downloaded yet".to_string()),
+        };
+        if let (Some(remote), Some(local)) = (&self.etag, &info.etag) {
+            return (remote != local).then(|| "ETag changed".to_string());
+        }
+        if !self.last_modified.is_empty() && !info.last_modified.is_empty() {
+            return (self.last_modified != info.last_modified)
+                .then(|| "Last-Modified changed".to_string());
+        }
+        // Nothing to compare, so we can't tell the copy is current
+        Some("no ETag or Last-Modified to compare".to_string())
+    }
+}
+
+// If-None-Match / If-Modified-Since headers for re-requesting a file we downloaded before.
+// Empty when there is nothing to validate against, which makes the request unconditional.
+pub fn conditional_headers(downloaded: Option<&FileInfo>) -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let info = match downloaded {
+        Some(info) => info,
+        None => return headers,
+    };
+    if let Some(value) = info
+        .etag
+        .as_deref()
+        .and_then(|etag| reqwest::header::HeaderValue::from_str(etag).ok())
+    {
+        headers.insert(reqwest::header::IF_NONE_MATCH, value);
+    }
+    if !info.last_modified.is_empty() {
+        if let Ok(value) = reqwest::header::HeaderValue::from_str(&info.last_modified) {
+            headers.insert(reqwest::header::IF_MODIFIED_SINCE, value);
+        }
+    }
+    headers
+}
+
+pub(crate) fn load_file_hash_registry(cache_dir: &Path) -> FileHashRegistry {
+    std::fs::read_to_string(cache_dir.join("hash_registry.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
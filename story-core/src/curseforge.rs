@@ -0,0 +1,495 @@
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::cache::{fetch_into_blob_store, ResponseCache};
+use crate::install::{read_pack_source, LauncherContext};
+use crate::instance::{create_local_instance_config, safe_join};
+use crate::modrinth::{ModrinthIndex, ModrinthIndexFile};
+
+pub const CURSEFORGE_API_BASE: &str = "https://api.curseforge.com";
+
+// CurseForge class ids and the .minecraft folder their files go into
+const CLASS_FOLDERS: &[(u32, &str)] = &[(6, "mods"), (12, "resourcepacks"), (6552, "shaderpacks")];
+
+// CurseForge's hash algorithm id for sha1 (2 is md5)
+const SHA1_ALGO: u32 = 1;
+
+// Where CurseForge pack files are resolved: the official API with a key from the
+// CurseForge console, or any server that speaks the same /v1 endpoints.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CurseForgeSettings {
+    pub api_base: String,
+    // Sent as x-api-key
+    pub api_key: Option<String>,
+    pub timeout_secs: u64,
+}
+
+impl Default for CurseForgeSettings {
+    fn default() -> Self {
+        CurseForgeSettings {
+            api_base: CURSEFORGE_API_BASE.to_string(),
+            api_key: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+// The CurseForge API as configured. Like the Modrinth one, the key only goes to the API;
+// file downloads use a plain client.
+pub struct CurseForgeApi {
+    base: String,
+    client: reqwest::Client,
+}
+
+impl CurseForgeApi {
+    pub fn new(settings: &CurseForgeSettings) -> Result<Self, String> {
+        let base = settings.api_base.trim_end_matches('/').to_string();
+        if reqwest::Url::parse(&base).is_err() {
+            return Err(format!(
+                "Invalid CurseForge API base URL: {}",
+                settings.api_base
+            ));
+        }
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(key) = settings.api_key.as_deref().filter(|k| !k.is_empty()) {
+            let mut value = reqwest::header::HeaderValue::from_str(key)
+                .map_err(|_| "Invalid CurseForge API key".to_string())?;
+            value.set_sensitive(true);
+            headers.insert("x-api-key", value);
+        }
+        let mut client = reqwest::Client::builder()
+            .user_agent(crate::modrinth::DEFAULT_USER_AGENT)
+            .default_headers(headers);
+        if settings.timeout_secs > 0 {
+            client = client.timeout(std::time::Duration::from_secs(settings.timeout_secs));
+        }
+        let client = client
+            .build()
+            .map_err(|e| format!("Failed to create CurseForge API client: {}", e))?;
+
+        Ok(CurseForgeApi { base, client })
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+}
+
+// manifest.json at the root of a CurseForge modpack zip
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CurseForgeManifest {
+    pub minecraft: CurseForgeMinecraft,
+    #[serde(rename = "manifestType")]
+    pub manifest_type: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub files: Vec<CurseForgeManifestFile>,
+    #[serde(default = "default_overrides")]
+    pub overrides: String,
+}
+
+fn default_overrides() -> String {
+    "overrides".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CurseForgeMinecraft {
+    pub version: String,
+    #[serde(rename = "modLoaders", default)]
+    pub mod_loaders: Vec<CurseForgeModLoader>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CurseForgeModLoader {
+    // e.g. "fabric-0.16.10" or "forge-47.2.0"
+    pub id: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CurseForgeManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u64,
+    #[serde(rename = "fileID")]
+    pub file_id: u64,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct CurseForgeData<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct CurseForgeFile {
+    id: u64,
+    #[serde(rename = "modId")]
+    mod_id: u64,
+    #[serde(rename = "fileName")]
+    file_name: String,
+    #[serde(rename = "downloadUrl")]
+    download_url: Option<String>,
+    #[serde(default)]
+    hashes: Vec<CurseForgeHash>,
+    #[serde(rename = "fileLength", default)]
+    file_length: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct CurseForgeHash {
+    value: String,
+    algo: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct CurseForgeMod {
+    id: u64,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "classId", default)]
+    class_id: Option<u32>,
+}
+
+pub fn read_curseforge_manifest(pack_bytes: &[u8]) -> Result<CurseForgeManifest, String> {
+    let mut zip = ZipArchive::new(Cursor::new(pack_bytes))
+        .map_err(|e| format!("Failed to open CurseForge pack as zip: {}", e))?;
+    let mut content = String::new();
+    zip.by_name("manifest.json")
+        .map_err(|_| "CurseForge pack has no manifest.json".to_string())?
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read manifest.json: {}", e))?;
+    let manifest: CurseForgeManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse manifest.json: {}", e))?;
+    if manifest.manifest_type != "minecraftModpack" {
+        return Err(format!(
+            "Unsupported CurseForge manifest type: {}",
+            manifest.manifest_type
+        ));
+    }
+    Ok(manifest)
+}
+
+// The mrpack dependencies block for a CurseForge manifest: the Minecraft version plus
+// the primary mod loader, whose id is "<loader>-<version>"
+pub fn curseforge_dependencies(manifest: &CurseForgeManifest) -> HashMap<String, String> {
+    let mut dependencies = HashMap::new();
+    dependencies.insert("minecraft".to_string(), manifest.minecraft.version.clone());
+    let loader = manifest
+        .minecraft
+        .mod_loaders
+        .iter()
+        .find(|l| l.primary)
+        .or_else(|| manifest.minecraft.mod_loaders.first());
+    if let Some((name, version)) = loader.and_then(|l| l.id.split_once('-')) {
+        let dependency = match name {
+            "fabric" => "fabric-loader",
+            "quilt" => "quilt-loader",
+            "forge" => "forge",
+            "neoforge" => "neoforge",
+            other => {
+                eprintln!("⚠️ Warning: Unknown CurseForge mod loader {}", other);
+                return dependencies;
+            }
+        };
+        dependencies.insert(dependency.to_string(), version.to_string());
+    }
+    dependencies
+}
+
+// Bulk lookups go through the response cache, so an offline reinstall can replay them
+async fn fetch_bulk<T: serde::de::DeserializeOwned>(
+    api: &CurseForgeApi,
+    cache: &ResponseCache,
+    path: &str,
+    body: serde_json::Value,
+) -> Result<Vec<T>, String> {
+    let text = cache
+        .post_text(api.client(), &api.url(path), &body)
+        .await
+        .map_err(|e| format!("Failed to query CurseForge {}: {}", path, e))?;
+    let response: CurseForgeData<T> = serde_json::from_str(&text)
+        .map_err(|e| format!("Failed to parse CurseForge {}: {}", path, e))?;
+    Ok(response.data)
+}
+
+// Download URLs of CurseForge files by id, None where the author turned off third-party
+// downloads. For packwiz metafiles that only name a CurseForge file.
+pub(crate) async fn curseforge_download_urls(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    file_ids: &[u64],
+) -> Result<HashMap<u64, Option<String>>, String> {
+    let api = CurseForgeApi::new(&ctx.settings().curseforge)?;
+    let files: Vec<CurseForgeFile> = fetch_bulk(
+        &api,
+        cache,
+        "/v1/mods/files",
+        serde_json::json!({ "fileIds": file_ids }),
+    )
+    .await?;
+    Ok(files.into_iter().map(|f| (f.id, f.download_url)).collect())
+}
+
+fn sha1_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Extract the pack's overrides folder into .minecraft
+fn extract_curseforge_overrides(
+    pack_bytes: &[u8],
+    overrides: &str,
+    minecraft_dir: &Path,
+) -> Result<usize, String> {
+    let mut zip = ZipArchive::new(Cursor::new(pack_bytes))
+        .map_err(|e| format!("Failed to open CurseForge pack as zip: {}", e))?;
+    let prefix = format!("{}/", overrides.trim_end_matches('/'));
+    let mut extracted = 0;
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).map_err(|e| e.to_string())?;
+        let Some(relative) = file.name().strip_prefix(&prefix).map(String::from) else {
+            continue;
+        };
+        if relative.is_empty() || file.is_dir() {
+            continue;
+        }
+        let Some(output_path) = safe_join(minecraft_dir, &relative) else {
+            eprintln!(
+                "⚠️ Warning: Skipping override outside the instance: {}",
+                relative
+            );
+            continue;
+        };
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Don't write through a hardlink into the blob store
+        if output_path.is_file() {
+            std::fs::remove_file(&output_path).map_err(|e| e.to_string())?;
+        }
+        let mut output_file = File::create(&output_path).map_err(|e| e.to_string())?;
+        std::io::copy(&mut file, &mut output_file).map_err(|e| e.to_string())?;
+        extracted += 1;
+    }
+    Ok(extracted)
+}
+
+// Import a CurseForge modpack zip from a local path or URL. Files are resolved through
+// the configured CurseForge API and land in the blob store like Modrinth downloads; the
+// pack is recorded as a modrinth.index.json so verify and repair work on it too.
+pub async fn install_curseforge_modpack(
+    ctx: &LauncherContext,
+    source: String,
+    instance_base: String,
+    offline: bool,
+) -> Result<String, String> {
+    eprintln!("=== INSTALL_CURSEFORGE_MODPACK START ===");
+    eprintln!("Source: {}", source);
+    eprintln!("Instance base path: {}", instance_base);
+
+    let cache = ctx.response_cache(offline);
+    let ctx = &ctx.begin_install();
+
+    let instance_base_path = Path::new(&instance_base);
+    if !instance_base_path.exists() {
+        let error_msg = format!(
+            "Instance base path does not exist: {}",
+            instance_base_path.display()
+        );
+        eprintln!("ERROR: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    let pack_bytes = read_pack_source(ctx, &cache, &source)
+        .await
+        .map_err(|e| format!("Failed to read CurseForge pack {}: {}", source, e))?;
+    let manifest = read_curseforge_manifest(&pack_bytes)?;
+    eprintln!(
+        "Installing CurseForge modpack: {} v{}",
+        manifest.name, manifest.version
+    );
+
+    let story_path = instance_base_path.join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+    let mrpack_dir = story_path.join("mrpack");
+    for dir in [&minecraft_dir, &mrpack_dir] {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+    }
+
+    let extracted = extract_curseforge_overrides(&pack_bytes, &manifest.overrides, &minecraft_dir)?;
+    eprintln!("Extracted {} override files", extracted);
+
+    let required: Vec<&CurseForgeManifestFile> =
+        manifest.files.iter().filter(|f| f.required).collect();
+    let api = CurseForgeApi::new(&ctx.settings().curseforge)?;
+    let files: Vec<CurseForgeFile> = fetch_bulk(
+        &api,
+        &cache,
+        "/v1/mods/files",
+        serde_json::json!({ "fileIds": required.iter().map(|f| f.file_id).collect::<Vec<_>>() }),
+    )
+    .await?;
+    let projects: Vec<CurseForgeMod> = fetch_bulk(
+        &api,
+        &cache,
+        "/v1/mods",
+        serde_json::json!({ "modIds": required.iter().map(|f| f.project_id).collect::<Vec<_>>() }),
+    )
+    .await?;
+    let files: HashMap<u64, CurseForgeFile> = files.into_iter().map(|f| (f.id, f)).collect();
+    let projects: HashMap<u64, CurseForgeMod> = projects.into_iter().map(|m| (m.id, m)).collect();
+
+    let client = ctx.api().http();
+    let store = ctx.blob_store();
+    let mut index = ModrinthIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: manifest.version.clone(),
+        name: manifest.name.clone(),
+        summary: None,
+        files: Vec::new(),
+        dependencies: curseforge_dependencies(&manifest),
+    };
+
+    for (position, entry) in required.iter().enumerate() {
+        let Some(file) = files.get(&entry.file_id) else {
+            cache.record_missing(format!(
+                "CurseForge file {} (project {}): not found",
+                entry.file_id, entry.project_id
+            ));
+            continue;
+        };
+        let project = projects.get(&file.mod_id);
+        let folder = project
+            .and_then(|p| p.class_id)
+            .and_then(|class| CLASS_FOLDERS.iter().find(|(id, _)| *id == class))
+            .map(|(_, folder)| *folder)
+            .unwrap_or("mods");
+        let relative = format!("{}/{}", folder, file.file_name);
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": ((position as f64 / required.len() as f64) * 100.0) as u32,
+                "current": position + 1,
+                "total": required.len(),
+                "filename": format!("Downloading mod: {}", relative),
+                "stage": "mods"
+            }),
+        );
+
+        // Authors can turn off third-party distribution, in which case there is no URL
+        let Some(download_url) = file.download_url.clone() else {
+            cache.record_missing(format!(
+                "{}: {} does not allow third-party downloads, download it manually from CurseForge",
+                relative,
+                project.map(|p| p.name.as_str()).unwrap_or(&file.file_name)
+            ));
+            continue;
+        };
+        let Some(dest) = safe_join(&minecraft_dir, &relative) else {
+            eprintln!(
+                "⚠️ Warning: Skipping file outside the instance: {}",
+                relative
+            );
+            continue;
+        };
+        let sha1 = file
+            .hashes
+            .iter()
+            .find(|h| h.algo == SHA1_ALGO)
+            .map(|h| h.value.to_lowercase());
+
+        let sha512 = match fetch_into_blob_store(
+            client,
+            &store,
+            std::slice::from_ref(&download_url),
+            cache.load_sha512(&download_url).as_deref(),
+            cache.offline,
+        )
+        .await
+        {
+            Ok(sha512) => sha512,
+            Err(e) => {
+                eprintln!("Warning: Failed to download {}: {}", relative, e);
+                cache.record_missing(format!("{}: {}", relative, e));
+                continue;
+            }
+        };
+        if let Some(expected) = &sha1 {
+            let actual = sha1_file(&store.blob_path(&sha512))?;
+            if &actual != expected {
+                cache.record_missing(format!(
+                    "{}: sha1 mismatch (expected {}, got {})",
+                    relative, expected, actual
+                ));
+                continue;
+            }
+        }
+        // CurseForge only publishes sha1 and md5, so remember the sha512 for reinstalls
+        cache.store_sha512(&download_url, &sha512);
+        store
+            .link_to(&sha512, &dest)
+            .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+        ctx.session().track_downloaded_file(&file.file_name);
+
+        let mut hashes = HashMap::from([("sha512".to_string(), sha512)]);
+        if let Some(sha1) = sha1 {
+            hashes.insert("sha1".to_string(), sha1);
+        }
+        index.files.push(ModrinthIndexFile {
+            path: relative,
+            hashes,
+            downloads: vec![download_url],
+            env: None,
+            file_size: file.file_length,
+        });
+    }
+
+    let index_json = serde_json::to_string_pretty(&index)
+        .map_err(|e| format!("Failed to serialize modrinth.index.json: {}", e))?;
+    std::fs::write(mrpack_dir.join("modrinth.index.json"), index_json)
+        .map_err(|e| format!("Failed to write modrinth.index.json: {}", e))?;
+    create_local_instance_config(&story_path, &index)?;
+    cache.ensure_complete()?;
+
+    let result = format!(
+        "✅ Successfully installed CurseForge modpack: {} v{}",
+        manifest.name, manifest.version
+    );
+    let missing = cache.missing();
+    let result = if missing.is_empty() {
+        result
+    } else {
+        format!(
+            "{}\n⚠️ {} item(s) could not be downloaded:\n - {}",
+            result,
+            missing.len(),
+            missing.join("\n - ")
+        )
+    };
+    eprintln!("=== INSTALL_CURSEFORGE_MODPACK COMPLETE ===");
+    Ok(result)
+}
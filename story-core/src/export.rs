@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::cache::sha512_file;
+use crate::install::LauncherContext;
+use crate::modrinth::{fetch_versions_by_hash, ModrinthIndex, ModrinthIndexFile};
+
+// Folders and files in .minecraft that belong to the player or the game, not the pack
+const EXPORT_EXCLUDED: &[&str] = &[
+    "saves",
+    "logs",
+    "crash-reports",
+    "screenshots",
+    "backups",
+    ".fabric",
+    ".quilt",
+    ".cache",
+    ".installed_hash",
+    "usercache.json",
+    "usernamecache.json",
+    "servers.dat",
+    "servers.dat_old",
+];
+
+// Only these can be Modrinth project files; everything else goes straight to overrides/
+const LOOKUP_EXTENSIONS: &[&str] = &["jar", "zip"];
+
+// mmc-pack.json component uids and the mrpack dependency each one becomes
+const PACK_COMPONENTS: &[(&str, &str)] = &[
+    ("net.minecraft", "minecraft"),
+    ("net.fabricmc.fabric-loader", "fabric-loader"),
+    ("org.quiltmc.quilt-loader", "quilt-loader"),
+    ("net.minecraftforge", "forge"),
+    ("net.neoforged", "neoforge"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MrpackExportReport {
+    pub output_path: String,
+    pub name: String,
+    pub version_id: String,
+    pub dependencies: HashMap<String, String>,
+    // Files referenced by their Modrinth download URL
+    pub modrinth_files: usize,
+    // Files packed into overrides/
+    pub override_files: usize,
+}
+
+// The mrpack dependencies block from an instance's mmc-pack.json
+pub fn pack_dependencies(story_path: &Path) -> Result<HashMap<String, String>, String> {
+    let mmc_pack_path = story_path.join("mmc-pack.json");
+    let content = std::fs::read_to_string(&mmc_pack_path)
+        .map_err(|e| format!("Failed to read {}: {}", mmc_pack_path.display(), e))?;
+    let mmc_pack: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse mmc-pack.json: {}", e))?;
+
+    let mut dependencies = HashMap::new();
+    for component in mmc_pack["components"].as_array().into_iter().flatten() {
+        let uid = component["uid"].as_str().unwrap_or_default();
+        let Some((_, dependency)) = PACK_COMPONENTS.iter().find(|(known, _)| *known == uid) else {
+            continue;
+        };
+        if let Some(version) = component["version"].as_str() {
+            dependencies.insert(dependency.to_string(), version.to_string());
+        }
+    }
+    if !dependencies.contains_key("minecraft") {
+        return Err("mmc-pack.json has no Minecraft version".to_string());
+    }
+    Ok(dependencies)
+}
+
+// Every file under `dir` that belongs in an export, as (relative path, absolute path)
+fn collect_export_files(
+    base: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let relative = path
+            .strip_prefix(base)
+            .map_err(|e| e.to_string())?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        if EXPORT_EXCLUDED.contains(&relative.as_str()) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_export_files(base, &path, files)?;
+        } else if path.is_file() {
+            files.push((relative, path));
+        }
+    }
+    Ok(())
+}
+
+// Export an installed instance as an .mrpack. Files Modrinth knows by their sha512 are
+// listed in modrinth.index.json with their download URL; configs, in-house jars and
+// everything else is packed into overrides/. Name and version default to the ones the
+// instance was installed from.
+pub async fn export_mrpack(
+    ctx: &LauncherContext,
+    instance_base: &str,
+    output_path: &Path,
+    version_id: Option<String>,
+) -> Result<MrpackExportReport, String> {
+    eprintln!("=== EXPORT_MRPACK START ===");
+    let story_path = Path::new(instance_base).join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+    if !minecraft_dir.is_dir() {
+        return Err(format!(
+            "No installed instance at {}",
+            minecraft_dir.display()
+        ));
+    }
+
+    let dependencies = pack_dependencies(&story_path)?;
+    let installed: serde_json::Value =
+        std::fs::read_to_string(story_path.join(".current_version.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+    let name = installed["instance_name"]
+        .as_str()
+        .unwrap_or("Story")
+        .to_string();
+    let version_id = version_id
+        .or_else(|| installed["instance_version"].as_str().map(String::from))
+        .unwrap_or_else(|| "1.0.0".to_string());
+
+    let mut files = Vec::new();
+    collect_export_files(&minecraft_dir, &minecraft_dir, &mut files)?;
+    files.sort();
+    eprintln!(
+        "Exporting {} files from {}",
+        files.len(),
+        minecraft_dir.display()
+    );
+
+    // Ask Modrinth which of the jars and zips it hosts
+    let mut hashes: HashMap<String, String> = HashMap::new();
+    for (relative, path) in &files {
+        let lookup = Path::new(relative)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| LOOKUP_EXTENSIONS.contains(&e));
+        if lookup {
+            hashes.insert(relative.clone(), sha512_file(path)?);
+        }
+    }
+    let cache = ctx.response_cache(false);
+    let lookups: Vec<String> = hashes.values().cloned().collect();
+    let versions = fetch_versions_by_hash(ctx.api(), &cache, &lookups, None).await?;
+
+    let mut index = ModrinthIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: version_id.clone(),
+        name: name.clone(),
+        summary: None,
+        files: Vec::new(),
+        dependencies: dependencies.clone(),
+    };
+    let mut overrides = Vec::new();
+    for (relative, path) in &files {
+        let modrinth_file = hashes.get(relative).and_then(|sha512| {
+            versions.get(sha512)?.files.iter().find(|f| {
+                f.hashes
+                    .get("sha512")
+                    .is_some_and(|h| h.eq_ignore_ascii_case(sha512))
+            })
+        });
+        match modrinth_file {
+            Some(file) if file.hashes.contains_key("sha1") => {
+                eprintln!("Modrinth file: {}", relative);
+                index.files.push(ModrinthIndexFile {
+                    path: relative.clone(),
+                    hashes: file.hashes.clone(),
+                    downloads: vec![file.url.clone()],
+                    env: None,
+                    file_size: Some(file.size),
+                });
+            }
+            _ => overrides.push((relative, path)),
+        }
+    }
+
+    // Write the pack next to its final name, then move it in place
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let partial_path = output_path.with_extension("mrpack.part");
+    let file = File::create(&partial_path)
+        .map_err(|e| format!("Failed to create {}: {}", partial_path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let index_json = serde_json::to_string_pretty(&index)
+        .map_err(|e| format!("Failed to serialize modrinth.index.json: {}", e))?;
+    zip.start_file("modrinth.index.json", options)
+        .map_err(|e| format!("Failed to write modrinth.index.json: {}", e))?;
+    zip.write_all(index_json.as_bytes())
+        .map_err(|e| format!("Failed to write modrinth.index.json: {}", e))?;
+    for (relative, path) in &overrides {
+        let mut source =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        zip.start_file(format!("overrides/{}", relative), options)
+            .map_err(|e| format!("Failed to add {}: {}", relative, e))?;
+        std::io::copy(&mut source, &mut zip)
+            .map_err(|e| format!("Failed to add {}: {}", relative, e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to finish {}: {}", partial_path.display(), e))?;
+    std::fs::rename(&partial_path, output_path)
+        .map_err(|e| format!("Failed to write {}: {}", output_path.display(), e))?;
+
+    eprintln!(
+        "✅ Exported {} v{}: {} Modrinth files, {} override files",
+        name,
+        version_id,
+        index.files.len(),
+        overrides.len()
+    );
+    Ok(MrpackExportReport {
+        output_path: output_path.to_string_lossy().to_string(),
+        name,
+        version_id,
+        dependencies,
+        modrinth_files: index.files.len(),
+        override_files: overrides.len(),
+    })
+}
@@ -0,0 +1,2332 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zip::ZipArchive;
+
+use crate::cache::{
+    cached_zip_path, check_pinned_zip, conditional_headers, fetch_into_blob_store, gc_blob_store,
+    load_file_hash_registry, sha256_file, sha256_hex, sha512_file, BlobStore, FileHashRegistry,
+    FileInfo, RemoteValidators, ResponseCache,
+};
+use crate::instance::{
+    cleanup_extra_jars, create_instance_config, create_local_instance_config, load_managed_files,
+    record_managed_files, safe_join, zip_entry_hashes, ManagedFile, ManagedSource,
+};
+use crate::lockfile::{
+    load_or_resolve_lock, loader_fallback_report, minecraft_version_fallback_report, LockedFile,
+    LockedMod, ModResolver,
+};
+use crate::manifest::{
+    diff_manifest, parse_manifest, tracked_extra_mod, verify_manifest_trust, ChangeKind, ExtraMod,
+    ManifestChange, ManifestDiff, ResolvedMod, StoryManifest, VersionPreference,
+};
+use crate::modrinth::{
+    default_loader_fallbacks, fetch_versions_by_hash, is_latest_install, primary_file,
+    read_mrpack_index, resolve_mod_version, select_mod_file, ModrinthApi, ModrinthIndex,
+    ModrinthVersionResponse,
+};
+use crate::packwiz::{
+    install_packwiz_files, install_packwiz_instance, is_packwiz_url, packwiz_manifest, PackwizSide,
+};
+use crate::settings::{load_launcher_settings, LauncherSettings};
+
+// What one install placed. Each install gets its own (LauncherContext::begin_install),
+// so installs running side by side don't see or clear each other's records.
+#[derive(Default)]
+pub(crate) struct InstallSession {
+    // Jar filenames downloaded this install, including dependencies
+    downloaded_files: Mutex<HashSet<String>>,
+    // Extra mods placed this install: absolute path -> (sha512, source)
+    managed_mods: Mutex<HashMap<PathBuf, (String, ManagedSource)>>,
+}
+
+impl InstallSession {
+    pub(crate) fn track_downloaded_file(&self, filename: &str) {
+        if let Ok(mut files) = self.downloaded_files.lock() {
+            files.insert(filename.to_string());
+            eprintln!("📝 Tracked downloaded file: {}", filename);
+        }
+    }
+
+    // Remember where an extra mod came from, for verify/repair
+    fn track_managed_mod(&self, path: &Path, sha512: &str, source: ManagedSource) {
+        if let Ok(mut mods) = self.managed_mods.lock() {
+            mods.insert(path.to_path_buf(), (sha512.to_string(), source));
+        }
+    }
+
+    pub(crate) fn downloaded_files(&self) -> HashSet<String> {
+        self.downloaded_files
+            .lock()
+            .map(|files| files.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn managed_mods(&self) -> HashMap<PathBuf, (String, ManagedSource)> {
+        self.managed_mods
+            .lock()
+            .map(|mods| mods.clone())
+            .unwrap_or_default()
+    }
+}
+
+// Legacy manifest structure for old zip-based downloads
+#[derive(Serialize, Deserialize)]
+pub struct LegacyManifestFile {
+    pub delete: Option<Vec<String>>,
+    pub notes: Option<String>,
+    pub required_files: Option<Vec<String>>,
+}
+
+// Add this function to verify extraction integrity based on manifest requirements
+pub fn verify_extraction_integrity(
+    extract_path: &Path,
+    manifest_data: &Option<LegacyManifestFile>,
+) -> Result<bool, String> {
+    eprintln!("Verifying extraction integrity");
+
+    // Check if we have manifest requirements to verify
+    if let Some(manifest) = manifest_data {
+        if let Some(required_files) = &manifest.required_files {
+            eprintln!(
+                "Checking {} required files from manifest",
+                required_files.len()
+            );
+
+            for (index, relative_path) in required_files.iter().enumerate() {
+                let full_path = extract_path.join(relative_path);
+                eprintln!(
+                    "Checking required file {}/{}: {}",
+                    index + 1,
+                    required_files.len(),
+                    full_path.display()
+                );
+
+                if !full_path.exists() {
+                    eprintln!("Missing required file: {}", full_path.display());
+                    return Ok(false);
+                }
+            }
+            eprintln!("All required files verified successfully");
+        } else {
+            eprintln!("No required files specified in manifest");
+        }
+    } else {
+        eprintln!("No manifest data available for verification");
+    }
+
+    // If we get here, all required files are present (or none were specified)
+    Ok(true)
+}
+
+// Whether the zip at `download_url` changed since it was last downloaded, judged by a
+// conditional HEAD request against the ETag / Last-Modified saved at download time
+pub async fn check_download_updates(
+    ctx: &LauncherContext,
+    download_url: &str,
+) -> Result<String, String> {
+    let registry = load_file_hash_registry(&ctx.zip_cache_dir());
+    let file_info = registry.files.get(download_url);
+
+    // Make a conditional HEAD request so the server can answer 304 when nothing changed
+    let resp = ctx
+        .api()
+        .http()
+        .head(download_url)
+        .headers(conditional_headers(file_info))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let remote = RemoteValidators::from_headers(resp.headers());
+
+    // Compare with the ETag / Last-Modified date saved when the file was downloaded
+    let update_available = if file_info.is_none() {
+        "Yes, file has not been downloaded yet"
+    } else if resp.status() == reqwest::StatusCode::NOT_MODIFIED
+        || remote.changed_since(file_info).is_none()
+    {
+        "No, you have the latest version"
+    } else {
+        "Yes, a new version is available"
+    };
+
+    // Return info including the last-modified date
+    Ok(format!(
+        "{} (Last modified: {})",
+        update_available, remote.last_modified
+    ))
+}
+
+struct CachedZip {
+    path: PathBuf,
+    sha256: String,
+    // False when the cached copy was still current
+    downloaded: bool,
+}
+
+// Bring the cached copy of a zip up to date and return its path and sha256. Offline
+// only the cached copy is used. A download that doesn't match the pins is discarded.
+fn fetch_zip_to_cache(
+    ctx: &LauncherContext,
+    download_url: String,
+    force_download: bool,
+    offline: bool,
+    expected_sha256: Option<String>,
+    expected_size: Option<u64>,
+) -> Result<CachedZip, String> {
+    // Create cache directory inside the app's data directory
+    eprintln!("App data directory: {}", ctx.data_dir().display());
+    let cache_dir = ctx.zip_cache_dir();
+    eprintln!("Cache directory: {}", cache_dir.display());
+
+    match std::fs::create_dir_all(&cache_dir) {
+        Ok(_) => eprintln!("Cache directory created/verified"),
+        Err(e) => {
+            eprintln!(
+                "Failed to create cache directory {}: {}",
+                cache_dir.display(),
+                e
+            );
+            return Err(e.to_string());
+        }
+    }
+
+    // Path to the hash registry file
+    let hash_registry_path = cache_dir.join("hash_registry.json");
+    eprintln!("Hash registry path: {}", hash_registry_path.display());
+
+    // Load existing hash registry or create a new one
+    let mut registry: FileHashRegistry = if hash_registry_path.exists() {
+        eprintln!("Reading existing hash registry");
+        let registry_content = match std::fs::read_to_string(&hash_registry_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to read hash registry: {}", e);
+                return Err(format!("Failed to read hash registry: {}", e));
+            }
+        };
+
+        match serde_json::from_str(&registry_content) {
+            Ok(reg) => reg,
+            Err(e) => {
+                eprintln!("Failed to parse hash registry, using default: {}", e);
+                FileHashRegistry::default()
+            }
+        }
+    } else {
+        eprintln!("No existing hash registry found, creating new one");
+        FileHashRegistry::default()
+    };
+
+    // Generate filename from URL
+    let cached_file_path = cached_zip_path(&cache_dir, &download_url);
+    eprintln!("Cached file path: {}", cached_file_path.display());
+
+    // Check if we need to download based on existence, hash, and last-modified
+    let file_info = registry.files.get(&download_url);
+    let file_exists = cached_file_path.exists()
+        && std::fs::metadata(&cached_file_path)
+            .map(|m| m.len() > 0)
+            .unwrap_or(false);
+
+    eprintln!(
+        "File exists: {}, Previous info exists: {}",
+        file_exists,
+        file_info.is_some()
+    );
+
+    // A cached zip that doesn't match the pinned hash/size (corrupted, or the pin
+    // changed) is never reused
+    let pinned = expected_sha256.is_some() || expected_size.is_some();
+    let cached_matches_pin = !pinned
+        || (file_exists
+            && sha256_file(&cached_file_path).is_ok_and(|hash| {
+                let size = std::fs::metadata(&cached_file_path)
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                check_pinned_zip(&hash, size, expected_sha256.as_deref(), expected_size).is_ok()
+            }));
+    eprintln!("Cached file matches pin: {}", cached_matches_pin);
+
+    // Offline, the cached zip is the only option. Online we always ask the server, with a
+    // conditional GET (If-None-Match / If-Modified-Since) when we have a cached copy;
+    // 304 Not Modified means the cached zip is still current and nothing is transferred.
+    let response = if offline {
+        if !file_exists {
+            eprintln!("Offline mode and no cached copy of {}", download_url);
+            return Err(format!(
+                "{} is not in the local cache (offline mode)",
+                download_url
+            ));
+        }
+        if !cached_matches_pin {
+            return Err(format!(
+                "Cached copy of {} does not match the pinned sha256/size (offline mode)",
+                download_url
+            ));
+        }
+        None
+    } else {
+        let headers = if force_download || !file_exists || !cached_matches_pin {
+            reqwest::header::HeaderMap::new()
+        } else {
+            conditional_headers(file_info)
+        };
+        eprintln!(
+            "Requesting {} (conditional: {})",
+            download_url,
+            !headers.is_empty()
+        );
+        let resp = reqwest::blocking::Client::new()
+            .get(&download_url)
+            .headers(headers)
+            .send()
+            .map_err(|e| {
+                eprintln!("Failed to start download: {}", e);
+                e.to_string()
+            })?;
+
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            eprintln!("Server returned 304 Not Modified, using cached file");
+            None
+        } else {
+            Some(resp.error_for_status().map_err(|e| e.to_string())?)
+        }
+    };
+
+    let download_needed = response.is_some();
+    eprintln!("Download needed: {}", download_needed);
+    let file_hash: String;
+
+    if let Some(mut resp) = response {
+        // Download the file to cache
+        eprintln!("Starting download to cache");
+        let validators = RemoteValidators::from_headers(resp.headers());
+        eprintln!(
+            "Got Last-Modified header: {} (ETag: {:?})",
+            validators.last_modified, validators.etag
+        );
+
+        let total_size = match resp.content_length() {
+            Some(size) => size,
+            None => {
+                eprintln!("Couldn't get content length");
+                return Err("Couldn't get content length".to_string());
+            }
+        };
+        eprintln!("Download size: {} bytes", total_size);
+
+        // Create file and prepare for download
+        let mut file = match File::create(&cached_file_path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Failed to create cache file: {}", e);
+                return Err(e.to_string());
+            }
+        };
+
+        // Calculate hash while downloading
+        let mut hasher = Sha256::new();
+        let mut downloaded = 0u64;
+        let mut buffer = [0u8; 8192];
+        let mut last_update = std::time::Instant::now();
+        let update_frequency = std::time::Duration::from_millis(100);
+
+        // Download in chunks and report progress
+        while let Ok(n) = resp.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+
+            // Update hash calculation
+            hasher.update(&buffer[..n]);
+
+            // Write chunk to file
+            file.write_all(&buffer[..n]).map_err(|e| e.to_string())?;
+
+            // Update progress
+            downloaded += n as u64;
+
+            // Throttle progress updates to avoid overwhelming the UI
+            if last_update.elapsed() >= update_frequency {
+                let pct = (downloaded as f64 / total_size as f64) * 100.0;
+                ctx.emit(
+                    "download_progress",
+                    serde_json::json!({
+                        "percent": pct as u32,
+                        "downloaded": downloaded,
+                        "total": total_size
+                    }),
+                );
+                last_update = std::time::Instant::now();
+            }
+        }
+
+        // Final progress update
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": 100,
+                "downloaded": downloaded,
+                "total": total_size
+            }),
+        );
+
+        // Finalize file and hash
+        file.flush().map_err(|e| e.to_string())?;
+        file_hash = format!("{:x}", hasher.finalize());
+
+        // Throw away a zip that doesn't match the manifest before anything is extracted
+        if let Err(e) = check_pinned_zip(
+            &file_hash,
+            downloaded,
+            expected_sha256.as_deref(),
+            expected_size,
+        ) {
+            drop(file);
+            let _ = std::fs::remove_file(&cached_file_path);
+            eprintln!("❌ Rejected {}: {}", download_url, e);
+            return Err(format!("Rejected {}: {}", download_url, e));
+        }
+
+        // Update registry with new hash and the cache validators
+        registry.files.insert(
+            download_url.clone(),
+            FileInfo {
+                hash: file_hash.clone(),
+                last_modified: validators.last_modified,
+                etag: validators.etag,
+            },
+        );
+
+        // Save updated registry
+        let registry_json = serde_json::to_string(&registry)
+            .map_err(|e| format!("Failed to serialize registry: {}", e))?;
+        std::fs::write(&hash_registry_path, registry_json)
+            .map_err(|e| format!("Failed to write hash registry: {}", e))?;
+    } else if let Some(file_info) = registry.files.get(&download_url) {
+        // Use cached file
+        eprintln!("Using cached file with hash {}", file_info.hash);
+        file_hash = file_info.hash.clone();
+
+        // Report 100% progress for existing file
+        let size = std::fs::metadata(&cached_file_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        eprintln!("Cached file size: {} bytes", size);
+    } else {
+        // Cached zip without a registry entry (only reachable offline), hash it directly
+        let bytes = std::fs::read(&cached_file_path).map_err(|e| e.to_string())?;
+        file_hash = sha256_hex(&bytes);
+        eprintln!("Hashed cached file without registry entry: {}", file_hash);
+    }
+
+    Ok(CachedZip {
+        path: cached_file_path,
+        sha256: file_hash,
+        downloaded: download_needed,
+    })
+}
+
+// Download a zip into the zip cache without extracting anything
+pub(crate) async fn download_zip_to_cache(
+    ctx: &LauncherContext,
+    download_url: String,
+    offline: bool,
+    expected_sha256: Option<String>,
+) -> Result<PathBuf, String> {
+    let ctx = ctx.clone();
+    tokio::task::spawn_blocking(move || {
+        fetch_zip_to_cache(&ctx, download_url, false, offline, expected_sha256, None)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+    .map(|cached| cached.path)
+}
+
+pub async fn fetch_and_extract_zip(
+    ctx: &LauncherContext,
+    download_url: String,
+    extract_path: String,
+    force_download: bool,
+    offline: bool,
+    expected_sha256: Option<String>,
+    expected_size: Option<u64>,
+) -> Result<String, String> {
+    eprintln!(
+        "Starting fetch_and_extract_zip with params: url={}, path={}, force={}, offline={}, sha256={:?}, size={:?}",
+        download_url, extract_path, force_download, offline, expected_sha256, expected_size
+    );
+
+    // Clone values that need to be moved into the task
+    let ctx = ctx.clone();
+
+    // Use tokio's spawn_blocking for file operations that can't be async
+    let result = tokio::task::spawn_blocking(move || {
+        let CachedZip {
+            path: cached_file_path,
+            sha256: file_hash,
+            downloaded: download_needed,
+        } = fetch_zip_to_cache(
+            &ctx,
+            download_url,
+            force_download,
+            offline,
+            expected_sha256,
+            expected_size,
+        )?;
+
+        // Now extract from the cached file
+        eprintln!(
+            "Opening cached file for extraction: {}",
+            cached_file_path.display()
+        );
+        let file = match File::open(&cached_file_path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Failed to open cached file: {}", e);
+                return Err(e.to_string());
+            }
+        };
+
+        eprintln!("Ensuring extract path exists: {}", extract_path);
+        match std::fs::create_dir_all(&extract_path) {
+            Ok(_) => eprintln!("Created extract directory"),
+            Err(e) => {
+                eprintln!("Failed to create extract directory: {}", e);
+                return Err(format!("Failed to create extract directory: {}", e));
+            }
+        }
+
+        // Now try to get the canonical path
+        eprintln!("Canonicalizing extract path: {}", extract_path);
+        let extract_path = match dunce::canonicalize(&extract_path) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Failed to canonicalize extract path: {}", e);
+                // Use the original path as fallback if canonicalization fails
+                eprintln!("Using original path instead");
+                PathBuf::from(&extract_path)
+            }
+        };
+        eprintln!("Final extract path: {}", extract_path.display());
+
+        // Create a hash file at the extract location to track what version is installed
+        let extract_hash_path = extract_path.join(".installed_hash");
+        eprintln!("Hash marker path: {}", extract_hash_path.display()); // Initialize manifest_data earlier in the code flow
+        let mut manifest_data: Option<LegacyManifestFile> = None;
+
+        // Try to find and parse the manifest file from the zip before extraction
+        eprintln!("Looking for manifest.json in zip for verification");
+        let file_for_manifest = match File::open(&cached_file_path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Failed to open cached file for manifest check: {}", e);
+                return Err(e.to_string());
+            }
+        };
+
+        // Try to read the manifest to use for verification
+        if let Ok(mut zip) = ZipArchive::new(file_for_manifest) {
+            if let Ok(mut manifest_file) = zip.by_name("manifest.json") {
+                eprintln!("Found manifest.json for verification, reading content");
+                let mut manifest_content = String::new();
+                if manifest_file.read_to_string(&mut manifest_content).is_ok() {
+                    match serde_json::from_str::<LegacyManifestFile>(&manifest_content) {
+                        Ok(manifest) => {
+                            eprintln!("Successfully parsed manifest.json for verification");
+                            manifest_data = Some(manifest);
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to parse manifest.json for verification: {}", e)
+                        }
+                    }
+                } else {
+                    eprintln!("Failed to read manifest.json content for verification");
+                }
+            } else {
+                eprintln!("No manifest.json found for verification");
+            }
+        } else {
+            eprintln!("Failed to open zip for manifest verification");
+        }
+
+        let current_hash = if extract_hash_path.exists() {
+            match std::fs::read_to_string(&extract_hash_path) {
+                Ok(hash) => {
+                    eprintln!("Found existing installation hash: {}", hash);
+                    hash
+                }
+                Err(e) => {
+                    eprintln!("Failed to read installation hash: {}", e);
+                    String::new()
+                }
+            }
+        } else {
+            eprintln!("No existing installation hash found");
+            String::new()
+        };
+
+        // Check if extraction integrity is maintained - we need to verify files are present
+        // even if the hash hasn't changed
+        let files_verified = if !current_hash.is_empty() && current_hash == file_hash {
+            eprintln!("Hash matches, checking file integrity...");
+            match verify_extraction_integrity(&extract_path, &manifest_data) {
+                Ok(true) => {
+                    eprintln!("File integrity verified successfully");
+                    true
+                }
+                Ok(false) => {
+                    eprintln!("File integrity check failed - some required files are missing");
+                    false
+                }
+                Err(e) => {
+                    eprintln!("Error during file verification: {}", e);
+                    false
+                }
+            }
+        } else {
+            eprintln!("Hash mismatch or no previous hash, skipping integrity check");
+            false
+        };
+
+        // Need extraction if:
+        // 1. Forced download is enabled
+        // 2. Hash is different from current
+        // 3. Files failed verification check
+        let need_extraction = force_download || current_hash != file_hash || !files_verified;
+        eprintln!("Extraction needed: {}", need_extraction);
+
+        let mut notes_text = String::new();
+
+        if need_extraction {
+            // Extract files
+            eprintln!("Creating ZipArchive from file");
+            let mut zip = match ZipArchive::new(file) {
+                Ok(zip) => zip,
+                Err(e) => {
+                    eprintln!("Failed to open zip archive: {}", e);
+                    return Err(e.to_string());
+                }
+            };
+
+            let total_files = zip.len();
+            eprintln!("Zip archive contains {} files", total_files); // Check for manifest.json again, but no need to re-initialize
+            eprintln!("Looking for manifest.json in zip");
+
+            // Only re-read manifest if we couldn't read it earlier
+            if manifest_data.is_none() {
+                // Try to find and parse the manifest file
+                match zip.by_name("manifest.json") {
+                    Ok(mut manifest_file) => {
+                        eprintln!("Found manifest.json, reading content");
+                        let mut manifest_content = String::new();
+                        if manifest_file.read_to_string(&mut manifest_content).is_ok() {
+                            match serde_json::from_str::<LegacyManifestFile>(&manifest_content) {
+                                Ok(manifest) => {
+                                    eprintln!("Successfully parsed manifest.json");
+                                    manifest_data = Some(manifest);
+                                }
+                                Err(e) => eprintln!("Failed to parse manifest.json: {}", e),
+                            }
+                        } else {
+                            eprintln!("Failed to read manifest.json content");
+                        }
+                    }
+                    Err(e) => eprintln!("No manifest.json found: {}", e),
+                }
+            } else {
+                eprintln!("Using manifest data from prior verification step");
+            }
+
+            // Process deletion requests from manifest
+            if let Some(manifest) = &manifest_data {
+                if let Some(delete_list) = &manifest.delete {
+                    eprintln!("Processing {} deletion requests", delete_list.len());
+
+                    for (index, file_path) in delete_list.iter().enumerate() {
+                        let full_path = extract_path.join(file_path);
+                        eprintln!(
+                            "Deletion {}/{}: {}",
+                            index + 1,
+                            delete_list.len(),
+                            full_path.display()
+                        );
+
+                        // Security check - prevent path traversal
+                        if !full_path.starts_with(&extract_path) {
+                            eprintln!(
+                                "Security warning: Attempted deletion outside extract path: {}",
+                                file_path
+                            );
+                            continue;
+                        }
+
+                        // Delete file if it exists
+                        if full_path.exists() {
+                            eprintln!("File exists, deleting");
+                            if full_path.is_dir() {
+                                match fs::remove_dir_all(&full_path) {
+                                    Ok(_) => eprintln!("Deleted directory"),
+                                    Err(e) => eprintln!("Failed to delete directory: {}", e),
+                                }
+                            } else {
+                                match fs::remove_file(&full_path) {
+                                    Ok(_) => eprintln!("Deleted file"),
+                                    Err(e) => eprintln!("Failed to delete file: {}", e),
+                                }
+                            }
+                        } else {
+                            eprintln!("File doesn't exist, skipping deletion");
+                        }
+                    }
+                }
+            }
+
+            // Extract all files from the zip archive
+            let total_zip_files = zip.len();
+            eprintln!("Starting extraction of {} files", total_zip_files);
+            let mut last_progress_update = std::time::Instant::now();
+            let update_frequency = std::time::Duration::from_millis(100);
+
+            for i in 0..total_zip_files {
+                let mut file = match zip.by_index(i) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        eprintln!("Failed to get file at index {}: {}", i, e);
+                        return Err(e.to_string());
+                    }
+                };
+
+                let file_name = file.name().to_string();
+                eprintln!("Extracting {}/{}: {}", i + 1, total_zip_files, file_name);
+
+                // Skip manifest.json if it exists
+                if file_name == "manifest.json" {
+                    eprintln!("Skipping manifest.json");
+                    continue;
+                }
+
+                let file_path = Path::new(file.name());
+
+                // Security checks
+                if file_path
+                    .components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir))
+                {
+                    eprintln!("Security error: zip contains directory traversal pattern");
+                    return Err(
+                        "Invalid zip file: contains directory traversal patterns".to_string()
+                    );
+                }
+
+                // Report extraction progress if it's time
+                if last_progress_update.elapsed() >= update_frequency {
+                    ctx.emit(
+                        "extraction_progress",
+                        serde_json::json!({
+                            "percent": ((i + 1) as f64 / total_zip_files as f64 * 100.0) as u32,
+                            "current": i + 1,
+                            "total": total_zip_files,
+                            "filename": file_name
+                        }),
+                    );
+                    last_progress_update = std::time::Instant::now();
+                }
+
+                let out_path = extract_path.join(file_path);
+                eprintln!("Output path: {}", out_path.display());
+
+                if !out_path.starts_with(&extract_path) {
+                    eprintln!("Security error: zip would extract outside target directory");
+                    return Err(
+                        "Invalid zip file: path would extract outside target directory".to_string(),
+                    );
+                }
+
+                if file.is_dir() {
+                    eprintln!("Creating directory: {}", out_path.display());
+                    match std::fs::create_dir_all(&out_path) {
+                        Ok(_) => eprintln!("Created directory successfully"),
+                        Err(e) => {
+                            eprintln!("Failed to create directory {}: {}", out_path.display(), e);
+                            return Err(e.to_string());
+                        }
+                    }
+                } else {
+                    if let Some(parent) = out_path.parent() {
+                        eprintln!("Ensuring parent directory exists: {}", parent.display());
+                        match std::fs::create_dir_all(parent) {
+                            Ok(_) => eprintln!("Created parent directory successfully"),
+                            Err(e) => {
+                                eprintln!(
+                                    "Failed to create parent directory {}: {}",
+                                    parent.display(),
+                                    e
+                                );
+                                return Err(e.to_string());
+                            }
+                        }
+                    }
+
+                    // Don't write through a hardlink into the blob store
+                    if out_path.is_file() {
+                        if let Err(e) = std::fs::remove_file(&out_path) {
+                            eprintln!("Failed to replace file {}: {}", out_path.display(), e);
+                            return Err(e.to_string());
+                        }
+                    }
+
+                    eprintln!("Creating file: {}", out_path.display());
+                    let mut outfile = match File::create(&out_path) {
+                        Ok(file) => file,
+                        Err(e) => {
+                            eprintln!("Failed to create file {}: {}", out_path.display(), e);
+                            return Err(e.to_string());
+                        }
+                    };
+
+                    eprintln!("Copying file content");
+                    match std::io::copy(&mut file, &mut outfile) {
+                        Ok(bytes) => eprintln!("Copied {} bytes", bytes),
+                        Err(e) => {
+                            eprintln!("Failed to copy file content: {}", e);
+                            return Err(e.to_string());
+                        }
+                    }
+                }
+            }
+
+            // Extract notes from manifest
+            if let Some(manifest) = &manifest_data {
+                if let Some(notes) = &manifest.notes {
+                    eprintln!("Found notes in manifest: {}", notes);
+                    notes_text = format!(" Notes: {}", notes);
+                }
+            }
+
+            // Save the hash to track this installation
+            eprintln!(
+                "Writing installation hash to {}",
+                extract_hash_path.display()
+            );
+            match std::fs::write(&extract_hash_path, &file_hash) {
+                Ok(_) => eprintln!("Installation hash written successfully"),
+                Err(e) => {
+                    eprintln!("Failed to write installation hash: {}", e);
+                    return Err(format!("Failed to write installation hash: {}", e));
+                }
+            }
+
+            // Final extraction progress update
+            ctx.emit(
+                "extraction_progress",
+                serde_json::json!({
+                    "percent": 100,
+                    "current": total_zip_files,
+                    "total": total_zip_files,
+                    "filename": "Extraction complete"
+                }),
+            );
+        }
+
+        // Return successful result
+        let status = if download_needed {
+            if need_extraction {
+                format!("✅ Downloaded and extracted new version.{}", notes_text)
+            } else {
+                "✅ Downloaded but extraction skipped (files up to date)".to_string()
+            }
+        } else if need_extraction {
+            format!("✅ Used cached file and extracted.{}", notes_text)
+        } else {
+            "✅ All files already up to date".to_string()
+        };
+
+        eprintln!("Operation completed: {}", status);
+        Ok(format!("{} (Hash: {})", status, file_hash))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Task join error: {}", e);
+        format!("Task join error: {}", e)
+    })?;
+
+    eprintln!("Returning final result");
+    result
+}
+
+// Download a manifest, check its signature and parse it
+pub(crate) async fn fetch_trusted_manifest(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    manifest_url: &str,
+) -> Result<StoryManifest, String> {
+    eprintln!("Downloading manifest from: {}", manifest_url);
+    let client = ctx.api().http();
+    let manifest_text = cache.get_text(client, manifest_url).await.map_err(|e| {
+        let error_msg = format!("Failed to download manifest: {}", e);
+        eprintln!("ERROR: {}", error_msg);
+        error_msg
+    })?;
+    eprintln!("Successfully downloaded manifest");
+
+    // Nothing from the manifest is used until its signature checks out
+    let trust = verify_manifest_trust(ctx, client, cache, manifest_url, &manifest_text)
+        .await
+        .map_err(|e| {
+            eprintln!("ERROR: {}", e);
+            e
+        })?;
+    eprintln!("Manifest trust: {:?}", trust);
+
+    eprintln!(
+        "Manifest content length: {} characters",
+        manifest_text.len()
+    );
+    eprintln!("Parsing manifest JSON...");
+    parse_manifest(&manifest_text).map_err(|e| {
+        eprintln!("ERROR: {}", e);
+        e
+    })
+}
+
+pub async fn install_from_manifest(
+    ctx: &LauncherContext,
+    manifest_url: String,
+    instance_base: String,
+    offline: bool,
+) -> Result<String, String> {
+    install_manifest(ctx, manifest_url, instance_base, offline, false).await
+}
+
+// Install the manifest without reusing the instance's lockfile, so unpinned mods move to
+// the newer versions check_instance_updates reports. The new lock replaces the old one.
+pub async fn update_from_manifest(
+    ctx: &LauncherContext,
+    manifest_url: String,
+    instance_base: String,
+) -> Result<String, String> {
+    install_manifest(ctx, manifest_url, instance_base, false, true).await
+}
+
+async fn install_manifest(
+    ctx: &LauncherContext,
+    manifest_url: String,
+    instance_base: String,
+    offline: bool,
+    refresh: bool,
+) -> Result<String, String> {
+    eprintln!("=== DOWNLOAD_FROM_MANIFEST START ===");
+    eprintln!("Manifest URL: {}", manifest_url);
+    eprintln!("Instance base path: {}", instance_base);
+    eprintln!("Offline mode: {}", offline);
+
+    // Manifests and API responses are cached so the install can be repeated offline
+    let cache = ctx.response_cache(offline);
+
+    let ctx = &ctx.begin_install();
+
+    // Validate instance_base path exists
+    let instance_base_path = Path::new(&instance_base);
+    eprintln!(
+        "Checking if instance_base exists: {}",
+        instance_base_path.display()
+    );
+    if !instance_base_path.exists() {
+        let error_msg = format!(
+            "Instance base path does not exist: {}",
+            instance_base_path.display()
+        );
+        eprintln!("ERROR: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    // Check if Story instance already exists and has content
+    let story_path = instance_base_path.join("Story");
+    if story_path.exists() {
+        let minecraft_dir = story_path.join(".minecraft");
+        let mods_dir = minecraft_dir.join("mods");
+        let config_dir = minecraft_dir.join("config");
+
+        // Check if instance already has mods and config
+        let has_mods = mods_dir.exists()
+            && std::fs::read_dir(&mods_dir)
+                .map(|mut dir| dir.next().is_some())
+                .unwrap_or(false);
+        let has_config = config_dir.exists()
+            && std::fs::read_dir(&config_dir)
+                .map(|mut dir| dir.next().is_some())
+                .unwrap_or(false);
+
+        if has_mods || has_config {
+            eprintln!("Instance already has content, checking if update is needed...");
+            // We'll still proceed to check for updates, but this helps with logging
+        }
+    }
+
+    // A packwiz pack.toml describes the whole instance by itself
+    if is_packwiz_url(&manifest_url) {
+        std::fs::create_dir_all(&story_path).map_err(|e| {
+            format!(
+                "Failed to create Story directory {}: {}",
+                story_path.display(),
+                e
+            )
+        })?;
+        let result = install_packwiz_instance(ctx, &cache, &manifest_url, &story_path).await?;
+        eprintln!("=== DOWNLOAD_FROM_MANIFEST COMPLETE ===");
+        return Ok(result);
+    }
+
+    // Download and parse the manifest
+    let api = ctx.api();
+    let client = api.http();
+    let manifest = fetch_trusted_manifest(ctx, &cache, &manifest_url).await?;
+
+    eprintln!(
+        "Successfully parsed manifest for instance: {} v{}",
+        manifest.instance.name, manifest.instance.version
+    );
+
+    // Pin the pack, extra mods and their dependencies before downloading anything
+    eprintln!("=== RESOLVING LOCKFILE ===");
+    let store = ctx.blob_store();
+    let lock = load_or_resolve_lock(api, &cache, &store, &manifest, &story_path, refresh)
+        .await
+        .map_err(|e| {
+            eprintln!("ERROR: {}", e);
+            e
+        })?;
+    eprintln!(
+        "🔒 Lockfile pins {} mod files for Minecraft {} ({})",
+        lock.mods.len(),
+        lock.minecraft_version,
+        lock.loader
+    );
+
+    // Step 1: Download the modpack
+    eprintln!("=== STEP 1: DOWNLOADING MODPACK ===");
+    eprintln!("Calling download_modrinth_modpack with:");
+    eprintln!("  - project_name: {}", manifest.instance.name);
+    eprintln!("  - version: {}", manifest.instance.version);
+    eprintln!("  - instance_base: {}", instance_base);
+
+    // Emit initial progress for modpack download
+    ctx.emit(
+        "download_progress",
+        serde_json::json!({
+            "percent": 0,
+            "current": 0,
+            "total": 1,
+            "filename": "Starting modpack download...",
+            "stage": "modpack"
+        }),
+    );
+
+    let modpack_result = install_modrinth_modpack(
+        ctx,
+        &cache,
+        manifest.instance.name.clone(),
+        manifest.instance.version.clone(),
+        instance_base.clone(),
+        lock.pack.as_ref(),
+    )
+    .await
+    .map_err(|e| {
+        let error_msg = format!("Modpack download failed: {}", e);
+        eprintln!("ERROR: {}", error_msg);
+        error_msg
+    })?;
+
+    eprintln!("Modpack download result: {}", modpack_result);
+
+    // Wait a moment for file operations to complete
+    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+
+    // Emit completion for modpack
+    ctx.emit(
+        "download_progress",
+        serde_json::json!({
+            "percent": 50,
+            "current": 1,
+            "total": 1,
+            "filename": "Modpack download completed",
+            "stage": "modpack"
+        }),
+    );
+
+    // Step 2: Install the extra mods and their dependencies exactly as locked
+    let mut skipped_count = 0;
+    let mut resolved_mods: Vec<ResolvedMod> = Vec::new();
+    if !lock.mods.is_empty() {
+        eprintln!(
+            "=== STEP 2: INSTALLING {} LOCKED MOD FILES ===",
+            lock.mods.len()
+        );
+
+        let story_path = Path::new(&instance_base).join("Story");
+        eprintln!("Story path: {}", story_path.display());
+
+        // Check if Story directory exists
+        if !story_path.exists() {
+            let error_msg = format!("Story directory does not exist: {}", story_path.display());
+            eprintln!("ERROR: {}", error_msg);
+            return Err(error_msg);
+        }
+
+        let mods_dir = story_path.join(".minecraft").join("mods");
+        eprintln!("Mods directory path: {}", mods_dir.display());
+        std::fs::create_dir_all(&mods_dir).map_err(|e| {
+            let error_msg = format!(
+                "Failed to create mods directory {}: {}",
+                mods_dir.display(),
+                e
+            );
+            eprintln!("ERROR: {}", error_msg);
+            error_msg
+        })?;
+
+        let installed = install_locked_mods(ctx, client, &cache, &lock.mods, &mods_dir).await?;
+        skipped_count = installed.skipped;
+        resolved_mods = installed.resolved;
+    } else {
+        eprintln!("=== STEP 2: NO EXTRA MODS TO DOWNLOAD ===");
+    }
+
+    // Packwiz files go in before the overrides, which win for the same path
+    let mut packwiz_files: Vec<ManagedFile> = Vec::new();
+    if let Some(packwiz) = &manifest.packwiz {
+        eprintln!("=== STEP 2b: INSTALLING PACKWIZ FILES ===");
+        let minecraft_dir = Path::new(&instance_base).join("Story").join(".minecraft");
+        let installed =
+            install_packwiz_files(ctx, &cache, packwiz, &minecraft_dir, PackwizSide::Client)
+                .await?;
+        packwiz_files = installed.files;
+    }
+
+    // Step 3: Download and extract override files if any
+    let mut override_files: Vec<ManagedFile> = Vec::new();
+    // Only overrides that made it onto disk are tracked, so a failed one is offered again
+    let mut extracted_overrides: HashSet<String> = HashSet::new();
+    if let Some(overrides) = &manifest.overrides {
+        eprintln!(
+            "=== STEP 3: DOWNLOADING {} OVERRIDE FILES ===",
+            overrides.len()
+        );
+
+        let story_path = Path::new(&instance_base).join("Story");
+        let minecraft_dir = story_path.join(".minecraft");
+        let zip_cache_dir = ctx.zip_cache_dir();
+
+        // Ensure .minecraft directory exists
+        std::fs::create_dir_all(&minecraft_dir).map_err(|e| {
+            let error_msg = format!(
+                "Failed to create .minecraft directory {}: {}",
+                minecraft_dir.display(),
+                e
+            );
+            eprintln!("ERROR: {}", error_msg);
+            error_msg
+        })?;
+
+        for (index, override_item) in overrides.iter().enumerate() {
+            eprintln!(
+                "Downloading override {}/{}: {} from {}",
+                index + 1,
+                overrides.len(),
+                override_item.name,
+                override_item.url
+            );
+
+            // Emit progress for this override
+            ctx.emit(
+                "download_progress",
+                serde_json::json!({
+                    "percent": 75 + ((index as f64 / overrides.len() as f64) * 20.0) as u32,
+                    "current": index + 1,
+                    "total": overrides.len(),
+                    "filename": format!("Downloading override ({}/{}): {}", index + 1, overrides.len(), override_item.name),
+                    "stage": "overrides"
+                }),
+            );
+
+            // Use the existing fetch_and_extract_zip function
+            let extract_result = fetch_and_extract_zip(
+                ctx,
+                override_item.url.clone(),
+                minecraft_dir.to_string_lossy().to_string(),
+                false, // Don't force download unless needed
+                offline,
+                override_item.sha256.clone(),
+                override_item.size,
+            )
+            .await;
+
+            match extract_result {
+                Ok(result) => {
+                    eprintln!("Override extracted: {}", result);
+                    extracted_overrides.insert(override_item.name.clone());
+
+                    // Remember what this override contains so verify/repair can check it
+                    let zip_path = cached_zip_path(&zip_cache_dir, &override_item.url);
+                    match zip_entry_hashes(&zip_path) {
+                        Ok(entries) => {
+                            for (path, sha512) in entries {
+                                override_files.push(ManagedFile {
+                                    path,
+                                    sha512,
+                                    source: ManagedSource::Override {
+                                        name: override_item.name.clone(),
+                                        url: override_item.url.clone(),
+                                        sha256: override_item.sha256.clone(),
+                                    },
+                                });
+                            }
+                        }
+                        Err(e) => eprintln!(
+                            "Warning: Failed to index override {}: {}",
+                            override_item.name, e
+                        ),
+                    }
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to download override {}: {}",
+                        override_item.name, e
+                    );
+                    cache.record_missing(format!(
+                        "override {} ({}): {}",
+                        override_item.name, override_item.url, e
+                    ));
+                    // Continue with other overrides instead of failing completely
+                }
+            }
+        }
+
+        // Emit completion for overrides
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": 95,
+                "current": overrides.len(),
+                "total": overrides.len(),
+                "filename": "Override downloads completed",
+                "stage": "overrides"
+            }),
+        );
+    } else {
+        eprintln!("=== STEP 3: NO OVERRIDE FILES TO DOWNLOAD ===");
+    }
+
+    // An offline rebuild that couldn't find everything must not be recorded as installed
+    cache.ensure_complete()?;
+
+    // Record extra mods and override contents for verify_instance/repair_instance
+    let story_path = Path::new(&instance_base).join("Story");
+    packwiz_files.extend(override_files);
+    match record_managed_files(ctx, &story_path, packwiz_files) {
+        Ok(count) => eprintln!("💾 Recorded {} managed files", count),
+        Err(e) => eprintln!("⚠️ Warning: Failed to record managed files: {}", e),
+    }
+
+    // Step 4: Save version tracking information
+    eprintln!("=== STEP 4: SAVING VERSION TRACKING ===");
+    let story_path = Path::new(&instance_base).join("Story");
+    eprintln!("Story path for version tracking: {}", story_path.display());
+
+    if !story_path.exists() {
+        let error_msg = format!(
+            "Story directory does not exist for version tracking: {}",
+            story_path.display()
+        );
+        eprintln!("ERROR: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    let version_file = story_path.join(".current_version.json");
+    eprintln!("Version file path: {}", version_file.display());
+    let previous_version_info: Option<serde_json::Value> = std::fs::read_to_string(&version_file)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    let version_info = serde_json::json!({
+        "instance_name": manifest.instance.name,
+        "instance_version": manifest.instance.version,
+        "extra_mods": manifest.extra_mods.as_ref().map(|mods| {
+            mods.iter().map(|m| {
+                tracked_extra_mod(m, &resolved_mods, previous_version_info.as_ref())
+            }).collect::<Vec<_>>()
+        }).unwrap_or_default(),
+        "overrides": manifest.overrides.as_ref().map(|overrides| {
+            overrides.iter().filter(|o| extracted_overrides.contains(&o.name)).map(|o| {
+                serde_json::json!({
+                    "name": o.name,
+                    "url": o.url,
+                    "sha256": o.sha256,
+                    "size": o.size
+                })
+            }).collect::<Vec<_>>()
+        }).unwrap_or_default(),
+        "packwiz": manifest.packwiz.as_ref().map(|p| {
+            serde_json::json!({ "url": p.url, "sha256": p.sha256 })
+        }),
+        "last_updated": chrono::Utc::now().to_rfc3339()
+    });
+
+    eprintln!("Writing version info to file...");
+    std::fs::write(
+        &version_file,
+        serde_json::to_string_pretty(&version_info).unwrap(),
+    )
+    .map_err(|e| {
+        let error_msg = format!(
+            "Failed to save version info to {}: {}",
+            version_file.display(),
+            e
+        );
+        eprintln!("ERROR: {}", error_msg);
+        error_msg
+    })?;
+
+    eprintln!(
+        "Successfully saved version tracking information to: {}",
+        version_file.display()
+    );
+
+    // Emit final completion event
+    ctx.emit(
+        "download_progress",
+        serde_json::json!({
+            "percent": 100,
+            "current": 1,
+            "total": 1,
+            "filename": "All downloads completed successfully!",
+            "stage": "complete"
+        }),
+    );
+
+    let total_extra_mods = manifest.extra_mods.as_ref().map_or(0, |m| m.len());
+    let total_overrides = manifest.overrides.as_ref().map_or(0, |o| o.len());
+    let downloaded_mods = total_extra_mods - skipped_count;
+
+    let final_result = if total_overrides > 0 {
+        if skipped_count > 0 {
+            format!("✅ Successfully downloaded modpack, {} extra mods ({} downloaded, {} skipped), and {} override files", 
+                    total_extra_mods, downloaded_mods, skipped_count, total_overrides)
+        } else {
+            format!(
+                "✅ Successfully downloaded modpack, {} extra mods, and {} override files",
+                total_extra_mods, total_overrides
+            )
+        }
+    } else {
+        if skipped_count > 0 {
+            format!("✅ Successfully downloaded modpack and {} extra mods ({} downloaded, {} skipped as already present)", 
+                    total_extra_mods, downloaded_mods, skipped_count)
+        } else {
+            format!(
+                "✅ Successfully downloaded modpack and {} extra mods",
+                total_extra_mods
+            )
+        }
+    };
+
+    // Step 4: Cleanup extra JAR files not in manifest
+    eprintln!("=== STEP 4: CLEANUP EXTRA JAR FILES ===");
+    let cleanup_result = cleanup_extra_jars(ctx, &story_path, &manifest).await;
+    match cleanup_result {
+        Ok(cleaned_count) => {
+            if cleaned_count > 0 {
+                eprintln!("✅ Cleaned up {} extra JAR files", cleaned_count);
+            } else {
+                eprintln!("✅ No extra JAR files found to clean up");
+            }
+        }
+        Err(e) => {
+            eprintln!("⚠️ Warning: Failed to cleanup extra JAR files: {}", e);
+            // Don't fail the entire operation for cleanup issues
+        }
+    }
+
+    // Step 5: Keep the shared blob cache within its size limit
+    eprintln!("=== STEP 5: BLOB CACHE GC ===");
+    match gc_blob_store(ctx, None).await {
+        Ok(report) => eprintln!(
+            "✅ Blob cache GC finished: {} blobs removed, {} bytes remaining",
+            report.removed, report.remaining_bytes
+        ),
+        Err(e) => eprintln!("⚠️ Warning: Blob cache GC failed: {}", e),
+    }
+
+    // Mods that only run here through a loader fallback are worth knowing about
+    let final_result = match loader_fallback_report(&lock.mods) {
+        Some(report) => format!("{}\n{}", final_result, report),
+        None => final_result,
+    };
+    let final_result = match minecraft_version_fallback_report(&lock.mods, &lock.minecraft_version)
+    {
+        Some(report) => format!("{}\n{}", final_result, report),
+        None => final_result,
+    };
+
+    // Report anything that failed to download instead of silently dropping it
+    let missing = cache.missing();
+    let final_result = if missing.is_empty() {
+        final_result
+    } else {
+        format!(
+            "{}\n⚠️ {} item(s) could not be downloaded:\n - {}",
+            final_result,
+            missing.len(),
+            missing.join("\n - ")
+        )
+    };
+
+    eprintln!("=== DOWNLOAD_FROM_MANIFEST COMPLETE ===");
+    eprintln!("Final result: {}", final_result);
+
+    Ok(final_result)
+}
+
+pub async fn install_modpack(
+    ctx: &LauncherContext,
+    project_name: String,
+    version: String,
+    instance_base: String,
+    offline: bool,
+) -> Result<String, String> {
+    let cache = ctx.response_cache(offline);
+    let result =
+        install_modrinth_modpack(ctx, &cache, project_name, version, instance_base, None).await?;
+    cache.ensure_complete()?;
+    Ok(result)
+}
+
+// The bytes of a pack file given as a local path or an http(s) URL. Downloads go
+// through the blob store like every other file. A URL can publish a new build at any
+// time, so it is downloaded again online; offline, the build it last served is used.
+pub(crate) async fn read_pack_source(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    source: &str,
+) -> Result<Vec<u8>, String> {
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return std::fs::read(source).map_err(|e| e.to_string());
+    }
+    let store = ctx.blob_store();
+    let known = if cache.offline {
+        cache.load_sha512(source)
+    } else {
+        None
+    };
+    let sha512 = fetch_into_blob_store(
+        ctx.api().http(),
+        &store,
+        &[source.to_string()],
+        known.as_deref(),
+        cache.offline,
+    )
+    .await?;
+    cache.store_sha512(source, &sha512);
+    std::fs::read(store.blob_path(&sha512)).map_err(|e| e.to_string())
+}
+
+// Install an .mrpack from a local path or any URL into the Story instance, without
+// looking the pack up on Modrinth. For unreleased pack builds and private packs.
+pub async fn install_mrpack(
+    ctx: &LauncherContext,
+    source: String,
+    instance_base: String,
+    offline: bool,
+) -> Result<String, String> {
+    eprintln!("=== INSTALL_MRPACK START ===");
+    eprintln!("Source: {}", source);
+    eprintln!("Instance base path: {}", instance_base);
+
+    let cache = ctx.response_cache(offline);
+    let ctx = &ctx.begin_install();
+
+    let instance_base_path = Path::new(&instance_base);
+    if !instance_base_path.exists() {
+        let error_msg = format!(
+            "Instance base path does not exist: {}",
+            instance_base_path.display()
+        );
+        eprintln!("ERROR: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    let mrpack_bytes = read_pack_source(ctx, &cache, &source)
+        .await
+        .map_err(|e| format!("Failed to read mrpack {}: {}", source, e))?;
+    let index = read_mrpack_index(&mrpack_bytes)?;
+    eprintln!("Installing modpack: {} v{}", index.name, index.version_id);
+
+    let story_path = instance_base_path.join("Story");
+    std::fs::create_dir_all(&story_path).map_err(|e| {
+        let error_msg = format!(
+            "Failed to create Story directory {}: {}",
+            story_path.display(),
+            e
+        );
+        eprintln!("ERROR: {}", error_msg);
+        error_msg
+    })?;
+
+    install_mrpack_contents(ctx, &cache, &mrpack_bytes, &story_path).await?;
+    create_local_instance_config(&story_path, &index)?;
+    cache.ensure_complete()?;
+
+    // The pack replaces whatever was installed before, so drop the extra mods and
+    // overrides an earlier manifest install recorded
+    match record_managed_files(ctx, &story_path, Vec::new()) {
+        Ok(count) => eprintln!("💾 Recorded {} managed files", count),
+        Err(e) => eprintln!("⚠️ Warning: Failed to record managed files: {}", e),
+    }
+    let version_info = serde_json::json!({
+        "instance_name": index.name,
+        "instance_version": index.version_id,
+        "last_updated": chrono::Utc::now().to_rfc3339()
+    });
+    std::fs::write(
+        story_path.join(".current_version.json"),
+        serde_json::to_string_pretty(&version_info).unwrap(),
+    )
+    .map_err(|e| format!("Failed to save version info: {}", e))?;
+
+    let result = format!(
+        "✅ Successfully installed modpack: {} v{}",
+        index.name, index.version_id
+    );
+    let missing = cache.missing();
+    let result = if missing.is_empty() {
+        result
+    } else {
+        format!(
+            "{}\n⚠️ {} item(s) could not be downloaded:\n - {}",
+            result,
+            missing.len(),
+            missing.join("\n - ")
+        )
+    };
+    eprintln!("=== INSTALL_MRPACK COMPLETE ===");
+    Ok(result)
+}
+
+async fn install_modrinth_modpack(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    project_name: String,
+    version: String,
+    instance_base: String,
+    locked: Option<&LockedFile>,
+) -> Result<String, String> {
+    eprintln!(
+        "Downloading Modrinth modpack: {} v{}",
+        project_name, version
+    );
+
+    // Construct the Modrinth API URL; a locked pack is fetched by its exact version id
+    let api = ctx.api();
+    let api_url = match locked {
+        Some(locked) => api.url(&format!("/version/{}", locked.version_id)),
+        None => api.url(&format!("/project/{}/version/{}", project_name, version)),
+    };
+    eprintln!("API URL: {}", api_url);
+
+    // Get version info from Modrinth API
+    let client = api.http();
+    let response_text = cache
+        .get_text(api.client(), &api_url)
+        .await
+        .map_err(|e| format!("Failed to fetch modpack info: {}", e))?;
+
+    let version_info: ModrinthVersionResponse = serde_json::from_str(&response_text)
+        .map_err(|e| format!("Failed to parse modpack info: {}", e))?;
+
+    eprintln!("Found modpack: {}", version_info.name);
+
+    // Find the primary .mrpack file
+    let mrpack_file = version_info
+        .files
+        .iter()
+        .find(|f| f.primary && f.filename.ends_with(".mrpack"))
+        .ok_or("No primary .mrpack file found")?;
+
+    eprintln!(
+        "Found mrpack file: {} ({} bytes)",
+        mrpack_file.filename, mrpack_file.size
+    );
+
+    // Create the Story instance directory
+    let story_path = Path::new(&instance_base).join("Story");
+    eprintln!("=== MODPACK: CREATING DIRECTORIES ===");
+    eprintln!("Instance base: {}", instance_base);
+    eprintln!("Story path to create: {}", story_path.display());
+
+    // Check if instance_base exists and is accessible
+    let instance_base_path = Path::new(&instance_base);
+    if !instance_base_path.exists() {
+        let error_msg = format!(
+            "Instance base directory does not exist: {}",
+            instance_base_path.display()
+        );
+        eprintln!("ERROR: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    eprintln!("Instance base exists, creating Story directory...");
+    std::fs::create_dir_all(&story_path).map_err(|e| {
+        let error_msg = format!(
+            "Failed to create Story directory {}: {}",
+            story_path.display(),
+            e
+        );
+        eprintln!("ERROR: {}", error_msg);
+        error_msg
+    })?;
+    eprintln!("Successfully created Story directory");
+
+    // Download the mrpack file (or reuse it from the blob store)
+    eprintln!("Downloading mrpack file from: {}", mrpack_file.url);
+    let store = ctx.blob_store();
+    let mrpack_sha512 = fetch_into_blob_store(
+        client,
+        &store,
+        std::slice::from_ref(&mrpack_file.url),
+        locked
+            .map(|l| l.sha512.as_str())
+            .or_else(|| mrpack_file.hashes.get("sha512").map(|h| h.as_str())),
+        cache.offline,
+    )
+    .await
+    .map_err(|e| format!("Failed to download mrpack {}: {}", mrpack_file.filename, e))?;
+
+    let mrpack_bytes = std::fs::read(store.blob_path(&mrpack_sha512))
+        .map_err(|e| format!("Failed to read mrpack bytes: {}", e))?;
+    install_mrpack_contents(ctx, cache, &mrpack_bytes, &story_path).await?;
+
+    // Create instance configuration files
+    create_instance_config(&story_path, &version_info)?;
+
+    Ok(format!(
+        "✅ Successfully downloaded and extracted modpack: {} v{}",
+        project_name, version
+    ))
+}
+
+// Unpack an mrpack into a Story instance: modrinth.index.json into mrpack/, overrides/
+// into .minecraft, and every file of the index from the blob store or its downloads
+pub(crate) async fn install_mrpack_contents(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    mrpack_bytes: &[u8],
+    story_path: &Path,
+) -> Result<(), String> {
+    let client = ctx.api().http();
+    let store = ctx.blob_store();
+
+    // Extract the mrpack (it's a zip file)
+    let cursor = Cursor::new(mrpack_bytes);
+    let mut zip =
+        ZipArchive::new(cursor).map_err(|e| format!("Failed to open mrpack as zip: {}", e))?;
+
+    eprintln!("Extracting mrpack with {} files", zip.len());
+
+    // Create necessary directories
+    let mrpack_dir = story_path.join("mrpack");
+    let minecraft_dir = story_path.join(".minecraft");
+    eprintln!("Creating mrpack directory at: {}", mrpack_dir.display());
+    std::fs::create_dir_all(&mrpack_dir).map_err(|e| {
+        let error_msg = format!(
+            "Failed to create mrpack directory {}: {}",
+            mrpack_dir.display(),
+            e
+        );
+        eprintln!("{}", error_msg);
+        error_msg
+    })?;
+    eprintln!(
+        "Creating minecraft directory at: {}",
+        minecraft_dir.display()
+    );
+    std::fs::create_dir_all(&minecraft_dir).map_err(|e| {
+        let error_msg = format!(
+            "Failed to create minecraft directory {}: {}",
+            minecraft_dir.display(),
+            e
+        );
+        eprintln!("{}", error_msg);
+        error_msg
+    })?;
+
+    let mut modrinth_index_content = String::new();
+
+    // Extract files from mrpack
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).map_err(|e| e.to_string())?;
+        let file_name = file.name();
+
+        eprintln!("Extracting: {}", file_name);
+
+        if file_name == "modrinth.index.json" {
+            // Save modrinth.index.json to mrpack folder
+            file.read_to_string(&mut modrinth_index_content)
+                .map_err(|e| e.to_string())?;
+            let index_path = mrpack_dir.join("modrinth.index.json");
+            std::fs::write(index_path, &modrinth_index_content).map_err(|e| e.to_string())?;
+        } else if file_name.starts_with("overrides/") {
+            // Extract overrides to .minecraft folder
+            let relative_path = file_name.strip_prefix("overrides/").unwrap_or(file_name);
+            let Some(output_path) = safe_join(&minecraft_dir, relative_path) else {
+                eprintln!(
+                    "⚠️ Warning: Skipping override outside the instance: {}",
+                    relative_path
+                );
+                continue;
+            };
+
+            if file.is_dir() {
+                std::fs::create_dir_all(&output_path).map_err(|e| e.to_string())?;
+            } else {
+                if let Some(parent) = output_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+
+                // Don't write through a hardlink into the blob store
+                if output_path.is_file() {
+                    std::fs::remove_file(&output_path).map_err(|e| e.to_string())?;
+                }
+                let mut output_file = File::create(&output_path).map_err(|e| e.to_string())?;
+                std::io::copy(&mut file, &mut output_file).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    // Parse modrinth.index.json and download mods
+    if !modrinth_index_content.is_empty() {
+        let modrinth_index: ModrinthIndex = serde_json::from_str(&modrinth_index_content)
+            .map_err(|e| format!("Failed to parse modrinth.index.json: {}", e))?;
+
+        let mods_dir = minecraft_dir;
+        eprintln!("Creating mods directory at: {}", mods_dir.display());
+        std::fs::create_dir_all(&mods_dir).map_err(|e| {
+            let error_msg = format!(
+                "Failed to create mods directory {}: {}",
+                mods_dir.display(),
+                e
+            );
+            eprintln!("{}", error_msg);
+            error_msg
+        })?;
+
+        eprintln!("Downloading {} mod files", modrinth_index.files.len());
+
+        for (index, mod_file) in modrinth_index.files.iter().enumerate() {
+            eprintln!(
+                "Downloading mod {}/{}: {}",
+                index + 1,
+                modrinth_index.files.len(),
+                mod_file.path
+            );
+
+            // Emit progress update to frontend
+            ctx.emit(
+                "download_progress",
+                serde_json::json!({
+                    "percent": ((index as f64 / modrinth_index.files.len() as f64) * 100.0) as u32,
+                    "current": index + 1,
+                    "total": modrinth_index.files.len(),
+                    "filename": format!("Downloading mod: {}", mod_file.path),
+                    "stage": "mods"
+                }),
+            );
+
+            // Try each download URL until one works, reusing the blob store when possible
+            let sha512 = match fetch_into_blob_store(
+                client,
+                &store,
+                &mod_file.downloads,
+                mod_file.hashes.get("sha512").map(|h| h.as_str()),
+                cache.offline,
+            )
+            .await
+            {
+                Ok(sha512) => sha512,
+                Err(e) => {
+                    eprintln!("Warning: Failed to download mod {}: {}", mod_file.path, e);
+                    cache.record_missing(format!("{}: {}", mod_file.path, e));
+                    continue;
+                }
+            };
+
+            let Some(mod_path) = safe_join(&mods_dir, &mod_file.path) else {
+                eprintln!(
+                    "⚠️ Warning: Skipping file outside the instance: {}",
+                    mod_file.path
+                );
+                continue;
+            };
+            eprintln!("Linking mod file to: {}", mod_path.display());
+            store.link_to(&sha512, &mod_path).map_err(|e| {
+                let error_msg = format!("Failed to write mod file {}: {}", mod_path.display(), e);
+                eprintln!("ERROR: {}", error_msg);
+                error_msg
+            })?;
+
+            // Track the downloaded JAR file
+            if let Some(filename) = mod_path.file_name().and_then(|n| n.to_str()) {
+                ctx.session().track_downloaded_file(filename);
+            }
+        }
+
+        // Final progress update for mods download
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": 100,
+                "current": modrinth_index.files.len(),
+                "total": modrinth_index.files.len(),
+                "filename": "Mod downloads completed",
+                "stage": "mods"
+            }),
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn install_mod(
+    ctx: &LauncherContext,
+    mod_name: String,
+    version: Option<String>,
+    minecraft_version: String,
+    loader: String,
+    mods_dir: String,
+    offline: bool,
+) -> Result<String, String> {
+    let cache = ctx.response_cache(offline);
+    let extra_mod = ExtraMod {
+        name: mod_name,
+        version,
+        version_id: None,
+        sha512: None,
+        filename: None,
+        release_channel: None,
+        prefer_featured: None,
+        env: None,
+        url: None,
+        path: None,
+    };
+    let resolved =
+        install_modrinth_mod(ctx, &cache, &extra_mod, minecraft_version, loader, mods_dir).await?;
+    cache.ensure_complete()?;
+    Ok(format!(
+        "✅ Downloaded mod: {} {} ({}) with dependencies",
+        resolved.name, resolved.version_number, resolved.filename
+    ))
+}
+
+async fn install_modrinth_mod(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    extra_mod: &ExtraMod,
+    minecraft_version: String,
+    loader: String,
+    mods_dir: String,
+) -> Result<ResolvedMod, String> {
+    let api = ctx.api();
+    let mod_name = &extra_mod.name;
+
+    // Resolve the mod and its whole dependency graph before downloading anything
+    let mut loaders = default_loader_fallbacks(&loader, &minecraft_version);
+    loaders.insert(0, loader);
+    let store = ctx.blob_store();
+    let mut resolver = ModResolver::new(
+        api,
+        cache,
+        minecraft_version,
+        loaders,
+        VersionPreference::default(),
+        HashMap::new(),
+        None,
+    );
+    resolver.add_extra_mod(extra_mod, &store).await;
+    resolver.resolve_dependencies().await;
+    let (mods, failures) = resolver.finish()?;
+    if mods.is_empty() {
+        return Err(failures.join("; "));
+    }
+    for failure in failures {
+        eprintln!("Warning: {}", failure);
+        cache.record_missing(failure);
+    }
+
+    eprintln!(
+        "Installing {} with {} dependencies",
+        mod_name,
+        mods.len() - 1
+    );
+    let installed =
+        install_locked_mods(ctx, api.http(), cache, &mods, Path::new(&mods_dir)).await?;
+
+    // Emit completion progress
+    ctx.emit(
+        "download_progress",
+        serde_json::json!({
+            "percent": 100,
+            "current": 1,
+            "total": 1,
+            "filename": format!("Completed: {}", mod_name),
+            "stage": "extra_mods"
+        }),
+    );
+
+    installed
+        .resolved
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Failed to install {}", mod_name))
+}
+
+pub async fn check_instance_updates(
+    ctx: &LauncherContext,
+    manifest_url: String,
+    instance_base: String,
+) -> Result<ManifestDiff, String> {
+    eprintln!("Checking for manifest updates from: {}", manifest_url);
+
+    // Download and parse the manifest
+    let api = ctx.api();
+    let client = api.http();
+    let cache = ctx.response_cache(false);
+    let packwiz_only = is_packwiz_url(&manifest_url);
+    let manifest = if packwiz_only {
+        packwiz_manifest(ctx, &cache, &manifest_url).await?
+    } else {
+        fetch_trusted_manifest(ctx, &cache, &manifest_url).await?
+    };
+
+    eprintln!(
+        "Checking updates for: {} v{}",
+        manifest.instance.name, manifest.instance.version
+    );
+
+    // Load the version tracking file written by the last install. Without it we can't
+    // tell what is installed, so everything in the manifest counts as an update.
+    let story_path = Path::new(&instance_base).join("Story");
+    let version_file = story_path.join(".current_version.json");
+    let installed: Option<serde_json::Value> = std::fs::read_to_string(&version_file)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    let mut diff = diff_manifest(&manifest, installed.as_ref());
+    diff.instance_missing = !story_path.exists();
+
+    let minecraft_version = manifest.instance.minecraft_version_or_default();
+    let loaders = manifest.instance.compatible_loaders();
+    let version_preference = manifest.instance.version_preference();
+
+    // Pack download size; a packwiz pack has no Modrinth project to ask
+    if let Some(pack) = diff.pack.as_mut().filter(|_| !packwiz_only) {
+        let api_url = api.url(&format!(
+            "/project/{}/version/{}",
+            manifest.instance.name, manifest.instance.version
+        ));
+        match cache
+            .get_text(api.client(), &api_url)
+            .await
+            .and_then(|text| {
+                serde_json::from_str::<ModrinthVersionResponse>(&text)
+                    .map_err(|e| format!("Failed to parse modpack info: {}", e))
+            }) {
+            Ok(version_info) => pack.download_size = primary_file(&version_info).map(|f| f.size),
+            Err(e) => eprintln!("Warning: could not get modpack size: {}", e),
+        }
+    }
+
+    // Extra mods: sizes for added/changed mods, and unpinned mods whose best
+    // compatible version is no longer the file we installed
+    let installed_mod_hashes: Vec<String> = load_managed_files(&story_path)
+        .files
+        .into_iter()
+        .filter(|f| matches!(f.source, ManagedSource::ExtraMod { .. }))
+        .map(|f| f.sha512)
+        .collect();
+
+    // The jar each extra mod resolved to at the last install. One bulk request tells
+    // which of them are still the newest version, so only the rest need a version list.
+    let installed_jars: HashMap<String, String> = installed
+        .as_ref()
+        .and_then(|v| v.get("extra_mods"))
+        .and_then(|mods| mods.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|m| {
+            let name = m.get("name")?.as_str()?;
+            let sha512 = m.get("resolved")?.get("sha512")?.as_str()?;
+            Some((name.to_string(), sha512.to_lowercase()))
+        })
+        .collect();
+    let installed_jar_hashes: Vec<String> = installed_jars
+        .values()
+        .filter(|sha512| installed_mod_hashes.contains(sha512))
+        .cloned()
+        .collect();
+    // A published lockfile decides the versions, so no update can move to a newer one
+    let check_newer = !diff.untracked && manifest.lockfile.is_none();
+    let latest_versions = if installed_jar_hashes.is_empty() || !check_newer {
+        HashMap::new()
+    } else {
+        let game_versions = [minecraft_version.clone()];
+        fetch_versions_by_hash(
+            api,
+            &cache,
+            &installed_jar_hashes,
+            Some((&loaders, &game_versions)),
+        )
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: could not check installed mods in bulk: {}", e);
+            HashMap::new()
+        })
+    };
+
+    for extra_mod in manifest.extra_mods.as_deref().unwrap_or_default() {
+        // Direct-URL mods have no newer version to look for; a new url or hash is in the diff
+        if extra_mod.url.is_some() {
+            continue;
+        }
+        let existing = diff
+            .extra_mods
+            .iter()
+            .position(|c| c.name == extra_mod.name);
+        let check_content = existing.is_none()
+            && extra_mod.version.is_none()
+            && !extra_mod.is_pinned()
+            && check_newer
+            && !installed_mod_hashes.is_empty();
+        if existing.is_none() && !check_content {
+            continue;
+        }
+        let up_to_date = installed_jars.get(&extra_mod.name).is_some_and(|sha512| {
+            latest_versions.get(sha512).is_some_and(|latest| {
+                is_latest_install(
+                    latest,
+                    sha512,
+                    &minecraft_version,
+                    &loaders[0],
+                    &extra_mod.version_preference(&version_preference),
+                )
+            })
+        });
+        if existing.is_none() && up_to_date {
+            eprintln!("{} is still the newest version", extra_mod.name);
+            continue;
+        }
+
+        let version_info = match resolve_mod_version(
+            api,
+            &cache,
+            extra_mod,
+            &minecraft_version,
+            &loaders,
+            &version_preference,
+        )
+        .await
+        {
+            Ok(version_info) => version_info,
+            Err(e) => {
+                eprintln!("Warning: could not resolve mod {}: {}", extra_mod.name, e);
+                continue;
+            }
+        };
+        let file = select_mod_file(&version_info, extra_mod).ok();
+
+        match existing {
+            Some(index) => diff.extra_mods[index].download_size = file.map(|f| f.size),
+            None => {
+                let installed = file
+                    .and_then(|f| f.hashes.get("sha512"))
+                    .is_some_and(|sha| installed_mod_hashes.contains(sha));
+                if !installed {
+                    let mut change =
+                        ManifestChange::new(&extra_mod.name, ChangeKind::Changed, None, None);
+                    change.reason = Some(format!(
+                        "newer compatible version {}",
+                        version_info.version_number
+                    ));
+                    change.download_size = file.map(|f| f.size);
+                    diff.extra_mods.push(change);
+                }
+            }
+        }
+    }
+
+    // Overrides: sizes for added/changed URLs, and remote content changes for the rest
+    let registry = load_file_hash_registry(&ctx.zip_cache_dir());
+    for override_item in manifest.overrides.as_deref().unwrap_or_default() {
+        let existing = diff
+            .overrides
+            .iter()
+            .position(|c| c.name == override_item.name);
+        let downloaded = registry.files.get(&override_item.url);
+        if existing.is_none() && (diff.untracked || downloaded.is_none()) {
+            continue;
+        }
+
+        let request = client
+            .head(&override_item.url)
+            .headers(conditional_headers(downloaded));
+        let (not_modified, validators) = match request.send().await {
+            Ok(resp) => (
+                resp.status() == reqwest::StatusCode::NOT_MODIFIED,
+                RemoteValidators::from_headers(resp.headers()),
+            ),
+            Err(e) => {
+                eprintln!(
+                    "Warning: HEAD request for {} failed: {}",
+                    override_item.url, e
+                );
+                continue;
+            }
+        };
+
+        match existing {
+            Some(index) => diff.overrides[index].download_size = validators.size,
+            None if not_modified => {}
+            None => {
+                if let Some(reason) = validators.changed_since(downloaded) {
+                    let url = Some(override_item.url.clone());
+                    let mut change = ManifestChange::new(
+                        &override_item.name,
+                        ChangeKind::Changed,
+                        url.clone(),
+                        url,
+                    );
+                    change.reason = Some(reason);
+                    change.download_size = validators.size;
+                    diff.overrides.push(change);
+                }
+            }
+        }
+    }
+
+    let diff = diff.finalize();
+    eprintln!("{}", diff.summary);
+    Ok(diff)
+}
+
+// Where install progress goes. The app forwards it to the window as events; headless
+// callers and tests can log it, collect it or drop it.
+pub trait ProgressSink: Send + Sync {
+    fn progress(&self, event: &str, payload: serde_json::Value);
+}
+
+// Progress sink that drops every event
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn progress(&self, _event: &str, _payload: serde_json::Value) {}
+}
+
+// Everything an install needs from its surroundings: the launcher data directory (settings,
+// blob store, caches), the Modrinth API and where to report progress. Commands build it
+// from their window; tests and headless tools point it at a directory of their own.
+#[derive(Clone)]
+pub struct LauncherContext {
+    data_dir: PathBuf,
+    api: ModrinthApi,
+    progress: Arc<dyn ProgressSink>,
+    session: Arc<InstallSession>,
+}
+
+impl LauncherContext {
+    // The Modrinth API is configured from settings.json in the data directory
+    pub fn new(data_dir: PathBuf, progress: Arc<dyn ProgressSink>) -> Result<Self, String> {
+        let api = ModrinthApi::new(&load_launcher_settings(&data_dir).modrinth)?;
+        Ok(LauncherContext {
+            data_dir,
+            api,
+            progress,
+            session: Arc::default(),
+        })
+    }
+
+    // A copy of the context for one install, with nothing recorded yet
+    pub(crate) fn begin_install(&self) -> LauncherContext {
+        LauncherContext {
+            session: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn session(&self) -> &InstallSession {
+        &self.session
+    }
+
+    pub fn with_api(mut self, api: ModrinthApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn api(&self) -> &ModrinthApi {
+        &self.api
+    }
+
+    pub fn settings(&self) -> LauncherSettings {
+        load_launcher_settings(&self.data_dir)
+    }
+
+    pub fn blob_store(&self) -> BlobStore {
+        BlobStore::new(self.data_dir.join("blobs"))
+    }
+
+    pub fn response_cache(&self, offline: bool) -> ResponseCache {
+        ResponseCache::new(self.data_dir.join("offline"), offline)
+    }
+
+    // Override zips and their hash registry
+    pub fn zip_cache_dir(&self) -> PathBuf {
+        self.data_dir.join("cache")
+    }
+
+    pub fn emit(&self, event: &str, payload: serde_json::Value) {
+        self.progress.progress(event, payload);
+    }
+}
+
+pub(crate) struct LockedInstall {
+    pub(crate) resolved: Vec<ResolvedMod>,
+    pub(crate) skipped: usize,
+}
+
+// Link every locked mod jar into the mods folder. Jars already in place with the
+// locked hash are left alone; extra mods among them count as skipped.
+pub(crate) async fn install_locked_mods(
+    ctx: &LauncherContext,
+    client: &reqwest::Client,
+    cache: &ResponseCache,
+    mods: &[LockedMod],
+    mods_dir: &Path,
+) -> Result<LockedInstall, String> {
+    let store = ctx.blob_store();
+    let mut installed = LockedInstall {
+        resolved: Vec::new(),
+        skipped: 0,
+    };
+
+    for (index, locked) in mods.iter().enumerate() {
+        let kind = if locked.requested.is_some() {
+            "extra mod"
+        } else {
+            "dependency"
+        };
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": 50 + ((index as f64 / mods.len() as f64) * 25.0) as u32,
+                "current": index + 1,
+                "total": mods.len(),
+                "filename": format!("Installing {} ({}/{}): {}", kind, index + 1, mods.len(), locked.file.filename),
+                "stage": "extra_mods"
+            }),
+        );
+
+        // A published lockfile must not be able to write outside the mods folder
+        let file_name = Path::new(&locked.file.filename).file_name();
+        if file_name != Some(std::ffi::OsStr::new(&locked.file.filename)) {
+            cache.record_missing(format!(
+                "{} {}: invalid filename {}",
+                kind, locked.name, locked.file.filename
+            ));
+            continue;
+        }
+        // Direct-URL mods may name a path outside mods/, still inside the game directory
+        let target = locked.requested.as_ref().and_then(|r| r.target_path());
+        let jar_path = match target {
+            Some(target) => match mods_dir.parent().and_then(|dir| safe_join(dir, &target)) {
+                Some(path) => path,
+                None => {
+                    cache.record_missing(format!(
+                        "{} {}: invalid path {}",
+                        kind, locked.name, target
+                    ));
+                    continue;
+                }
+            },
+            None => mods_dir.join(&locked.file.filename),
+        };
+        if let Some(parent) = jar_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let in_place = jar_path.is_file()
+            && sha512_file(&jar_path).is_ok_and(|h| h.eq_ignore_ascii_case(&locked.file.sha512));
+        if in_place {
+            eprintln!("Already installed: {}", locked.file.filename);
+            if locked.requested.is_some() {
+                installed.skipped += 1;
+            }
+        } else {
+            eprintln!(
+                "Installing {} {} {} ({})",
+                kind, locked.name, locked.file.version_number, locked.file.filename
+            );
+            let linked = match fetch_into_blob_store(
+                client,
+                &store,
+                std::slice::from_ref(&locked.file.url),
+                Some(&locked.file.sha512),
+                cache.offline,
+            )
+            .await
+            {
+                Ok(sha512) => store.link_to(&sha512, &jar_path),
+                Err(e) => Err(e),
+            };
+            match linked {
+                Ok(true) => {}
+                Ok(false) => {
+                    cache.record_missing(format!(
+                        "{} {} ({}): not in the blob store",
+                        kind, locked.name, locked.file.filename
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    cache.record_missing(format!(
+                        "{} {} ({}): {}",
+                        kind, locked.name, locked.file.filename, e
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        ctx.session().track_downloaded_file(&locked.file.filename);
+        ctx.session().track_managed_mod(
+            &jar_path,
+            &locked.file.sha512,
+            ManagedSource::ExtraMod {
+                name: locked.file.project_id.clone(),
+                urls: vec![locked.file.url.clone()],
+            },
+        );
+        if locked.requested.is_some() {
+            installed.resolved.push(locked.resolved());
+        }
+    }
+
+    Ok(installed)
+}
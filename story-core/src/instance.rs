@@ -0,0 +1,932 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::cache::{cached_zip_path, fetch_into_blob_store, sha256_file, sha512_file};
+use crate::install::{download_zip_to_cache, LauncherContext};
+use crate::manifest::StoryManifest;
+use crate::modrinth::{ModrinthIndex, ModrinthVersionResponse};
+
+pub fn check_story_instance(instance_base: String, folder_name: String) -> bool {
+    let story_path = Path::new(&instance_base).join(folder_name);
+    // Check if the Story instance directory exists
+    eprintln!("Checking for Story instance at: {:?}", story_path);
+    // also check if the dir has a instance.cfg
+    story_path.exists()
+}
+
+// Check if Base Is Installed (check for npcmessageparser-1.0-SNAPSHOT.jar)
+pub fn is_base_installed(instance_base: String) -> bool {
+    let base_path = Path::new(&instance_base).join("npcmessageparser-1.0-SNAPSHOT.jar");
+    base_path.exists()
+}
+
+pub fn check_path_exists(path: String) -> bool {
+    let path = Path::new(&path);
+    path.exists() && path.is_dir()
+}
+
+pub async fn repair_instance_files(
+    ctx: &LauncherContext,
+    instance_base: &str,
+    remove_extra: bool,
+    offline: bool,
+) -> Result<RepairReport, String> {
+    let story_path = Path::new(&instance_base).join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+    if !story_path.exists() {
+        return Err(format!("Instance not found: {}", story_path.display()));
+    }
+
+    let verify_path = story_path.clone();
+    let verify_report = tokio::task::spawn_blocking(move || verify_instance_files(&verify_path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))??;
+
+    let broken: Vec<ManagedFile> = verify_report
+        .missing
+        .iter()
+        .chain(verify_report.modified.iter())
+        .cloned()
+        .collect();
+    eprintln!("=== REPAIRING {} FILES ===", broken.len());
+
+    let client = ctx.api().http();
+    let store = ctx.blob_store();
+    let zip_cache_dir = ctx.zip_cache_dir();
+    let mut report = RepairReport::default();
+    let mut broken_overrides: Vec<(String, String, Option<String>, Vec<ManagedFile>)> = Vec::new();
+
+    for (index, file) in broken.iter().enumerate() {
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": ((index as f64 / broken.len() as f64) * 100.0) as u32,
+                "current": index + 1,
+                "total": broken.len(),
+                "filename": format!("Repairing: {}", file.path),
+                "stage": "repair"
+            }),
+        );
+
+        let urls = match &file.source {
+            ManagedSource::Mrpack { urls }
+            | ManagedSource::ExtraMod { urls, .. }
+            | ManagedSource::Packwiz { urls } => urls,
+            ManagedSource::Override { name, url, sha256 } => {
+                // Overrides are repaired per zip below
+                match broken_overrides.iter_mut().find(|(_, u, _, _)| u == url) {
+                    Some((_, _, _, files)) => files.push(file.clone()),
+                    None => broken_overrides.push((
+                        name.clone(),
+                        url.clone(),
+                        sha256.clone(),
+                        vec![file.clone()],
+                    )),
+                }
+                continue;
+            }
+        };
+
+        let dest = match safe_join(&minecraft_dir, &file.path) {
+            Some(dest) => dest,
+            None => {
+                report.failed.push(format!("{}: unsafe path", file.path));
+                continue;
+            }
+        };
+
+        store.evict_if_corrupt(&file.sha512);
+        let result =
+            match fetch_into_blob_store(client, &store, urls, Some(&file.sha512), offline).await {
+                Ok(sha512) => store.link_to(&sha512, &dest).map(|_| ()),
+                Err(e) => Err(e),
+            };
+        match result {
+            Ok(()) => report.repaired.push(file.path.clone()),
+            Err(e) => report.failed.push(format!("{}: {}", file.path, e)),
+        }
+    }
+
+    for (name, url, sha256, files) in broken_overrides {
+        eprintln!("Repairing {} files from override {}", files.len(), name);
+        let zip_path = cached_zip_path(&zip_cache_dir, &url);
+
+        // Fetch the zip again if it's no longer cached or no longer matches its pin. Only the
+        // broken entries are extracted, so healthy and player-edited files stay as they are.
+        let cached_ok = zip_path.is_file()
+            && sha256.as_deref().is_none_or(|expected| {
+                sha256_file(&zip_path).is_ok_and(|hash| hash.eq_ignore_ascii_case(expected))
+            });
+        if !cached_ok {
+            if let Err(e) = download_zip_to_cache(ctx, url.clone(), offline, sha256.clone()).await {
+                for file in &files {
+                    report.failed.push(format!("{}: {}", file.path, e));
+                }
+                continue;
+            }
+        }
+
+        let names: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+        if let Err(e) = extract_zip_entries(&zip_path, &minecraft_dir, &names) {
+            for file in &files {
+                report.failed.push(format!("{}: {}", file.path, e));
+            }
+            continue;
+        }
+
+        // The override may have changed upstream since it was installed
+        for file in files {
+            let repaired = safe_join(&minecraft_dir, &file.path)
+                .and_then(|path| sha512_file(&path).ok())
+                .is_some_and(|actual| actual.eq_ignore_ascii_case(&file.sha512));
+            if repaired {
+                report.repaired.push(file.path);
+            } else {
+                report.failed.push(format!(
+                    "{}: override {} changed since install, reinstall from the manifest",
+                    file.path, name
+                ));
+            }
+        }
+    }
+
+    if remove_extra {
+        for extra in &verify_report.extra {
+            if let Some(path) = safe_join(&minecraft_dir, extra) {
+                match std::fs::remove_file(&path) {
+                    Ok(_) => report.removed.push(extra.clone()),
+                    Err(e) => report.failed.push(format!("{}: {}", extra, e)),
+                }
+            }
+        }
+    }
+
+    ctx.emit(
+        "download_progress",
+        serde_json::json!({
+            "percent": 100,
+            "current": broken.len(),
+            "total": broken.len(),
+            "filename": "Repair completed",
+            "stage": "repair"
+        }),
+    );
+
+    eprintln!(
+        "Repair finished: {} repaired, {} failed, {} removed",
+        report.repaired.len(),
+        report.failed.len(),
+        report.removed.len()
+    );
+    Ok(report)
+}
+
+// Delete files in mods/, or a pack folder the launcher installs packs into, that neither
+// the mrpack index nor .managed_files.json account for.
+// With `dry_run`, only report what would be removed.
+pub fn clean_instance(instance_base: &str, dry_run: bool) -> Result<CleanReport, String> {
+    let story_path = Path::new(instance_base).join("Story");
+    if !story_path.exists() {
+        return Err(format!("Instance not found: {}", story_path.display()));
+    }
+    let minecraft_dir = story_path.join(".minecraft");
+    let verify_report = verify_instance_files(&story_path)?;
+
+    let mut report = CleanReport {
+        dry_run,
+        ..CleanReport::default()
+    };
+    for extra in verify_report.extra {
+        let path = match safe_join(&minecraft_dir, &extra) {
+            Some(path) => path,
+            None => continue,
+        };
+        if dry_run {
+            report.removed.push(extra);
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(_) => {
+                eprintln!("🗑️ Removed unmanaged file {}", extra);
+                report.removed.push(extra);
+            }
+            Err(e) => report.failed.push(format!("{}: {}", extra, e)),
+        }
+    }
+    Ok(report)
+}
+
+// create Story instance with configurable folder name
+pub fn create_story_instance(instance_base: String, folder_name: String) -> Result<String, String> {
+    let story_path = Path::new(&instance_base).join(&folder_name);
+    // Create the Story instance directory
+    std::fs::create_dir_all(&story_path).map_err(|e| e.to_string())?;
+    eprintln!("Created instance at: {:?}", story_path);
+    eprintln!("Finalizing instance at {}", instance_base);
+
+    // Use path joining for cross-platform compatibility
+    let full_path = story_path.to_string_lossy().to_string();
+    match finalize_instance(full_path) {
+        Ok(_) => eprintln!("Instance finalized successfully"),
+        Err(e) => {
+            eprintln!("Failed to finalize instance: {}", e);
+            return Err(format!("Failed to finalize instance: {}", e));
+        }
+    }
+    Ok(story_path.to_string_lossy().into_owned())
+}
+
+pub fn finalize_instance(instance_path: String) -> Result<(), String> {
+    let instance_dir = PathBuf::from(instance_path);
+    let _mrpack_dir = instance_dir.join("mrpack");
+    let mc_dir = instance_dir.join(".minecraft");
+    let mods_dir = mc_dir.join("mods");
+
+    // Ensure mods dir exists
+    fs::create_dir_all(&mods_dir).map_err(|e| e.to_string())?;
+
+    // Write instance.cfg
+    let instance_cfg = r#"[General]
+ConfigVersion=1.2
+ManagedPack=true
+iconKey=modrinth_fabulously-optimized
+ManagedPackID=1KVo5zza
+ManagedPackType=modrinth
+ManagedPackName=Fabulously Optimized
+ManagedPackVersionID=iRJMsGhm
+ManagedPackVersionName=6.4.0
+name=Story
+InstanceType=OneSix
+"#;
+    fs::write(instance_dir.join("instance.cfg"), instance_cfg)
+        .map_err(|e| format!("Failed to write instance.cfg: {}", e))?;
+
+    // Write mmc-pack.json
+    let mmc_pack_json = r#"{
+    "components": [
+        {
+            "cachedName": "Minecraft",
+            "cachedRequires": [
+                { "suggests": "3.3.3", "uid": "org.lwjgl3" }
+            ],
+            "cachedVersion": "1.21.1",
+            "important": true,
+            "uid": "net.minecraft",
+            "version": "1.21.1"
+        },
+        {
+            "cachedName": "Fabric Loader",
+            "cachedRequires": [
+                { "uid": "net.fabricmc.intermediary" }
+            ],
+            "cachedVersion": "0.16.14",
+            "uid": "net.fabricmc.fabric-loader",
+            "version": "0.16.14"
+        }
+    ],
+    "formatVersion": 1
+}"#;
+    fs::write(instance_dir.join("mmc-pack.json"), mmc_pack_json)
+        .map_err(|e| format!("Failed to write mmc-pack.json: {}", e))?;
+
+    Ok(())
+}
+
+pub fn create_instance_config(
+    story_path: &Path,
+    version_info: &ModrinthVersionResponse,
+) -> Result<(), String> {
+    eprintln!("Creating instance configuration files");
+
+    // Create instance.cfg
+    let instance_cfg = format!(
+        r#"[General]
+ConfigVersion=1.2
+ManagedPack=true
+iconKey=modrinth_{0}
+ManagedPackID={1}
+ManagedPackType=modrinth
+ManagedPackName={2}
+ManagedPackVersionID={3}
+ManagedPackVersionName={4}
+name=Story
+InstanceType=OneSix
+"#,
+        version_info.project_id,
+        version_info.project_id,
+        version_info.name,
+        version_info.id,
+        version_info.version_number
+    );
+
+    std::fs::write(story_path.join("instance.cfg"), instance_cfg)
+        .map_err(|e| format!("Failed to write instance.cfg: {}", e))?;
+
+    // Determine Minecraft version and loader from the version info
+    let minecraft_version = version_info
+        .game_versions
+        .first()
+        .ok_or("No game version found")?;
+    let loader = version_info.loaders.first().ok_or("No loader found")?;
+
+    write_mmc_pack(story_path, minecraft_version, loader, None)
+}
+
+// Instance files for a pack installed from an .mrpack file or URL rather than a Modrinth
+// project. The pack is not linked to Modrinth, so MultiMC never tries to update it.
+pub fn create_local_instance_config(
+    story_path: &Path,
+    index: &ModrinthIndex,
+) -> Result<(), String> {
+    create_unmanaged_instance_config(story_path, &index.name, &index.dependencies)
+}
+
+// instance.cfg and mmc-pack.json for an instance no Modrinth project manages, from
+// mrpack-style dependencies ("minecraft", "fabric-loader", ...)
+pub(crate) fn create_unmanaged_instance_config(
+    story_path: &Path,
+    name: &str,
+    dependencies: &HashMap<String, String>,
+) -> Result<(), String> {
+    eprintln!("Creating instance configuration files for {}", name);
+
+    let instance_cfg = r#"[General]
+ConfigVersion=1.2
+ManagedPack=false
+name=Story
+InstanceType=OneSix
+"#;
+    std::fs::write(story_path.join("instance.cfg"), instance_cfg)
+        .map_err(|e| format!("Failed to write instance.cfg: {}", e))?;
+
+    let minecraft_version = dependencies
+        .get("minecraft")
+        .ok_or("The modpack does not name a Minecraft version")?;
+    let (loader, loader_version) = if let Some(version) = dependencies.get("fabric-loader") {
+        ("fabric", Some(version.as_str()))
+    } else if let Some(version) = dependencies.get("quilt-loader") {
+        ("quilt", Some(version.as_str()))
+    } else if let Some(version) = dependencies.get("neoforge") {
+        ("neoforge", Some(version.as_str()))
+    } else if let Some(version) = dependencies.get("forge") {
+        ("forge", Some(version.as_str()))
+    } else {
+        ("vanilla", None)
+    };
+
+    write_mmc_pack(story_path, minecraft_version, loader, loader_version)
+}
+
+// mmc-pack.json for a Minecraft version and loader. Fabric gets its loader component,
+// pinned to `loader_version` when the pack names one.
+fn write_mmc_pack(
+    story_path: &Path,
+    minecraft_version: &str,
+    loader: &str,
+    loader_version: Option<&str>,
+) -> Result<(), String> {
+    let mmc_pack_json = if loader == "fabric" {
+        format!(
+            r#"{{
+    "components": [
+        {{
+            "cachedName": "Minecraft",
+            "cachedRequires": [
+                {{ "suggests": "3.3.3", "uid": "org.lwjgl3" }}
+            ],
+            "cachedVersion": "{0}",
+            "important": true,
+            "uid": "net.minecraft",
+            "version": "{0}"
+        }},
+        {{
+            "cachedName": "Fabric Loader",
+            "cachedRequires": [
+                {{ "uid": "net.fabricmc.intermediary" }}
+            ],
+            "cachedVersion": "{1}",
+            "uid": "net.fabricmc.fabric-loader",
+            "version": "{1}"
+        }}
+    ],
+    "formatVersion": 1
+}}"#,
+            minecraft_version,
+            loader_version.unwrap_or("0.16.14")
+        )
+    } else {
+        // Default/NeoForge configuration
+        format!(
+            r#"{{
+    "components": [
+        {{
+            "cachedName": "Minecraft",
+            "cachedVersion": "{0}",
+            "important": true,
+            "uid": "net.minecraft",
+            "version": "{0}"
+        }}
+    ],
+    "formatVersion": 1
+}}"#,
+            minecraft_version
+        )
+    };
+
+    std::fs::write(story_path.join("mmc-pack.json"), mmc_pack_json)
+        .map_err(|e| format!("Failed to write mmc-pack.json: {}", e))?;
+
+    Ok(())
+}
+
+// Function to cleanup extra JAR files not in current manifest
+pub(crate) async fn cleanup_extra_jars(
+    ctx: &LauncherContext,
+    story_path: &Path,
+    manifest: &StoryManifest,
+) -> Result<usize, String> {
+    let mods_dir = story_path.join(".minecraft").join("mods");
+
+    if !mods_dir.exists() {
+        eprintln!("Mods directory doesn't exist, skipping cleanup");
+        return Ok(0);
+    }
+
+    // Get the list of all files that were downloaded in this session (including dependencies)
+    let current_session_files = ctx.session().downloaded_files();
+
+    // Save the current manifest locally for future comparison
+    let manifest_file = story_path.join(".current_manifest.json");
+    let manifest_json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    std::fs::write(&manifest_file, manifest_json)
+        .map_err(|e| format!("Failed to save manifest: {}", e))?;
+
+    eprintln!("💾 Saved current manifest to: {}", manifest_file.display());
+
+    // Save the complete list of downloaded files (including dependencies) for future comparison
+    let downloaded_files_list = story_path.join(".downloaded_files.json");
+    let files_json = serde_json::to_string_pretty(&current_session_files)
+        .map_err(|e| format!("Failed to serialize downloaded files: {}", e))?;
+
+    std::fs::write(&downloaded_files_list, files_json)
+        .map_err(|e| format!("Failed to save downloaded files list: {}", e))?;
+
+    eprintln!(
+        "💾 Saved downloaded files list to: {}",
+        downloaded_files_list.display()
+    );
+
+    eprintln!(
+        "Current session downloaded files ({}): {:?}",
+        current_session_files.len(),
+        current_session_files
+    );
+
+    // For now, let's be conservative and only delete files that are clearly problematic
+    // We'll implement a more sophisticated cleanup later that compares against previous manifests
+    eprintln!("⚠️ Cleanup disabled for now - preserving all existing files");
+    eprintln!("📝 All downloaded files in this session are tracked and will be preserved");
+
+    Ok(0)
+}
+
+// Helper function to extract mod name from filename
+pub fn extract_mod_name_from_filename(filename: &str) -> String {
+    let mut name = filename.to_lowercase();
+
+    // Remove common loader suffixes first (case insensitive)
+    let loader_patterns = vec![
+        "_fabric_",
+        "_forge_",
+        "_neoforge_",
+        "_quilt_",
+        "-fabric-",
+        "-forge-",
+        "-neoforge-",
+        "-quilt-",
+        "_fabric",
+        "_forge",
+        "_neoforge",
+        "_quilt",
+        "-fabric",
+        "-forge",
+        "-neoforge",
+        "-quilt",
+    ];
+
+    for pattern in loader_patterns {
+        if let Some(pos) = name.find(pattern) {
+            name = name[..pos].to_string();
+            break;
+        }
+    }
+
+    // Remove version patterns by looking for common patterns
+    // Look for patterns like _v6.1.12_, _mc1.21_, etc.
+    let version_patterns = vec!["_v", "_mc", "-v", "-mc", "+v", "+mc"];
+
+    for pattern in version_patterns {
+        if let Some(pos) = name.find(pattern) {
+            name = name[..pos].to_string();
+            break;
+        }
+    }
+
+    // Also look for pure version numbers at the end
+    let parts: Vec<&str> = name.split(&['-', '_', '+'][..]).collect();
+    let mut clean_parts = Vec::new();
+
+    for part in parts {
+        // Skip if this looks like a version number
+        if part.is_empty() {
+            continue;
+        }
+
+        // Check if this part looks like a version
+        if part.chars().next().is_some_and(|c| c.is_ascii_digit()) ||
+           part.starts_with("1.") || // Minecraft versions like 1.21.1
+           part.chars().all(|c| c.is_ascii_digit() || c == '.')
+        {
+            // Pure version numbers
+            break; // Stop processing once we hit a version-like part
+        }
+
+        clean_parts.push(part);
+    }
+
+    // Join the clean parts back together
+    let result = clean_parts
+        .join("-")
+        .trim_end_matches('-')
+        .trim_end_matches('_')
+        .to_string();
+
+    // Final cleanup - normalize separators
+    result.replace("_", "-")
+}
+
+// Helper function to normalize mod names for comparison
+pub fn normalize_mod_name(name: &str) -> String {
+    name.to_lowercase()
+        .replace("_", "-")
+        .replace(" ", "-")
+        .replace("--", "-") // Remove double dashes
+        .replace("-", "") // Remove all dashes for better matching
+        .trim_matches('-') // Remove leading/trailing dashes
+        .to_string()
+}
+
+// Files the launcher placed in an instance beyond the mrpack index, recorded in
+// .managed_files.json so they can be verified and repaired later
+#[derive(Serialize, Deserialize, Default)]
+pub struct ManagedFiles {
+    pub files: Vec<ManagedFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ManagedFile {
+    pub path: String, // relative to .minecraft, always with forward slashes
+    pub sha512: String,
+    pub source: ManagedSource,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ManagedSource {
+    Mrpack {
+        urls: Vec<String>,
+    },
+    ExtraMod {
+        name: String,
+        urls: Vec<String>,
+    },
+    Packwiz {
+        urls: Vec<String>,
+    },
+    Override {
+        name: String,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sha256: Option<String>,
+    },
+}
+
+pub fn load_managed_files(story_path: &Path) -> ManagedFiles {
+    std::fs::read_to_string(story_path.join(".managed_files.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_managed_files(story_path: &Path, managed: &ManagedFiles) -> Result<(), String> {
+    let managed_json = serde_json::to_string_pretty(managed)
+        .map_err(|e| format!("Failed to serialize managed files: {}", e))?;
+    std::fs::write(story_path.join(".managed_files.json"), managed_json)
+        .map_err(|e| format!("Failed to write managed files: {}", e))
+}
+
+// Path of `path` relative to `base` with forward slashes, if it lives inside `base`
+fn relative_slash_path(base: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(parts.join("/"))
+}
+
+// Resolve a relative instance path, refusing anything that escapes the instance
+pub(crate) fn safe_join(base: &Path, relative: &str) -> Option<PathBuf> {
+    let relative_path = Path::new(relative);
+    let escapes = relative_path.components().any(|c| {
+        matches!(
+            c,
+            std::path::Component::ParentDir
+                | std::path::Component::RootDir
+                | std::path::Component::Prefix(_)
+        )
+    });
+    if escapes {
+        None
+    } else {
+        Some(base.join(relative_path))
+    }
+}
+
+// sha512 of every file inside a zip, keyed by entry path (skips the legacy manifest.json)
+pub fn zip_entry_hashes(zip_path: &Path) -> Result<Vec<(String, String)>, String> {
+    let file = File::open(zip_path).map_err(|e| format!("Failed to open zip file: {}", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read zip entry {}: {}", i, e))?;
+        if entry.is_dir() || entry.name() == "manifest.json" {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        let mut hasher = Sha512::new();
+        let mut buffer = [0u8; 65536];
+        loop {
+            let n = entry.read(&mut buffer).map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+        }
+        entries.push((name, format!("{:x}", hasher.finalize())));
+    }
+
+    Ok(entries)
+}
+
+// Extract only the named entries of a zip into `extract_path`
+fn extract_zip_entries(
+    zip_path: &Path,
+    extract_path: &Path,
+    names: &[String],
+) -> Result<(), String> {
+    let file = File::open(zip_path).map_err(|e| format!("Failed to open zip file: {}", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+    for name in names {
+        let mut entry = archive
+            .by_name(name)
+            .map_err(|e| format!("{} not found in {}: {}", name, zip_path.display(), e))?;
+        let out_path = safe_join(extract_path, name)
+            .ok_or_else(|| format!("Refusing to extract {} outside the instance", name))?;
+
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Don't write through a hardlink into the blob store
+        if out_path.is_file() {
+            std::fs::remove_file(&out_path).map_err(|e| e.to_string())?;
+        }
+        let mut out_file = File::create(&out_path).map_err(|e| e.to_string())?;
+        std::io::copy(&mut entry, &mut out_file).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub missing: Vec<ManagedFile>,
+    pub modified: Vec<ManagedFile>,
+    pub extra: Vec<String>, // unmanaged files in mods/ and managed pack folders
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty()
+    }
+}
+
+// Every file the instance should contain: the mrpack index plus tracked extra mods and overrides
+pub fn expected_instance_files(story_path: &Path) -> Result<Vec<ManagedFile>, String> {
+    let mut expected = Vec::new();
+
+    let index_path = story_path.join("mrpack").join("modrinth.index.json");
+    if index_path.exists() {
+        let index_content = std::fs::read_to_string(&index_path)
+            .map_err(|e| format!("Failed to read modrinth.index.json: {}", e))?;
+        let index: ModrinthIndex = serde_json::from_str(&index_content)
+            .map_err(|e| format!("Failed to parse modrinth.index.json: {}", e))?;
+
+        for file in index.files {
+            match file.hashes.get("sha512") {
+                Some(sha512) => expected.push(ManagedFile {
+                    path: file.path.replace('\\', "/"),
+                    sha512: sha512.to_lowercase(),
+                    source: ManagedSource::Mrpack {
+                        urls: file.downloads,
+                    },
+                }),
+                None => eprintln!("Skipping {}: no sha512 in modrinth.index.json", file.path),
+            }
+        }
+    }
+
+    // Later entries win, so tracked files override the index for the same path
+    let managed = load_managed_files(story_path);
+    for file in managed.files {
+        expected.retain(|existing| existing.path != file.path);
+        expected.push(file);
+    }
+
+    Ok(expected)
+}
+
+// verify_instance_files for the Story instance under `instance_base`, off the async runtime
+pub async fn verify_instance(instance_base: &str) -> Result<VerifyReport, String> {
+    let story_path = Path::new(instance_base).join("Story");
+    if !story_path.exists() {
+        return Err(format!("Instance not found: {}", story_path.display()));
+    }
+
+    tokio::task::spawn_blocking(move || verify_instance_files(&story_path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+// Folders besides mods/ that verify checks for extra files, once the launcher has put
+// packs there
+const PACK_DIRS: [&str; 3] = ["resourcepacks", "shaderpacks", "datapacks"];
+
+// Re-hash every managed file of an instance and compare against what was installed
+pub fn verify_instance_files(story_path: &Path) -> Result<VerifyReport, String> {
+    let minecraft_dir = story_path.join(".minecraft");
+    let expected = expected_instance_files(story_path)?;
+    let mut report = VerifyReport::default();
+
+    for file in &expected {
+        let full_path = match safe_join(&minecraft_dir, &file.path) {
+            Some(path) => path,
+            None => {
+                eprintln!("Skipping unsafe managed path: {}", file.path);
+                continue;
+            }
+        };
+        report.checked += 1;
+
+        if !full_path.is_file() {
+            eprintln!("Missing: {}", file.path);
+            report.missing.push(file.clone());
+            continue;
+        }
+
+        match sha512_file(&full_path) {
+            Ok(actual) if actual.eq_ignore_ascii_case(&file.sha512) => {}
+            Ok(_) => {
+                eprintln!("Modified: {}", file.path);
+                report.modified.push(file.clone());
+            }
+            Err(e) => {
+                eprintln!("Failed to hash {}: {}", file.path, e);
+                report.modified.push(file.clone());
+            }
+        }
+    }
+
+    // Anything the launcher didn't put in mods/, or in a pack folder it installed packs
+    // into. Every other folder is left alone, since the game writes its own files there.
+    let managed_paths: HashSet<&str> = expected.iter().map(|f| f.path.as_str()).collect();
+    let content_dirs = std::iter::once("mods").chain(PACK_DIRS.into_iter().filter(|dir| {
+        expected.iter().any(|f| {
+            f.path
+                .rsplit_once('/')
+                .is_some_and(|(parent, _)| parent == *dir)
+        })
+    }));
+    for dir in content_dirs {
+        let Some(dir) = safe_join(&minecraft_dir, dir) else {
+            continue;
+        };
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(relative) = relative_slash_path(&minecraft_dir, &path) {
+                if !managed_paths.contains(relative.as_str()) {
+                    report.extra.push(relative);
+                }
+            }
+        }
+    }
+    report.extra.sort();
+
+    eprintln!(
+        "Verified {} files: {} missing, {} modified, {} extra",
+        report.checked,
+        report.missing.len(),
+        report.modified.len(),
+        report.extra.len()
+    );
+    Ok(report)
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RepairReport {
+    pub repaired: Vec<String>,
+    pub failed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct CleanReport {
+    pub dry_run: bool,
+    pub removed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+// Write .managed_files.json from this session's extra mods and the extracted overrides
+pub(crate) fn record_managed_files(
+    ctx: &LauncherContext,
+    story_path: &Path,
+    override_files: Vec<ManagedFile>,
+) -> Result<usize, String> {
+    let minecraft_dir = story_path.join(".minecraft");
+    let mut files: Vec<ManagedFile> = Vec::new();
+
+    for (path, (sha512, source)) in ctx.session().managed_mods() {
+        if let Some(relative) = relative_slash_path(&minecraft_dir, &path) {
+            files.push(ManagedFile {
+                path: relative,
+                sha512,
+                source,
+            });
+        }
+    }
+
+    for file in override_files {
+        files.retain(|existing| existing.path != file.path);
+        files.push(file);
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let count = files.len();
+    save_managed_files(story_path, &ManagedFiles { files })?;
+    Ok(count)
+}
+
+// Public wrapper functions for testing
+pub fn test_check_story_instance(instance_base: String, folder_name: String) -> bool {
+    check_story_instance(instance_base, folder_name)
+}
+
+pub fn test_is_base_installed(instance_base: String) -> bool {
+    is_base_installed(instance_base)
+}
+
+pub fn test_check_path_exists(path: String) -> bool {
+    check_path_exists(path)
+}
+
+pub fn test_create_story_instance(
+    instance_base: String,
+    folder_name: String,
+) -> Result<String, String> {
+    create_story_instance(instance_base, folder_name)
+}
+
+pub fn test_finalize_instance(instance_path: String) -> Result<(), String> {
+    finalize_instance(instance_path)
+}
@@ -0,0 +1,27 @@
+// Everything the launcher does short of showing a window: reading manifests, talking to
+// Modrinth, the download caches and installing into an instance. The Tauri app and
+// story-cli are thin front ends over it.
+
+pub mod cache;
+pub mod curseforge;
+pub mod export;
+pub mod install;
+pub mod instance;
+pub mod lockfile;
+pub mod manifest;
+pub mod modrinth;
+pub mod packwiz;
+pub mod server;
+pub mod settings;
+
+pub use cache::*;
+pub use curseforge::*;
+pub use export::*;
+pub use install::*;
+pub use instance::*;
+pub use lockfile::*;
+pub use manifest::*;
+pub use modrinth::*;
+pub use packwiz::*;
+pub use server::*;
+pub use settings::*;
@@ -0,0 +1,1146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::cache::{fetch_into_blob_store, sha256_hex, BlobStore, ResponseCache};
+use crate::install::LauncherContext;
+use crate::manifest::{
+    parse_manifest, ExtraMod, InstanceConfig, ResolvedMod, StoryManifest, VersionPreference,
+};
+use crate::modrinth::{
+    cache_response, candidate_versions_url, fetch_project_versions, fetch_project_versions_bulk,
+    fetch_versions_bulk, fetch_versions_by_hash, find_best_mod_version, loader_fallback,
+    minecraft_version_fallback, pack_projects, pick_best_mod_version, primary_jar,
+    read_mrpack_index, resolve_mod_version, select_mod_file, ModrinthApi, ModrinthFile,
+    ModrinthIndex, ModrinthVersionResponse,
+};
+
+// Current lockfile format
+pub const LOCKFILE_VERSION: u32 = 1;
+
+// The lockfile an instance keeps from its last install, inside the Story folder
+pub const LOCKFILE_NAME: &str = "story.lock.json";
+
+// A manifest resolved to exact files: the pack version plus every extra mod and
+// required dependency, with the URL and hashes to fetch each one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoryLock {
+    pub lock_version: u32,
+    pub generated_at: String,
+    pub instance_name: String,
+    pub instance_version: String,
+    pub minecraft_version: String,
+    pub loader: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loader_fallbacks: Vec<String>,
+    #[serde(default)]
+    pub preference: VersionPreference,
+    pub pack: Option<LockedFile>,
+    pub mods: Vec<LockedMod>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedFile {
+    pub project_id: String,
+    pub version_id: String,
+    pub version_number: String,
+    pub filename: String,
+    pub url: String,
+    pub sha512: String,
+    pub size: u64,
+}
+
+impl LockedFile {
+    pub fn from_version(
+        version_info: &ModrinthVersionResponse,
+        file: &ModrinthFile,
+    ) -> Result<Self, String> {
+        let sha512 = file
+            .hashes
+            .get("sha512")
+            .ok_or_else(|| format!("{} has no sha512 hash to lock", file.filename))?;
+        Ok(LockedFile {
+            project_id: version_info.project_id.clone(),
+            version_id: version_info.id.clone(),
+            version_number: version_info.version_number.clone(),
+            filename: file.filename.clone(),
+            url: file.url.clone(),
+            sha512: sha512.to_lowercase(),
+            size: file.size,
+        })
+    }
+
+    // Lock a direct-URL extra mod as the manifest gives it; there is nothing to resolve.
+    // `size` is that of the downloaded jar, since the manifest doesn't give one.
+    pub fn direct(extra_mod: &ExtraMod, size: u64) -> Result<Self, String> {
+        let url = extra_mod
+            .url
+            .as_ref()
+            .ok_or_else(|| "no download URL".to_string())?;
+        let sha512 = extra_mod
+            .sha512
+            .as_ref()
+            .ok_or_else(|| format!("{} has no sha512 hash to lock", url))?;
+        let target = extra_mod.target_path().unwrap_or_default();
+        let filename = target.rsplit('/').next().unwrap_or_default();
+        if filename.is_empty() {
+            return Err(format!("no filename in {}", target));
+        }
+        Ok(LockedFile {
+            project_id: extra_mod.name.clone(),
+            version_id: String::new(),
+            version_number: extra_mod
+                .version
+                .clone()
+                .unwrap_or_else(|| "direct".to_string()),
+            filename: filename.to_string(),
+            url: url.clone(),
+            sha512: sha512.to_lowercase(),
+            size,
+        })
+    }
+}
+
+// A locked mod jar. `requested` is the manifest entry for extra mods and None for
+// dependencies; `dependencies` are the project ids of its required dependencies locked
+// here (ones the modpack provides are left out), `incompatible` what it can't run with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedMod {
+    pub name: String,
+    #[serde(flatten)]
+    pub file: LockedFile,
+    pub requested: Option<ExtraMod>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub incompatible: Vec<ModRef>,
+    // The fallback loader this version was matched through, if not the instance loader
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_fallback: Option<String>,
+    // The game version tag this version was accepted through, if not tagged for the
+    // instance's exact Minecraft version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft_version_fallback: Option<String>,
+}
+
+// A Modrinth project, optionally narrowed to one version
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModRef {
+    pub project_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+}
+
+impl LockedMod {
+    pub(crate) fn resolved(&self) -> ResolvedMod {
+        ResolvedMod {
+            name: self.name.clone(),
+            project_id: self.file.project_id.clone(),
+            version_id: self.file.version_id.clone(),
+            version_number: self.file.version_number.clone(),
+            filename: self.file.filename.clone(),
+            sha512: self.file.sha512.clone(),
+            loader_fallback: self.loader_fallback.clone(),
+        }
+    }
+}
+
+// Install report lines for locked mods matched through a loader fallback
+pub fn loader_fallback_report(mods: &[LockedMod]) -> Option<String> {
+    let lines: Vec<String> = mods
+        .iter()
+        .filter_map(|m| {
+            let fallback = m.loader_fallback.as_ref()?;
+            Some(format!(
+                "{} {} ({})",
+                m.name, m.file.version_number, fallback
+            ))
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "ℹ️ {} mod(s) installed through a loader fallback:\n - {}",
+        lines.len(),
+        lines.join("\n - ")
+    ))
+}
+
+// Install report lines for locked mods not tagged for the instance's Minecraft version
+pub fn minecraft_version_fallback_report(
+    mods: &[LockedMod],
+    minecraft_version: &str,
+) -> Option<String> {
+    let lines: Vec<String> = mods
+        .iter()
+        .filter_map(|m| {
+            let tag = m.minecraft_version_fallback.as_ref()?;
+            Some(format!(
+                "{} {} (made for {})",
+                m.name, m.file.version_number, tag
+            ))
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "⚠️ {} mod(s) are not tagged for Minecraft {} and were accepted by minor version, so they may not work:\n - {}",
+        lines.len(),
+        minecraft_version,
+        lines.join("\n - ")
+    ))
+}
+
+pub fn load_lockfile(path: &Path) -> Option<StoryLock> {
+    let text = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+pub fn save_lockfile(path: &Path, lock: &StoryLock) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create lockfile directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(lock)
+        .map_err(|e| format!("Failed to serialize lockfile: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write lockfile: {}", e))
+}
+
+// Check that a lockfile was resolved from this manifest: same pack, Minecraft version
+// and loader, exactly the manifest's extra mods, and every dependency locked
+pub fn lock_matches_manifest(lock: &StoryLock, manifest: &StoryManifest) -> Result<(), String> {
+    if lock.lock_version > LOCKFILE_VERSION {
+        return Err(format!(
+            "lockfile version {} is newer than this launcher supports ({})",
+            lock.lock_version, LOCKFILE_VERSION
+        ));
+    }
+    if lock.instance_name != manifest.instance.name
+        || lock.instance_version != manifest.instance.version
+    {
+        return Err(format!(
+            "locked {} v{} but the manifest wants {} v{}",
+            lock.instance_name,
+            lock.instance_version,
+            manifest.instance.name,
+            manifest.instance.version
+        ));
+    }
+    if lock.pack.is_none() {
+        return Err("the modpack file is not locked".to_string());
+    }
+
+    let minecraft_version = manifest.instance.minecraft_version_or_default();
+    let loader = manifest.instance.loader_or_default();
+    if lock.minecraft_version != minecraft_version || lock.loader != loader {
+        return Err(format!(
+            "locked for Minecraft {} ({}) but the manifest wants Minecraft {} ({})",
+            lock.minecraft_version, lock.loader, minecraft_version, loader
+        ));
+    }
+    if lock.loader_fallbacks != manifest.instance.compatible_loaders()[1..] {
+        return Err("the manifest's loader fallbacks changed".to_string());
+    }
+    if lock.preference != manifest.instance.version_preference() {
+        return Err("the manifest's release channel preferences changed".to_string());
+    }
+
+    let extra_mods: &[ExtraMod] = manifest.extra_mods.as_deref().unwrap_or_default();
+    for extra_mod in extra_mods {
+        if !lock
+            .mods
+            .iter()
+            .any(|m| m.requested.as_ref() == Some(extra_mod))
+        {
+            return Err(format!(
+                "extra mod {} is not locked as the manifest specifies it",
+                extra_mod.name
+            ));
+        }
+    }
+    for locked in &lock.mods {
+        if let Some(requested) = &locked.requested {
+            if !extra_mods.contains(requested) {
+                return Err(format!(
+                    "{} is locked but no longer in the manifest",
+                    locked.name
+                ));
+            }
+        }
+        for dependency in &locked.dependencies {
+            if !lock.mods.iter().any(|m| &m.file.project_id == dependency) {
+                return Err(format!(
+                    "dependency {} of {} is not locked",
+                    dependency, locked.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn resolve_pack_file(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    instance: &InstanceConfig,
+) -> Result<LockedFile, String> {
+    let api_url = api.url(&format!(
+        "/project/{}/version/{}",
+        instance.name, instance.version
+    ));
+    let response_text = cache
+        .get_text(api.client(), &api_url)
+        .await
+        .map_err(|e| format!("Failed to fetch modpack info: {}", e))?;
+    let version_info: ModrinthVersionResponse = serde_json::from_str(&response_text)
+        .map_err(|e| format!("Failed to parse modpack info: {}", e))?;
+    let mrpack_file = version_info
+        .files
+        .iter()
+        .find(|f| f.primary && f.filename.ends_with(".mrpack"))
+        .ok_or_else(|| "No primary .mrpack file found".to_string())?;
+    LockedFile::from_version(&version_info, mrpack_file)
+}
+
+// The index of a locked modpack, fetched through the blob store
+async fn locked_pack_index(
+    client: &reqwest::Client,
+    cache: &ResponseCache,
+    store: &BlobStore,
+    pack: &LockedFile,
+) -> Result<ModrinthIndex, String> {
+    let sha512 = fetch_into_blob_store(
+        client,
+        store,
+        std::slice::from_ref(&pack.url),
+        Some(&pack.sha512),
+        cache.offline,
+    )
+    .await
+    .map_err(|e| format!("Failed to download mrpack {}: {}", pack.filename, e))?;
+    let mrpack_bytes = std::fs::read(store.blob_path(&sha512))
+        .map_err(|e| format!("Failed to read mrpack bytes: {}", e))?;
+    read_mrpack_index(&mrpack_bytes)
+}
+
+// A required dependency still to be resolved; `required_by` indexes ModResolver::mods
+struct PendingDependency {
+    project_id: Option<String>,
+    version_id: Option<String>,
+    required_by: usize,
+}
+
+// Resolves extra mods and their dependency graph into locked jars. Every project is
+// locked once for the whole install, and projects the modpack ships are never added again.
+// Lookups are prefetched a wave at a time: pinned versions and hashes in bulk requests,
+// version lists of unpinned projects concurrently.
+pub(crate) struct ModResolver<'a> {
+    api: &'a ModrinthApi,
+    cache: &'a ResponseCache,
+    minecraft_version: String,
+    // The instance loader followed by its fallbacks
+    loaders: Vec<String>,
+    preference: VersionPreference,
+    pack_projects: HashMap<String, String>,
+    previous: Option<&'a StoryLock>,
+    mods: Vec<LockedMod>,
+    queue: std::collections::VecDeque<PendingDependency>,
+    failures: Vec<String>,
+    // Version clashes and incompatibilities, which no retry can fix
+    conflicts: Vec<String>,
+    // Prefetched versions by id and by sha512, and version lists by project
+    versions: HashMap<String, ModrinthVersionResponse>,
+    by_hash: HashMap<String, ModrinthVersionResponse>,
+    listings: HashMap<String, Result<Vec<ModrinthVersionResponse>, String>>,
+}
+
+impl<'a> ModResolver<'a> {
+    pub(crate) fn new(
+        api: &'a ModrinthApi,
+        cache: &'a ResponseCache,
+        minecraft_version: String,
+        loaders: Vec<String>,
+        preference: VersionPreference,
+        pack_projects: HashMap<String, String>,
+        previous: Option<&'a StoryLock>,
+    ) -> Self {
+        ModResolver {
+            api,
+            cache,
+            minecraft_version,
+            loaders,
+            preference,
+            pack_projects,
+            previous,
+            mods: Vec::new(),
+            queue: std::collections::VecDeque::new(),
+            failures: Vec::new(),
+            conflicts: Vec::new(),
+            versions: HashMap::new(),
+            by_hash: HashMap::new(),
+            listings: HashMap::new(),
+        }
+    }
+
+    // Fetch what a batch of extra mods will look up, in as few requests as possible
+    async fn prefetch_extra_mods(&mut self, extra_mods: &[ExtraMod]) {
+        let previous = self.previous;
+        let mut version_ids = Vec::new();
+        let mut hashes = Vec::new();
+        let mut projects = Vec::new();
+        for extra_mod in extra_mods {
+            let reused = previous.is_some_and(|p| {
+                p.mods
+                    .iter()
+                    .any(|m| m.requested.as_ref() == Some(extra_mod))
+            });
+            if reused || extra_mod.url.is_some() {
+                continue;
+            }
+            // Same precedence as resolve_mod_version
+            if let Some(version_id) = &extra_mod.version_id {
+                version_ids.push(version_id.clone());
+            } else if let Some(sha512) = &extra_mod.sha512 {
+                hashes.push(sha512.to_lowercase());
+            } else if extra_mod.filename.is_none() && extra_mod.version.is_none() {
+                projects.push(extra_mod.name.clone());
+            }
+        }
+        self.prefetch_versions(version_ids).await;
+        self.prefetch_hashes(hashes).await;
+        self.prefetch_listings(projects).await;
+    }
+
+    // Fetch what the queued dependencies will look up. Dependencies the modpack provides,
+    // that are already locked or that the previous lock still covers need nothing.
+    async fn prefetch_queue(&mut self) {
+        let mut version_ids = Vec::new();
+        let mut projects = Vec::new();
+        for pending in &self.queue {
+            if let Some(project_id) = &pending.project_id {
+                if self.pack_projects.contains_key(project_id)
+                    || self.mods.iter().any(|m| &m.file.project_id == project_id)
+                    || self
+                        .reusable_dependency(project_id, pending.version_id.as_ref())
+                        .is_some()
+                {
+                    continue;
+                }
+            }
+            match (&pending.project_id, &pending.version_id) {
+                (_, Some(version_id)) => version_ids.push(version_id.clone()),
+                (Some(project_id), None) => projects.push(project_id.clone()),
+                (None, None) => {}
+            }
+        }
+        self.prefetch_versions(version_ids).await;
+        self.prefetch_listings(projects).await;
+    }
+
+    // Failed prefetches are only logged; the lookup is retried on its own when needed
+    async fn prefetch_versions(&mut self, mut ids: Vec<String>) {
+        ids.retain(|id| !self.versions.contains_key(id));
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() {
+            return;
+        }
+        match fetch_versions_bulk(self.api, self.cache, &ids).await {
+            Ok(found) => self.versions.extend(found),
+            Err(e) => eprintln!("Warning: bulk version lookup failed: {}", e),
+        }
+    }
+
+    async fn prefetch_hashes(&mut self, mut hashes: Vec<String>) {
+        hashes.retain(|sha512| !self.by_hash.contains_key(sha512));
+        hashes.sort();
+        hashes.dedup();
+        if hashes.is_empty() {
+            return;
+        }
+        match fetch_versions_by_hash(self.api, self.cache, &hashes, None).await {
+            Ok(found) => self.by_hash.extend(found),
+            Err(e) => eprintln!("Warning: bulk file hash lookup failed: {}", e),
+        }
+    }
+
+    async fn prefetch_listings(&mut self, mut projects: Vec<String>) {
+        projects.retain(|project| !self.listings.contains_key(project));
+        projects.sort();
+        projects.dedup();
+        if projects.is_empty() {
+            return;
+        }
+        let projects = self.prefetch_listings_bulk(projects).await;
+        if projects.is_empty() {
+            return;
+        }
+        eprintln!("Fetching version lists of {} mods", projects.len());
+        let mut tasks = tokio::task::JoinSet::new();
+        for project in projects {
+            let api = self.api.clone();
+            let cache = self.cache.clone();
+            let api_url = candidate_versions_url(
+                &api,
+                &project,
+                &self.minecraft_version,
+                &self.loaders,
+                &self.preference,
+            );
+            tasks.spawn(async move {
+                let versions = fetch_project_versions(&api, &cache, &api_url).await;
+                (project, versions)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((project, versions)) => {
+                    self.listings.insert(project, versions);
+                }
+                Err(e) => eprintln!("Warning: version list lookup failed: {}", e),
+            }
+        }
+    }
+
+    // Version lists of many projects from two bulk requests. Each list is narrowed to what
+    // the project's own filtered listing would return and cached under its URL, so one-off
+    // and offline lookups find it. Returns the projects that still need a list of their own.
+    async fn prefetch_listings_bulk(&mut self, projects: Vec<String>) -> Vec<String> {
+        let mut found = match fetch_project_versions_bulk(self.api, self.cache, &projects).await {
+            Ok(found) => found,
+            Err(e) => {
+                eprintln!("Warning: bulk project lookup failed: {}", e);
+                return projects;
+            }
+        };
+        let mut remaining = Vec::new();
+        for project in projects {
+            let Some(mut versions) = found.remove(&project) else {
+                remaining.push(project);
+                continue;
+            };
+            versions.retain(|v| {
+                v.loaders.iter().any(|l| self.loaders.contains(l))
+                    && (self.preference.accept_same_minor
+                        || v.game_versions.contains(&self.minecraft_version))
+            });
+            let api_url = candidate_versions_url(
+                self.api,
+                &project,
+                &self.minecraft_version,
+                &self.loaders,
+                &self.preference,
+            );
+            cache_response(self.cache, &api_url, &versions);
+            self.listings.insert(project, Ok(versions));
+        }
+        remaining
+    }
+
+    // The best version of an unpinned project, from its prefetched version list if there is one
+    async fn best_version(
+        &self,
+        project: &str,
+        preference: &VersionPreference,
+    ) -> Result<ModrinthVersionResponse, String> {
+        match self.listings.get(project) {
+            Some(Ok(versions)) => pick_best_mod_version(
+                versions.clone(),
+                project,
+                &self.minecraft_version,
+                &self.loaders,
+                preference,
+            ),
+            Some(Err(e)) => Err(e.clone()),
+            None => {
+                find_best_mod_version(
+                    self.api,
+                    self.cache,
+                    project,
+                    &self.minecraft_version,
+                    &self.loaders,
+                    preference,
+                )
+                .await
+            }
+        }
+    }
+
+    async fn resolve_extra_mod(
+        &self,
+        extra_mod: &ExtraMod,
+    ) -> Result<ModrinthVersionResponse, String> {
+        let prefetched = if let Some(version_id) = &extra_mod.version_id {
+            self.versions.get(version_id)
+        } else if let Some(sha512) = &extra_mod.sha512 {
+            self.by_hash.get(&sha512.to_lowercase())
+        } else if extra_mod.filename.is_none() && extra_mod.version.is_none() {
+            let preference = extra_mod.version_preference(&self.preference);
+            return self.best_version(&extra_mod.name, &preference).await;
+        } else {
+            None
+        };
+        match prefetched {
+            Some(version_info) => Ok(version_info.clone()),
+            None => {
+                resolve_mod_version(
+                    self.api,
+                    self.cache,
+                    extra_mod,
+                    &self.minecraft_version,
+                    &self.loaders,
+                    &self.preference,
+                )
+                .await
+            }
+        }
+    }
+
+    // An unrequested entry of the previous lock that still satisfies a dependency
+    fn reusable_dependency(&self, project_id: &str, pin: Option<&String>) -> Option<&'a LockedMod> {
+        self.previous?.mods.iter().find(|m| {
+            m.requested.is_none()
+                && m.file.project_id == project_id
+                && pin.is_none_or(|pin| pin == &m.file.version_id)
+        })
+    }
+
+    async fn fetch_version(&self, version_id: &str) -> Result<ModrinthVersionResponse, String> {
+        if let Some(version_info) = self.versions.get(version_id) {
+            return Ok(version_info.clone());
+        }
+        let api_url = self.api.url(&format!("/version/{}", version_id));
+        let response_text = self
+            .cache
+            .get_text(self.api.client(), &api_url)
+            .await
+            .map_err(|e| format!("Failed to fetch mod info: {}", e))?;
+        serde_json::from_str(&response_text).map_err(|e| format!("Failed to parse mod info: {}", e))
+    }
+
+    // Lock a version and queue what its dependency list asks for
+    fn lock(
+        &mut self,
+        name: String,
+        version_info: &ModrinthVersionResponse,
+        file: LockedFile,
+        requested: Option<ExtraMod>,
+    ) {
+        let index = self.mods.len();
+        let mut incompatible = Vec::new();
+        for dependency in &version_info.dependencies {
+            match dependency.dependency_type.as_str() {
+                "required"
+                    if dependency.project_id.is_some() || dependency.version_id.is_some() =>
+                {
+                    self.queue.push_back(PendingDependency {
+                        project_id: dependency.project_id.clone(),
+                        version_id: dependency.version_id.clone(),
+                        required_by: index,
+                    })
+                }
+                "required" => eprintln!(
+                    "Warning: {} requires {:?}, which is not on Modrinth",
+                    name, dependency.file_name
+                ),
+                "incompatible" => {
+                    if let Some(project_id) = &dependency.project_id {
+                        incompatible.push(ModRef {
+                            project_id: project_id.clone(),
+                            version_id: dependency.version_id.clone(),
+                        });
+                    }
+                }
+                "embedded" => eprintln!(
+                    "{} embeds {:?}, nothing to download",
+                    name,
+                    dependency
+                        .project_id
+                        .as_ref()
+                        .or(dependency.file_name.as_ref())
+                ),
+                _ => {}
+            }
+        }
+
+        let fallback = loader_fallback(version_info, &self.loaders);
+        match &fallback {
+            Some(fallback) => eprintln!(
+                "🔒 Locked {} to {} ({}) through the {} loader fallback",
+                name, file.version_number, file.filename, fallback
+            ),
+            None => eprintln!(
+                "🔒 Locked {} to {} ({})",
+                name, file.version_number, file.filename
+            ),
+        }
+        self.mods.push(LockedMod {
+            name,
+            file,
+            requested,
+            dependencies: Vec::new(),
+            incompatible,
+            loader_fallback: fallback,
+            minecraft_version_fallback: minecraft_version_fallback(
+                version_info,
+                &self.minecraft_version,
+            ),
+        });
+    }
+
+    // Keep an entry of the previous lockfile; its dependencies are checked again
+    fn reuse(&mut self, locked: &LockedMod) {
+        let index = self.mods.len();
+        for project_id in &locked.dependencies {
+            self.queue.push_back(PendingDependency {
+                project_id: Some(project_id.clone()),
+                version_id: None,
+                required_by: index,
+            });
+        }
+        self.mods.push(LockedMod {
+            dependencies: Vec::new(),
+            ..locked.clone()
+        });
+    }
+
+    fn add_dependency(&mut self, parent: usize, project_id: &str) {
+        let dependencies = &mut self.mods[parent].dependencies;
+        if !dependencies.iter().any(|d| d == project_id) {
+            dependencies.push(project_id.to_string());
+        }
+    }
+
+    // A direct-URL extra mod is downloaded into the blob store to learn its size, which also
+    // checks it against the manifest's sha512 before it's locked
+    async fn lock_direct(
+        &self,
+        extra_mod: &ExtraMod,
+        store: &BlobStore,
+    ) -> Result<LockedFile, String> {
+        let url = extra_mod
+            .url
+            .clone()
+            .ok_or_else(|| "no download URL".to_string())?;
+        let Some(expected) = extra_mod.sha512.as_deref() else {
+            return Err(format!("{} has no sha512 hash to lock", url));
+        };
+        let sha512 = fetch_into_blob_store(
+            self.api.http(),
+            store,
+            std::slice::from_ref(&url),
+            Some(expected),
+            self.cache.offline,
+        )
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+        let size = std::fs::metadata(store.blob_path(&sha512))
+            .map_err(|e| format!("Failed to read {}: {}", url, e))?
+            .len();
+        LockedFile::direct(extra_mod, size)
+    }
+
+    pub(crate) async fn add_extra_mod(&mut self, extra_mod: &ExtraMod, store: &BlobStore) {
+        let previous = self.previous;
+        if let Some(locked) = previous.and_then(|p| {
+            p.mods
+                .iter()
+                .find(|m| m.requested.as_ref() == Some(extra_mod))
+        }) {
+            eprintln!(
+                "🔒 Keeping {} at {} ({})",
+                extra_mod.name, locked.file.version_number, locked.file.filename
+            );
+            self.reuse(locked);
+            return;
+        }
+
+        if extra_mod.url.is_some() {
+            match self.lock_direct(extra_mod, store).await {
+                Ok(file) => {
+                    eprintln!(
+                        "🔒 Locked {} to {} ({})",
+                        extra_mod.name, file.url, file.filename
+                    );
+                    self.mods.push(LockedMod {
+                        name: extra_mod.name.clone(),
+                        file,
+                        requested: Some(extra_mod.clone()),
+                        dependencies: Vec::new(),
+                        incompatible: Vec::new(),
+                        loader_fallback: None,
+                        minecraft_version_fallback: None,
+                    });
+                }
+                Err(e) => self
+                    .failures
+                    .push(format!("extra mod {}: {}", extra_mod.name, e)),
+            }
+            return;
+        }
+
+        let resolved = match self.resolve_extra_mod(extra_mod).await {
+            Ok(version_info) => select_mod_file(&version_info, extra_mod)
+                .and_then(|file| LockedFile::from_version(&version_info, file))
+                .map(|file| (version_info, file)),
+            Err(e) => Err(e),
+        };
+        let (version_info, file) = match resolved {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.failures
+                    .push(format!("extra mod {}: {}", extra_mod.name, e));
+                return;
+            }
+        };
+
+        if let Some(pack_version) = self.pack_projects.get(&file.project_id) {
+            if pack_version != &file.version_id {
+                self.conflicts.push(format!(
+                    "extra mod {}: the modpack already includes {} as version {}, not {}",
+                    extra_mod.name, file.project_id, pack_version, file.version_id
+                ));
+                return;
+            }
+        }
+        if let Some(existing) = self
+            .mods
+            .iter()
+            .find(|m| m.file.project_id == file.project_id)
+        {
+            self.conflicts.push(format!(
+                "extra mod {}: same project as {}",
+                extra_mod.name, existing.name
+            ));
+            return;
+        }
+
+        self.lock(
+            extra_mod.name.clone(),
+            &version_info,
+            file,
+            Some(extra_mod.clone()),
+        );
+    }
+
+    // Work through the queued dependencies. Pinned versions go first so an unpinned
+    // request doesn't claim a project that a pin needs at a specific version.
+    pub(crate) async fn resolve_dependencies(&mut self) {
+        loop {
+            self.prefetch_queue().await;
+            let position = self
+                .queue
+                .iter()
+                .position(|d| d.version_id.is_some())
+                .unwrap_or(0);
+            match self.queue.remove(position) {
+                Some(pending) => self.resolve_dependency(pending).await,
+                None => break,
+            }
+        }
+    }
+
+    async fn resolve_dependency(&mut self, pending: PendingDependency) {
+        let parent = self.mods[pending.required_by].name.clone();
+
+        // A dependency given only by version id has to be fetched to learn its project
+        let mut fetched = None;
+        let project_id = match (&pending.project_id, &pending.version_id) {
+            (Some(project_id), _) => project_id.clone(),
+            (None, Some(version_id)) => match self.fetch_version(version_id).await {
+                Ok(version_info) => {
+                    let project_id = version_info.project_id.clone();
+                    fetched = Some(version_info);
+                    project_id
+                }
+                Err(e) => {
+                    self.failures
+                        .push(format!("dependency {} of {}: {}", version_id, parent, e));
+                    return;
+                }
+            },
+            (None, None) => return,
+        };
+
+        if let Some(pack_version) = self.pack_projects.get(&project_id) {
+            match &pending.version_id {
+                Some(pin) if pin != pack_version => self.conflicts.push(format!(
+                    "{} requires {} version {}, but the modpack has version {}",
+                    parent, project_id, pin, pack_version
+                )),
+                _ => eprintln!(
+                    "Dependency {} of {} is provided by the modpack",
+                    project_id, parent
+                ),
+            }
+            return;
+        }
+
+        if let Some(locked) = self.mods.iter().find(|m| m.file.project_id == project_id) {
+            if let Some(pin) = &pending.version_id {
+                if pin != &locked.file.version_id {
+                    self.conflicts.push(format!(
+                        "{} requires {} version {}, but {} is locked",
+                        parent, project_id, pin, locked.file.version_number
+                    ));
+                    return;
+                }
+            }
+            self.add_dependency(pending.required_by, &project_id);
+            return;
+        }
+
+        if let Some(locked) = self.reusable_dependency(&project_id, pending.version_id.as_ref()) {
+            self.add_dependency(pending.required_by, &project_id);
+            self.reuse(locked);
+            return;
+        }
+
+        let version_info = match (fetched, &pending.version_id) {
+            (Some(version_info), _) => Ok(version_info),
+            (None, Some(pin)) => self.fetch_version(pin).await,
+            (None, None) => self.best_version(&project_id, &self.preference).await,
+        };
+        let resolved = version_info.and_then(|version_info| {
+            let file = primary_jar(&version_info)
+                .and_then(|file| LockedFile::from_version(&version_info, file))?;
+            Ok((version_info, file))
+        });
+        match resolved {
+            Ok((version_info, file)) => {
+                self.add_dependency(pending.required_by, &project_id);
+                self.lock(project_id, &version_info, file, None);
+            }
+            Err(e) => self
+                .failures
+                .push(format!("dependency {} of {}: {}", project_id, parent, e)),
+        }
+    }
+
+    // Everything locked, plus what failed to resolve. Version conflicts and mods that
+    // declare themselves incompatible with something else in the install are an error
+    // instead, since installing the rest would put both sides of the clash in place.
+    pub(crate) fn finish(mut self) -> Result<(Vec<LockedMod>, Vec<String>), String> {
+        for locked in &self.mods {
+            for incompatible in &locked.incompatible {
+                let clash = self
+                    .mods
+                    .iter()
+                    .find(|m| m.file.project_id == incompatible.project_id)
+                    .map(|m| (m.name.clone(), &m.file.version_id))
+                    .or_else(|| {
+                        self.pack_projects
+                            .get(&incompatible.project_id)
+                            .map(|v| (format!("{} from the modpack", incompatible.project_id), v))
+                    });
+                if let Some((name, version_id)) = clash {
+                    if incompatible
+                        .version_id
+                        .as_ref()
+                        .is_none_or(|v| v == version_id)
+                    {
+                        self.conflicts
+                            .push(format!("{} is incompatible with {}", locked.name, name));
+                    }
+                }
+            }
+        }
+        if !self.conflicts.is_empty() {
+            return Err(format!(
+                "{} conflict(s) between mods:\n - {}",
+                self.conflicts.len(),
+                self.conflicts.join("\n - ")
+            ));
+        }
+        Ok((self.mods, self.failures))
+    }
+}
+
+// Resolve a manifest into a lockfile. Entries of `previous` that still match the manifest
+// are kept as they are, so adding one mod doesn't move every other unpinned mod.
+// Returns the lock together with everything that failed to resolve, or an error if mods
+// in it conflict.
+pub async fn resolve_lockfile(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    store: &BlobStore,
+    manifest: &StoryManifest,
+    previous: Option<&StoryLock>,
+) -> Result<(StoryLock, Vec<String>), String> {
+    let minecraft_version = manifest.instance.minecraft_version_or_default();
+    let loaders = manifest.instance.compatible_loaders();
+    let (loader, loader_fallbacks) = (loaders[0].clone(), loaders[1..].to_vec());
+    let preference = manifest.instance.version_preference();
+    // Entries only carry over for the same pack, Minecraft version, loaders and preferences
+    let previous = previous.filter(|p| {
+        p.minecraft_version == minecraft_version
+            && p.loader == loader
+            && p.loader_fallbacks == loader_fallbacks
+            && p.preference == preference
+            && p.instance_name == manifest.instance.name
+            && p.instance_version == manifest.instance.version
+    });
+    let mut failures = Vec::new();
+
+    let pack = match previous.and_then(|p| p.pack.clone()) {
+        Some(pack) => Some(pack),
+        None => match resolve_pack_file(api, cache, &manifest.instance).await {
+            Ok(pack) => Some(pack),
+            Err(e) => {
+                failures.push(format!(
+                    "modpack {} v{}: {}",
+                    manifest.instance.name, manifest.instance.version, e
+                ));
+                None
+            }
+        },
+    };
+
+    // The pack's own mods count as installed when resolving dependencies
+    let pack_projects = match &pack {
+        Some(pack) => match locked_pack_index(api.http(), cache, store, pack).await {
+            Ok(index) => pack_projects(&index),
+            Err(e) => {
+                failures.push(format!(
+                    "modpack {} v{}: {}",
+                    manifest.instance.name, manifest.instance.version, e
+                ));
+                HashMap::new()
+            }
+        },
+        None => HashMap::new(),
+    };
+
+    let mut resolver = ModResolver::new(
+        api,
+        cache,
+        minecraft_version.clone(),
+        loaders,
+        preference,
+        pack_projects,
+        previous,
+    );
+    let extra_mods: &[ExtraMod] = manifest.extra_mods.as_deref().unwrap_or_default();
+    resolver.prefetch_extra_mods(extra_mods).await;
+    for extra_mod in extra_mods {
+        resolver.add_extra_mod(extra_mod, store).await;
+    }
+    resolver.resolve_dependencies().await;
+    let (mods, mod_failures) = resolver.finish()?;
+    failures.extend(mod_failures);
+
+    let lock = StoryLock {
+        lock_version: LOCKFILE_VERSION,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        instance_name: manifest.instance.name.clone(),
+        instance_version: manifest.instance.version.clone(),
+        minecraft_version,
+        loader,
+        loader_fallbacks,
+        preference,
+        pack,
+        mods,
+    };
+    Ok((lock, failures))
+}
+
+// Resolve a manifest (URL or local path) into a lockfile written to `output_path`, for
+// publishing next to the manifest. Returns the sha256 to put in its "lockfile" entry.
+pub async fn write_lockfile(
+    ctx: &LauncherContext,
+    manifest: &str,
+    output_path: &Path,
+) -> Result<String, String> {
+    let api = ctx.api();
+    let cache = ctx.response_cache(false);
+    let manifest_text = if manifest.starts_with("http://") || manifest.starts_with("https://") {
+        cache
+            .get_text(api.http(), manifest)
+            .await
+            .map_err(|e| format!("Failed to download manifest: {}", e))?
+    } else {
+        std::fs::read_to_string(manifest)
+            .map_err(|e| format!("Failed to read manifest {}: {}", manifest, e))?
+    };
+    let story_manifest = parse_manifest(&manifest_text)?;
+
+    let store = ctx.blob_store();
+    let (lock, failures) = resolve_lockfile(api, &cache, &store, &story_manifest, None).await?;
+    if !failures.is_empty() {
+        return Err(format!(
+            "Failed to resolve {} item(s):\n - {}",
+            failures.len(),
+            failures.join("\n - ")
+        ));
+    }
+
+    save_lockfile(output_path, &lock)?;
+    let lock_bytes = std::fs::read(output_path)
+        .map_err(|e| format!("Failed to read lockfile {}: {}", output_path.display(), e))?;
+    Ok(sha256_hex(&lock_bytes))
+}
+
+// The lockfile to install from: the one published with the manifest (which must match
+// its pinned sha256 and the manifest), the instance's own if it still matches, or a
+// fresh resolution that reuses whatever of the old lock still applies. `refresh` skips
+// the instance's own lock, so unpinned mods move to their newest versions.
+pub(crate) async fn load_or_resolve_lock(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    store: &BlobStore,
+    manifest: &StoryManifest,
+    story_path: &Path,
+    refresh: bool,
+) -> Result<StoryLock, String> {
+    let lock_path = story_path.join(LOCKFILE_NAME);
+
+    if let Some(lock_ref) = &manifest.lockfile {
+        eprintln!("Downloading lockfile from: {}", lock_ref.url);
+        let lock_text = cache
+            .get_text(api.http(), &lock_ref.url)
+            .await
+            .map_err(|e| format!("Failed to download lockfile: {}", e))?;
+        let actual = sha256_hex(lock_text.as_bytes());
+        if !actual.eq_ignore_ascii_case(&lock_ref.sha256) {
+            return Err(format!(
+                "Lockfile {} does not match the manifest: expected sha256 {}, got {}",
+                lock_ref.url, lock_ref.sha256, actual
+            ));
+        }
+        let lock: StoryLock = serde_json::from_str(&lock_text)
+            .map_err(|e| format!("Failed to parse lockfile: {}", e))?;
+        lock_matches_manifest(&lock, manifest)
+            .map_err(|e| format!("Published lockfile is out of date: {}", e))?;
+        if let Err(e) = save_lockfile(&lock_path, &lock) {
+            eprintln!("Warning: Failed to keep a copy of the lockfile: {}", e);
+        }
+        return Ok(lock);
+    }
+
+    let previous = load_lockfile(&lock_path).filter(|_| !refresh);
+    if refresh {
+        eprintln!("Resolving the manifest again for the newest versions");
+    }
+    if let Some(previous) = &previous {
+        match lock_matches_manifest(previous, manifest) {
+            Ok(()) => {
+                eprintln!("🔒 Using lockfile {}", lock_path.display());
+                return Ok(previous.clone());
+            }
+            Err(e) => eprintln!("Lockfile is out of date ({}), resolving again", e),
+        }
+    }
+
+    let (lock, failures) = resolve_lockfile(api, cache, store, manifest, previous.as_ref()).await?;
+    if failures.is_empty() {
+        save_lockfile(&lock_path, &lock)?;
+    } else {
+        // Don't pin a partial resolution; the next install tries the failures again
+        for failure in failures {
+            cache.record_missing(failure);
+        }
+    }
+    Ok(lock)
+}
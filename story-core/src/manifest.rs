@@ -0,0 +1,1039 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::cache::ResponseCache;
+use crate::install::LauncherContext;
+use crate::modrinth::{default_loader_fallbacks, ModEnv};
+
+// Current manifest format. Manifests without schema_version use the original
+// unversioned format and are migrated when parsed.
+pub const MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct StoryManifest {
+    #[serde(default)]
+    pub schema_version: u32,
+    pub instance: InstanceConfig,
+    pub extra_mods: Option<Vec<ExtraMod>>,
+    pub overrides: Option<Vec<Override>>,
+    // Lockfile published next to the manifest; installs use it instead of resolving
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lockfile: Option<LockfileRef>,
+    // A packwiz pack whose files are installed alongside the modpack
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packwiz: Option<PackwizRef>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct LockfileRef {
+    #[schemars(regex(pattern = "^https?://"))]
+    pub url: String,
+    #[schemars(regex(pattern = "^[0-9a-fA-F]{64}$"))]
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct PackwizRef {
+    // pack.toml; index.toml and the .pw.toml files are found relative to it
+    #[schemars(regex(pattern = "^https?://"))]
+    pub url: String,
+    // sha256 of pack.toml, which pins index.toml and through it every file
+    #[schemars(regex(pattern = "^[0-9a-fA-F]{64}$"))]
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct InstanceConfig {
+    #[schemars(regex(pattern = "\\S"))]
+    pub name: String,
+    #[schemars(regex(pattern = "\\S"))]
+    pub version: String,
+    #[schemars(regex(pattern = "\\S"))]
+    pub minecraft_version: Option<String>,
+    #[schemars(regex(pattern = "\\S"))]
+    pub loader: Option<String>,
+    // Defaults for auto-selected mod versions; extra mods can override them
+    pub release_channel: Option<ReleaseChannel>,
+    pub prefer_featured: Option<bool>,
+    // Loaders whose mods this instance also accepts, in order; defaults to default_loader_fallbacks
+    #[schemars(inner(regex(pattern = "\\S")))]
+    pub loader_fallbacks: Option<Vec<String>>,
+    pub accept_same_minor: Option<bool>,
+}
+
+impl InstanceConfig {
+    // The instance loader followed by its fallbacks
+    pub fn compatible_loaders(&self) -> Vec<String> {
+        let loader = self.loader_or_default();
+        let fallbacks = match &self.loader_fallbacks {
+            Some(fallbacks) => fallbacks.clone(),
+            None => default_loader_fallbacks(&loader, &self.minecraft_version_or_default()),
+        };
+        let mut loaders = vec![loader];
+        for fallback in fallbacks {
+            if !loaders.contains(&fallback) {
+                loaders.push(fallback);
+            }
+        }
+        loaders
+    }
+
+    pub fn version_preference(&self) -> VersionPreference {
+        VersionPreference {
+            channel: self.release_channel,
+            prefer_featured: self.prefer_featured.unwrap_or(false),
+            accept_same_minor: self.accept_same_minor.unwrap_or(false),
+        }
+    }
+
+    pub fn minecraft_version_or_default(&self) -> String {
+        self.minecraft_version
+            .clone()
+            .unwrap_or_else(|| "1.21.1".to_string())
+    }
+
+    pub fn loader_or_default(&self) -> String {
+        self.loader.clone().unwrap_or_else(|| "fabric".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[schemars(deny_unknown_fields)]
+pub struct ExtraMod {
+    #[schemars(regex(pattern = "\\S"))]
+    pub name: String,
+    #[schemars(regex(pattern = "\\S"))]
+    pub version: Option<String>,
+    // Exact pins, used in this order when set: Modrinth version id, jar sha512, jar filename
+    #[schemars(regex(pattern = "\\S"))]
+    pub version_id: Option<String>,
+    #[schemars(regex(pattern = "^[0-9a-fA-F]{128}$"))]
+    pub sha512: Option<String>,
+    #[schemars(regex(pattern = "\\S"))]
+    pub filename: Option<String>,
+    pub release_channel: Option<ReleaseChannel>,
+    pub prefer_featured: Option<bool>,
+    // Sides the mod runs on; client-only mods are left out of server installs
+    #[serde(default)]
+    pub env: Option<ModEnv>,
+    // A jar from outside Modrinth, downloaded from this URL and checked against `sha512`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = "^https?://"))]
+    pub url: Option<String>,
+    // Where a direct-URL jar goes, relative to .minecraft (mods/<filename> by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = "\\S"))]
+    pub path: Option<String>,
+}
+
+impl ExtraMod {
+    pub fn is_pinned(&self) -> bool {
+        self.version_id.is_some() || self.sha512.is_some() || self.filename.is_some()
+    }
+
+    // The path a direct-URL mod installs to; None for Modrinth mods
+    pub fn target_path(&self) -> Option<String> {
+        let url = self.url.as_ref()?;
+        if let Some(path) = &self.path {
+            return Some(path.clone());
+        }
+        let filename = self.filename.clone().unwrap_or_else(|| {
+            let without_query = url.split(['?', '#']).next().unwrap_or(url);
+            without_query
+                .rsplit('/')
+                .next()
+                .unwrap_or(without_query)
+                .to_string()
+        });
+        Some(format!("mods/{}", filename))
+    }
+
+    pub fn version_preference(&self, defaults: &VersionPreference) -> VersionPreference {
+        VersionPreference {
+            channel: self.release_channel.or(defaults.channel),
+            prefer_featured: self.prefer_featured.unwrap_or(defaults.prefer_featured),
+            accept_same_minor: defaults.accept_same_minor,
+        }
+    }
+}
+
+// Least stable Modrinth version_type a mod may auto-select. Ordered from most to least stable.
+#[derive(
+    Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    Release,
+    Beta,
+    Alpha,
+}
+
+// How to pick among the compatible versions of an unpinned mod. Without a channel the
+// most stable channel the mod has is used, so mods without releases still install.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct VersionPreference {
+    pub channel: Option<ReleaseChannel>,
+    pub prefer_featured: bool,
+    // Accept versions tagged for another release of the same minor Minecraft version
+    // (`1.21` for a `1.21.1` instance) when none is tagged for the instance's exact version
+    #[serde(default)]
+    pub accept_same_minor: bool,
+}
+
+// What an extra mod resolved to during install. Recorded in version tracking so the
+// exact jar is known even when the manifest only gave a name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolvedMod {
+    pub name: String,
+    pub project_id: String,
+    pub version_id: String,
+    pub version_number: String,
+    pub filename: String,
+    pub sha512: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_fallback: Option<String>,
+}
+
+// Version tracking entry for an extra mod: the manifest entry plus what it resolved to.
+// Mods skipped because they were already installed keep their previous resolution.
+pub fn tracked_extra_mod(
+    extra_mod: &ExtraMod,
+    resolved: &[ResolvedMod],
+    previous: Option<&serde_json::Value>,
+) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "name": extra_mod.name,
+        "version": extra_mod.version,
+        "version_id": extra_mod.version_id,
+        "sha512": extra_mod.sha512,
+        "filename": extra_mod.filename,
+        "url": extra_mod.url,
+        "path": extra_mod.path
+    });
+
+    // A previous entry only applies if the manifest entry hasn't changed since
+    let null = serde_json::Value::Null;
+    let same_entry = |m: &&serde_json::Value| {
+        [
+            "name",
+            "version",
+            "version_id",
+            "sha512",
+            "filename",
+            "url",
+            "path",
+        ]
+        .iter()
+        .all(|key| m.get(*key).unwrap_or(&null) == &entry[*key])
+    };
+    let resolution = match resolved.iter().find(|r| r.name == extra_mod.name) {
+        Some(resolved) => serde_json::to_value(resolved).ok(),
+        None => previous
+            .and_then(|p| p.get("extra_mods"))
+            .and_then(|mods| mods.as_array())
+            .and_then(|mods| mods.iter().find(same_entry))
+            .and_then(|m| m.get("resolved"))
+            .cloned(),
+    };
+    if let Some(resolution) = resolution {
+        entry["resolved"] = resolution;
+    }
+    entry
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Override {
+    #[schemars(regex(pattern = "\\S"))]
+    pub name: String,
+    #[schemars(regex(pattern = "^https?://"))]
+    pub url: String,
+    // Optional pins; a zip that doesn't match is never extracted
+    #[schemars(regex(pattern = "^[0-9a-fA-F]{64}$"))]
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub env: Option<ModEnv>,
+}
+
+// How a manifest was authenticated before installing it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ManifestTrust {
+    Signed { key: String },
+    Unsigned,
+}
+
+// Location of the detached signature published next to a manifest
+pub fn manifest_signature_url(manifest_url: &str) -> String {
+    format!("{}.sig", manifest_url)
+}
+
+// Check a detached Ed25519 signature (base64, over the exact manifest bytes) against the
+// pinned keys. A bad signature is always an error; a missing one only passes with the opt-in.
+pub fn verify_manifest_signature(
+    manifest: &[u8],
+    signature: Option<&str>,
+    trusted_keys: &[String],
+    allow_unsigned: bool,
+) -> Result<ManifestTrust, String> {
+    use base64::Engine;
+    let base64 = base64::engine::general_purpose::STANDARD;
+
+    let signature = match signature {
+        Some(signature) => signature,
+        None if allow_unsigned => {
+            eprintln!("⚠️ Manifest is not signed, installing anyway (allow_unsigned_manifests)");
+            return Ok(ManifestTrust::Unsigned);
+        }
+        None => {
+            return Err(
+                "Manifest is not signed. Enable allow_unsigned_manifests in the launcher settings to install it anyway"
+                    .to_string(),
+            )
+        }
+    };
+
+    let signature_bytes = base64
+        .decode(signature.trim())
+        .map_err(|e| format!("Invalid manifest signature encoding: {}", e))?;
+    let signature = ed25519_dalek::Signature::from_slice(&signature_bytes)
+        .map_err(|e| format!("Invalid manifest signature: {}", e))?;
+
+    if trusted_keys.is_empty() {
+        return Err("Manifest is signed but no trusted manifest keys are configured".to_string());
+    }
+
+    for key in trusted_keys {
+        let verifying_key = base64
+            .decode(key.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+            .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok());
+        let verifying_key = match verifying_key {
+            Some(verifying_key) => verifying_key,
+            None => {
+                eprintln!("Warning: Ignoring invalid trusted manifest key {}", key);
+                continue;
+            }
+        };
+        if verifying_key.verify_strict(manifest, &signature).is_ok() {
+            eprintln!("🔏 Manifest signature verified with key {}", key);
+            return Ok(ManifestTrust::Signed { key: key.clone() });
+        }
+    }
+
+    Err("Manifest signature does not match any trusted key, refusing to install".to_string())
+}
+
+// Fetch the manifest's detached signature and verify it with the launcher settings
+pub(crate) async fn verify_manifest_trust(
+    ctx: &LauncherContext,
+    client: &reqwest::Client,
+    cache: &ResponseCache,
+    manifest_url: &str,
+    manifest_text: &str,
+) -> Result<ManifestTrust, String> {
+    let settings = ctx.settings();
+    let signature_url = manifest_signature_url(manifest_url);
+    let signature = cache
+        .get_optional_text(client, &signature_url)
+        .await
+        .map_err(|e| format!("Failed to download manifest signature: {}", e))?;
+
+    verify_manifest_signature(
+        manifest_text.as_bytes(),
+        signature.as_deref(),
+        &settings.trusted_manifest_keys,
+        settings.allow_unsigned_manifests,
+    )
+}
+
+// How a manifest entry differs from what is installed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub reason: Option<String>,
+    pub download_size: Option<u64>,
+}
+
+impl ManifestChange {
+    pub(crate) fn new(
+        name: &str,
+        kind: ChangeKind,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> Self {
+        ManifestChange {
+            name: name.to_string(),
+            kind,
+            from,
+            to,
+            reason: None,
+            download_size: None,
+        }
+    }
+}
+
+// Result of check_manifest_updates: what an install of the manifest would change
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ManifestDiff {
+    pub instance_missing: bool,
+    pub untracked: bool,
+    pub pack: Option<ManifestChange>,
+    pub extra_mods: Vec<ManifestChange>,
+    pub overrides: Vec<ManifestChange>,
+    #[serde(default)]
+    pub packwiz: Option<ManifestChange>,
+    pub download_size: u64,
+    pub has_updates: bool,
+    pub summary: String,
+}
+
+impl ManifestDiff {
+    // Fill in the totals and the human readable summary once all changes are known
+    pub fn finalize(mut self) -> Self {
+        let changes = self
+            .pack
+            .iter()
+            .chain(self.extra_mods.iter())
+            .chain(self.overrides.iter())
+            .chain(self.packwiz.iter());
+        self.download_size = changes.filter_map(|c| c.download_size).sum();
+        self.has_updates = self.instance_missing
+            || self.untracked
+            || self.pack.is_some()
+            || !self.extra_mods.is_empty()
+            || !self.overrides.is_empty()
+            || self.packwiz.is_some();
+        self.summary = self.summarize();
+        self
+    }
+
+    fn summarize(&self) -> String {
+        if !self.has_updates {
+            return "No updates available - everything is up to date".to_string();
+        }
+        let size = if self.download_size > 0 {
+            format!(" (download size: {})", format_size(self.download_size))
+        } else {
+            String::new()
+        };
+        if self.instance_missing {
+            return format!("Instance not found - needs to be created{}", size);
+        }
+        if self.untracked {
+            return format!("Updates available - no version tracking found{}", size);
+        }
+
+        let mut reasons = Vec::new();
+        if let Some(pack) = &self.pack {
+            reasons.push(format!("Pack {}", describe_change(pack)));
+        }
+        for change in &self.extra_mods {
+            reasons.push(format!("Mod {}", describe_change(change)));
+        }
+        for change in &self.overrides {
+            reasons.push(format!("Override {}", describe_change(change)));
+        }
+        if let Some(packwiz) = &self.packwiz {
+            reasons.push(format!("Packwiz pack {}", describe_change(packwiz)));
+        }
+        format!("Updates available: {}{}", reasons.join(", "), size)
+    }
+}
+
+fn describe_change(change: &ManifestChange) -> String {
+    let version = |v: &Option<String>| v.clone().unwrap_or_else(|| "auto-detect".to_string());
+    let mut text = match change.kind {
+        ChangeKind::Added => format!("added: {} {}", change.name, version(&change.to)),
+        ChangeKind::Removed => format!("removed: {}", change.name),
+        ChangeKind::Changed if change.from != change.to => format!(
+            "changed: {} {} -> {}",
+            change.name,
+            version(&change.from),
+            version(&change.to)
+        ),
+        ChangeKind::Changed => format!("changed: {}", change.name),
+    };
+    if let Some(reason) = &change.reason {
+        text.push_str(&format!(" ({})", reason));
+    }
+    text
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Compare a manifest with the .current_version.json written by the last install.
+// Only looks at names, versions, URLs and pins; remote content checks happen in check_manifest_updates.
+pub fn diff_manifest(
+    manifest: &StoryManifest,
+    installed: Option<&serde_json::Value>,
+) -> ManifestDiff {
+    let mut diff = ManifestDiff {
+        untracked: installed.is_none(),
+        ..Default::default()
+    };
+    let empty = serde_json::Value::Null;
+    let installed = installed.unwrap_or(&empty);
+    let field = |value: &serde_json::Value, key: &str| {
+        value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+    };
+
+    // Pack version
+    let current_name = field(installed, "instance_name");
+    let current_version = field(installed, "instance_version");
+    if current_name.is_none() {
+        diff.pack = Some(ManifestChange::new(
+            &manifest.instance.name,
+            ChangeKind::Added,
+            None,
+            Some(manifest.instance.version.clone()),
+        ));
+    } else if current_name.as_deref() != Some(manifest.instance.name.as_str())
+        || current_version.as_deref() != Some(manifest.instance.version.as_str())
+    {
+        let mut change = ManifestChange::new(
+            &manifest.instance.name,
+            ChangeKind::Changed,
+            current_version,
+            Some(manifest.instance.version.clone()),
+        );
+        if current_name.as_deref() != Some(manifest.instance.name.as_str()) {
+            change.reason = Some(format!("was {}", current_name.unwrap_or_default()));
+        }
+        diff.pack = Some(change);
+    }
+
+    // Extra mods, matched by name
+    let tracked_mods: Vec<&serde_json::Value> = installed
+        .get("extra_mods")
+        .and_then(|v| v.as_array())
+        .map(|mods| mods.iter().filter(|m| field(m, "name").is_some()).collect())
+        .unwrap_or_default();
+    let current_mods: Vec<(String, Option<String>)> = tracked_mods
+        .iter()
+        .filter_map(|m| field(m, "name").map(|name| (name, field(m, "version"))))
+        .collect();
+    let manifest_mods = manifest.extra_mods.as_deref().unwrap_or_default();
+    for extra_mod in manifest_mods {
+        let tracked = tracked_mods
+            .iter()
+            .find(|m| field(m, "name").as_deref() == Some(extra_mod.name.as_str()));
+        match tracked {
+            None => diff.extra_mods.push(ManifestChange::new(
+                &extra_mod.name,
+                ChangeKind::Added,
+                None,
+                extra_mod.version.clone(),
+            )),
+            Some(tracked) if field(tracked, "version") != extra_mod.version => {
+                diff.extra_mods.push(ManifestChange::new(
+                    &extra_mod.name,
+                    ChangeKind::Changed,
+                    field(tracked, "version"),
+                    extra_mod.version.clone(),
+                ))
+            }
+            // Entries tracked before pins existed have none, so only a newly set pin shows up
+            Some(tracked)
+                if field(tracked, "version_id") != extra_mod.version_id
+                    || field(tracked, "sha512") != extra_mod.sha512
+                    || field(tracked, "filename") != extra_mod.filename
+                    || field(tracked, "url") != extra_mod.url
+                    || field(tracked, "path") != extra_mod.path =>
+            {
+                let mut change = ManifestChange::new(
+                    &extra_mod.name,
+                    ChangeKind::Changed,
+                    field(tracked, "version"),
+                    extra_mod.version.clone(),
+                );
+                change.reason = Some("pin changed".to_string());
+                diff.extra_mods.push(change)
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, version) in &current_mods {
+        if !manifest_mods.iter().any(|m| &m.name == name) {
+            diff.extra_mods.push(ManifestChange::new(
+                name,
+                ChangeKind::Removed,
+                version.clone(),
+                None,
+            ));
+        }
+    }
+
+    // Overrides, matched by name; a new URL or pin counts as a change
+    let tracked_overrides: Vec<&serde_json::Value> = installed
+        .get("overrides")
+        .and_then(|v| v.as_array())
+        .map(|overrides| {
+            overrides
+                .iter()
+                .filter(|o| field(o, "name").is_some())
+                .collect()
+        })
+        .unwrap_or_default();
+    let manifest_overrides = manifest.overrides.as_deref().unwrap_or_default();
+    for override_item in manifest_overrides {
+        let tracked = tracked_overrides
+            .iter()
+            .find(|o| field(o, "name").as_deref() == Some(override_item.name.as_str()));
+        match tracked {
+            None => diff.overrides.push(ManifestChange::new(
+                &override_item.name,
+                ChangeKind::Added,
+                None,
+                Some(override_item.url.clone()),
+            )),
+            Some(tracked)
+                if field(tracked, "url").as_deref() != Some(override_item.url.as_str()) =>
+            {
+                let mut change = ManifestChange::new(
+                    &override_item.name,
+                    ChangeKind::Changed,
+                    field(tracked, "url"),
+                    Some(override_item.url.clone()),
+                );
+                change.reason = Some("URL changed".to_string());
+                diff.overrides.push(change)
+            }
+            // Entries tracked before pins existed have none, so only a newly set pin shows up
+            Some(tracked)
+                if field(tracked, "sha256") != override_item.sha256
+                    || tracked.get("size").and_then(|v| v.as_u64()) != override_item.size =>
+            {
+                let mut change = ManifestChange::new(
+                    &override_item.name,
+                    ChangeKind::Changed,
+                    field(tracked, "url"),
+                    Some(override_item.url.clone()),
+                );
+                change.reason = Some("pin changed".to_string());
+                diff.overrides.push(change)
+            }
+            Some(_) => {}
+        }
+    }
+    for tracked in &tracked_overrides {
+        let name = field(tracked, "name").unwrap_or_default();
+        if !manifest_overrides.iter().any(|o| o.name == name) {
+            diff.overrides.push(ManifestChange::new(
+                &name,
+                ChangeKind::Removed,
+                field(tracked, "url"),
+                None,
+            ));
+        }
+    }
+
+    // Packwiz pack, pinned by the sha256 of its pack.toml. Installs from before the pin
+    // tracked only the URL.
+    let (tracked_url, tracked_sha256) = match installed.get("packwiz") {
+        Some(serde_json::Value::String(url)) => (Some(url.clone()), None),
+        Some(packwiz) => (field(packwiz, "url"), field(packwiz, "sha256")),
+        None => (None, None),
+    };
+    let packwiz_change = |kind, reason: Option<&str>| {
+        let mut change = ManifestChange::new(
+            "packwiz",
+            kind,
+            tracked_url.clone(),
+            manifest.packwiz.as_ref().map(|p| p.url.clone()),
+        );
+        change.reason = reason.map(str::to_string);
+        change
+    };
+    diff.packwiz = match (&manifest.packwiz, &tracked_url) {
+        (Some(_), None) => Some(packwiz_change(ChangeKind::Added, None)),
+        (Some(packwiz), Some(url)) if url != &packwiz.url => {
+            Some(packwiz_change(ChangeKind::Changed, Some("URL changed")))
+        }
+        (Some(packwiz), Some(_))
+            if !tracked_sha256
+                .as_deref()
+                .is_some_and(|sha256| sha256.eq_ignore_ascii_case(&packwiz.sha256)) =>
+        {
+            Some(packwiz_change(ChangeKind::Changed, Some("pin changed")))
+        }
+        (None, Some(_)) => Some(packwiz_change(ChangeKind::Removed, None)),
+        _ => None,
+    };
+
+    diff
+}
+
+// A problem found while validating a manifest, located by a JSON path like $.overrides[1].url
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl ManifestIssue {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        ManifestIssue {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ManifestIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ManifestValidation {
+    pub valid: bool,
+    // schema_version as written in the file, 0 for the original unversioned format
+    pub schema_version: Option<u32>,
+    pub migrated: bool,
+    pub errors: Vec<ManifestIssue>,
+}
+
+lazy_static::lazy_static! {
+    static ref MANIFEST_SCHEMA: serde_json::Value =
+        serde_json::to_value(schemars::schema_for!(StoryManifest))
+            .expect("manifest schema serializes");
+    static ref MANIFEST_VALIDATOR: jsonschema::Validator =
+        jsonschema::validator_for(&MANIFEST_SCHEMA).expect("manifest schema compiles");
+}
+
+// JSON Schema for the current manifest format, generated from StoryManifest.
+// Published as story-manifest.schema.json in the repository root.
+pub fn manifest_schema() -> serde_json::Value {
+    MANIFEST_SCHEMA.clone()
+}
+
+// Bring an older manifest up to MANIFEST_SCHEMA_VERSION in place.
+// Returns the version the manifest was written in.
+pub fn migrate_manifest(value: &mut serde_json::Value) -> Result<u32, ManifestIssue> {
+    let root = match value.as_object_mut() {
+        Some(root) => root,
+        None => return Err(ManifestIssue::new("$", "expected an object")),
+    };
+    let original = match root.get("schema_version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                ManifestIssue::new("$.schema_version", "expected a non-negative integer")
+            })?,
+    };
+    if original > MANIFEST_SCHEMA_VERSION {
+        return Err(ManifestIssue::new(
+            "$.schema_version",
+            format!(
+                "schema_version {} is newer than this launcher supports ({}), please update the launcher",
+                original, MANIFEST_SCHEMA_VERSION
+            ),
+        ));
+    }
+
+    let mut version = original;
+    while version < MANIFEST_SCHEMA_VERSION {
+        match version {
+            // The unversioned format has the same shape as version 1
+            0 => {}
+            _ => unreachable!("no migration from manifest schema {}", version),
+        }
+        version += 1;
+    }
+    root.insert("schema_version".to_string(), serde_json::json!(version));
+    Ok(original)
+}
+
+fn validate_schema(value: &serde_json::Value, issues: &mut Vec<ManifestIssue>) {
+    for error in MANIFEST_VALIDATOR.iter_errors(value) {
+        schema_issues(&error, issues);
+    }
+}
+
+// Turn a schema violation into issues located by JSON path, worded for pack authors
+fn schema_issues(error: &jsonschema::ValidationError, issues: &mut Vec<ManifestIssue>) {
+    use jsonschema::error::ValidationErrorKind;
+
+    let path = json_path(error.instance_path());
+    let schema = schema_parent(error.schema_path());
+    let mut fail = |message: String| issues.push(ManifestIssue::new(&path, message));
+    match error.kind() {
+        ValidationErrorKind::AdditionalProperties { unexpected } => {
+            let known: Vec<&str> = schema
+                .and_then(|s| s.get("properties"))
+                .and_then(|p| p.as_object())
+                .map(|p| p.keys().map(String::as_str).collect())
+                .unwrap_or_default();
+            for key in unexpected {
+                let mut message = "unknown field".to_string();
+                if let Some(suggestion) = closest_field_name(key, &known) {
+                    message.push_str(&format!(" (did you mean `{}`?)", suggestion));
+                }
+                issues.push(ManifestIssue::new(&format!("{}.{}", path, key), message));
+            }
+        }
+        ValidationErrorKind::Required { property } => issues.push(ManifestIssue::new(
+            &format!("{}.{}", path, property.as_str().unwrap_or_default()),
+            "missing required field",
+        )),
+        ValidationErrorKind::Type { .. } if error.instance().is_null() => {
+            fail("must not be null".to_string())
+        }
+        ValidationErrorKind::Type { .. } => {
+            let types = schema.and_then(|s| s.get("type"));
+            let expected = match types {
+                Some(serde_json::Value::Array(types)) => types
+                    .iter()
+                    .filter_map(|t| t.as_str())
+                    .find(|t| *t != "null"),
+                Some(t) => t.as_str(),
+                None => None,
+            };
+            fail(match expected {
+                Some("string") => "expected a string".to_string(),
+                Some("boolean") => "expected true or false".to_string(),
+                Some("integer") => "expected a non-negative integer".to_string(),
+                Some("array") => "expected an array".to_string(),
+                Some("object") => "expected an object".to_string(),
+                _ => error.to_string(),
+            })
+        }
+        ValidationErrorKind::Minimum { .. } => fail("expected a non-negative integer".to_string()),
+        ValidationErrorKind::Pattern { pattern } => fail(
+            match pattern.as_str() {
+                "^https?://" => "expected an http(s) URL",
+                "^[0-9a-fA-F]{64}$" => "expected 64 hex characters",
+                "^[0-9a-fA-F]{128}$" => "expected 128 hex characters",
+                _ => "must not be empty",
+            }
+            .to_string(),
+        ),
+        ValidationErrorKind::Enum { options } => {
+            let choices: Vec<&str> = options
+                .as_array()
+                .map(|o| o.iter().filter_map(|c| c.as_str()).collect())
+                .unwrap_or_default();
+            fail(format!("expected one of {}", choices.join(", ")))
+        }
+        // Optional objects are `anyOf` the object and null; report why the object didn't match
+        ValidationErrorKind::AnyOf { context } => {
+            let only_wrong_type = |errors: &Vec<jsonschema::ValidationError>| {
+                errors.iter().all(|e| {
+                    matches!(e.kind(), ValidationErrorKind::Type { .. })
+                        && e.instance_path() == error.instance_path()
+                })
+            };
+            let branch = context
+                .iter()
+                .find(|errors| !only_wrong_type(errors))
+                .or(context.first());
+            for error in branch.into_iter().flatten() {
+                schema_issues(error, issues);
+            }
+        }
+        _ => fail(error.to_string()),
+    }
+}
+
+// JSON pointer of the failing value as a JSON path like $.extra_mods[1].name
+fn json_path(location: &jsonschema::paths::Location) -> String {
+    let mut path = "$".to_string();
+    for segment in location.iter() {
+        match segment {
+            jsonschema::paths::LocationSegment::Property(name) => {
+                path.push('.');
+                path.push_str(&name);
+            }
+            jsonschema::paths::LocationSegment::Index(index) => {
+                path.push_str(&format!("[{}]", index))
+            }
+        }
+    }
+    path
+}
+
+// The schema object holding the keyword that failed
+fn schema_parent(location: &jsonschema::paths::Location) -> Option<&'static serde_json::Value> {
+    let pointer = location.as_str();
+    let parent = &pointer[..pointer.rfind('/').unwrap_or(0)];
+    MANIFEST_SCHEMA.pointer(parent)
+}
+
+// Suggest the known field a typo was most likely meant to be
+fn closest_field_name<'a>(key: &str, fields: &[&'a str]) -> Option<&'a str> {
+    fields
+        .iter()
+        .map(|name| (edit_distance(key, name), *name))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// The schema can't say that names are unique within a list
+fn validate_unique_names(value: &serde_json::Value, issues: &mut Vec<ManifestIssue>) {
+    for list in ["extra_mods", "overrides"] {
+        let items = value.get(list).and_then(|l| l.as_array());
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for (index, item) in items.into_iter().flatten().enumerate() {
+            if let Some(name) = item.get("name").and_then(|n| n.as_str()) {
+                if let Some(first) = seen.insert(name, index) {
+                    issues.push(ManifestIssue::new(
+                        &format!("$.{}[{}].name", list, index),
+                        format!("duplicate name `{}` (also at $.{}[{}])", name, list, first),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// A direct-URL mod has no Modrinth version to trust, so its sha512 is the only pin
+fn validate_direct_mods(value: &serde_json::Value, issues: &mut Vec<ManifestIssue>) {
+    let extra_mods = value.get("extra_mods").and_then(|m| m.as_array());
+    for (index, extra_mod) in extra_mods.into_iter().flatten().enumerate() {
+        let path = format!("$.extra_mods[{}]", index);
+        let has = |key: &str| extra_mod.get(key).is_some_and(|v| !v.is_null());
+        if has("url") {
+            if !has("sha512") {
+                issues.push(ManifestIssue::new(
+                    &format!("{}.sha512", path),
+                    "required for a mod downloaded from `url`",
+                ));
+            }
+            if has("version_id") {
+                issues.push(ManifestIssue::new(
+                    &format!("{}.version_id", path),
+                    "a mod downloaded from `url` has no Modrinth version",
+                ));
+            }
+        } else if has("path") {
+            issues.push(ManifestIssue::new(
+                &format!("{}.path", path),
+                "only applies to a mod downloaded from `url`",
+            ));
+        }
+        if let Some(target) = extra_mod.get("path").and_then(|p| p.as_str()) {
+            let escapes = std::path::Path::new(target).components().any(|c| {
+                !matches!(
+                    c,
+                    std::path::Component::Normal(_) | std::path::Component::CurDir
+                )
+            });
+            if escapes {
+                issues.push(ManifestIssue::new(
+                    &format!("{}.path", path),
+                    "must be a relative path inside .minecraft",
+                ));
+            }
+        }
+    }
+}
+
+// Parse, migrate and validate manifest JSON. The migrated value is only returned when valid.
+fn check_manifest(text: &str) -> (ManifestValidation, Option<serde_json::Value>) {
+    let mut report = ManifestValidation::default();
+    let mut value: serde_json::Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => {
+            report
+                .errors
+                .push(ManifestIssue::new("$", format!("invalid JSON: {}", e)));
+            return (report, None);
+        }
+    };
+
+    match migrate_manifest(&mut value) {
+        Ok(original) => {
+            report.schema_version = Some(original);
+            report.migrated = original != MANIFEST_SCHEMA_VERSION;
+        }
+        Err(issue) => {
+            report.errors.push(issue);
+            return (report, None);
+        }
+    }
+
+    validate_schema(&value, &mut report.errors);
+    validate_unique_names(&value, &mut report.errors);
+    validate_direct_mods(&value, &mut report.errors);
+    report.valid = report.errors.is_empty();
+    let value = report.valid.then_some(value);
+    (report, value)
+}
+
+pub fn validate_manifest_text(text: &str) -> ManifestValidation {
+    check_manifest(text).0
+}
+
+// Strictly parse a manifest, migrating older schema versions. Every problem is listed in the error.
+pub fn parse_manifest(text: &str) -> Result<StoryManifest, String> {
+    let (report, value) = check_manifest(text);
+    let value = match value {
+        Some(value) => value,
+        None => {
+            let problems: Vec<String> = report.errors.iter().map(|e| e.to_string()).collect();
+            return Err(format!(
+                "Invalid manifest ({} problem(s)):\n - {}",
+                problems.len(),
+                problems.join("\n - ")
+            ));
+        }
+    };
+    if report.migrated {
+        eprintln!(
+            "Migrated manifest from schema {} to {}",
+            report.schema_version.unwrap_or_default(),
+            MANIFEST_SCHEMA_VERSION
+        );
+    }
+    serde_json::from_value(value).map_err(|e| format!("Failed to parse manifest JSON: {}", e))
+}
@@ -0,0 +1,1020 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+use crate::cache::ResponseCache;
+use crate::manifest::{ExtraMod, ReleaseChannel, VersionPreference};
+
+// Add new structs for Modrinth API and manifest handling
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModrinthVersionResponse {
+    pub game_versions: Vec<String>,
+    pub loaders: Vec<String>,
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub version_number: String,
+    pub changelog: Option<String>,
+    pub files: Vec<ModrinthFile>,
+    pub dependencies: Vec<ModrinthDependency>,
+    #[serde(default)]
+    pub version_type: Option<String>,
+    #[serde(default)]
+    pub featured: bool,
+    #[serde(default)]
+    pub date_published: Option<String>,
+}
+
+impl ModrinthVersionResponse {
+    pub fn release_channel(&self) -> ReleaseChannel {
+        match self.version_type.as_deref() {
+            Some("release") | None => ReleaseChannel::Release,
+            Some("beta") => ReleaseChannel::Beta,
+            Some(_) => ReleaseChannel::Alpha,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModrinthFile {
+    pub hashes: HashMap<String, String>,
+    pub url: String,
+    pub filename: String,
+    pub primary: bool,
+    pub size: u64,
+}
+
+// A project from `/projects?ids=`, with the ids of all its versions
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModrinthProject {
+    pub id: String,
+    pub slug: String,
+    pub versions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModrinthDependency {
+    pub version_id: Option<String>,
+    pub project_id: Option<String>,
+    pub file_name: Option<String>,
+    pub dependency_type: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModrinthIndex {
+    #[serde(rename = "formatVersion", default)]
+    pub format_version: u32,
+    #[serde(default)]
+    pub game: String,
+    #[serde(rename = "versionId", default)]
+    pub version_id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub files: Vec<ModrinthIndexFile>,
+    // Minecraft and loader versions the pack was built for, e.g. "fabric-loader" -> "0.16.14"
+    #[serde(default)]
+    pub dependencies: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModrinthIndexFile {
+    pub path: String,
+    pub hashes: HashMap<String, String>,
+    pub downloads: Vec<String>,
+    #[serde(default)]
+    pub env: Option<ModEnv>,
+    #[serde(rename = "fileSize", default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+}
+
+// Which sides a file runs on, as in the mrpack format. A missing side counts as required.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct ModEnv {
+    #[serde(default)]
+    pub client: EnvSupport,
+    #[serde(default)]
+    pub server: EnvSupport,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvSupport {
+    #[default]
+    Required,
+    Optional,
+    Unsupported,
+}
+
+impl ModEnv {
+    // Files without an env run everywhere
+    pub fn runs_on_server(env: Option<&ModEnv>) -> bool {
+        env.is_none_or(|env| env.server != EnvSupport::Unsupported)
+    }
+}
+
+// The file Modrinth marks as primary, or the first one if none is
+pub(crate) fn primary_file(version_info: &ModrinthVersionResponse) -> Option<&ModrinthFile> {
+    version_info
+        .files
+        .iter()
+        .find(|f| f.primary)
+        .or_else(|| version_info.files.first())
+}
+
+// Resolve an extra mod to a concrete version: an exact pin (version id, sha512, filename),
+// the version string from the manifest, or the best match for the instance
+pub(crate) async fn resolve_mod_version(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    extra_mod: &ExtraMod,
+    minecraft_version: &str,
+    loaders: &[String],
+    defaults: &VersionPreference,
+) -> Result<ModrinthVersionResponse, String> {
+    let mod_name = &extra_mod.name;
+    let fetch_version = |api_url: String| async move {
+        eprintln!("Mod API URL: {}", api_url);
+        let response_text = cache
+            .get_text(api.client(), &api_url)
+            .await
+            .map_err(|e| format!("Failed to fetch mod info: {}", e))?;
+        serde_json::from_str::<ModrinthVersionResponse>(&response_text)
+            .map_err(|e| format!("Failed to parse mod info: {}", e))
+    };
+
+    if let Some(version_id) = &extra_mod.version_id {
+        eprintln!(
+            "Resolving mod: {} pinned to version id {}",
+            mod_name, version_id
+        );
+        fetch_version(api.url(&format!("/version/{}", version_id))).await
+    } else if let Some(sha512) = &extra_mod.sha512 {
+        eprintln!("Resolving mod: {} pinned to sha512 {}", mod_name, sha512);
+        fetch_version(api.url(&format!(
+            "/version_file/{}?algorithm=sha512",
+            sha512.to_lowercase()
+        )))
+        .await
+    } else if let Some(filename) = &extra_mod.filename {
+        eprintln!("Resolving mod: {} pinned to file {}", mod_name, filename);
+        let api_url = api.url(&format!("/project/{}/version", mod_name));
+        let response_text = cache
+            .get_text(api.client(), &api_url)
+            .await
+            .map_err(|e| format!("Failed to fetch mod versions: {}", e))?;
+        let versions: Vec<ModrinthVersionResponse> = serde_json::from_str(&response_text)
+            .map_err(|e| format!("Failed to parse mod versions: {}", e))?;
+        versions
+            .into_iter()
+            .find(|v| v.files.iter().any(|f| &f.filename == filename))
+            .ok_or_else(|| format!("No version of {} has a file named {}", mod_name, filename))
+    } else if let Some(version) = &extra_mod.version {
+        eprintln!("Resolving mod: {} v{}", mod_name, version);
+        fetch_version(api.url(&format!("/project/{}/version/{}", mod_name, version))).await
+    } else {
+        eprintln!(
+            "Finding best version for mod: {} with Minecraft {} and loaders {}",
+            mod_name,
+            minecraft_version,
+            loaders.join(", ")
+        );
+
+        // Find the best version for this Minecraft version and loader
+        let preference = extra_mod.version_preference(defaults);
+        find_best_mod_version(
+            api,
+            cache,
+            mod_name,
+            minecraft_version,
+            loaders,
+            &preference,
+        )
+        .await
+    }
+}
+
+// The jar of a resolved version to install. Pinned sha512/filename must match exactly,
+// otherwise the primary jar is used.
+pub fn select_mod_file<'a>(
+    version_info: &'a ModrinthVersionResponse,
+    extra_mod: &ExtraMod,
+) -> Result<&'a ModrinthFile, String> {
+    let file = match (&extra_mod.sha512, &extra_mod.filename) {
+        (Some(sha512), _) => version_info.files.iter().find(|f| {
+            f.hashes
+                .get("sha512")
+                .is_some_and(|h| h.eq_ignore_ascii_case(sha512))
+        }),
+        (None, Some(filename)) => version_info.files.iter().find(|f| &f.filename == filename),
+        (None, None) => return primary_jar(version_info),
+    };
+    let file = file.ok_or_else(|| {
+        format!(
+            "Version {} of {} has no file matching the pinned sha512/filename",
+            version_info.version_number, extra_mod.name
+        )
+    })?;
+    if let Some(filename) = &extra_mod.filename {
+        if &file.filename != filename {
+            return Err(format!(
+                "Pinned sha512 of {} belongs to {}, not {}",
+                extra_mod.name, file.filename, filename
+            ));
+        }
+    }
+    Ok(file)
+}
+
+// Find the primary .jar file
+pub(crate) fn primary_jar(version_info: &ModrinthVersionResponse) -> Result<&ModrinthFile, String> {
+    version_info
+        .files
+        .iter()
+        .find(|f| f.primary && f.filename.ends_with(".jar"))
+        .ok_or_else(|| "No primary .jar file found".to_string())
+}
+
+// A Minecraft version as Modrinth tags them: releases (`1.21.1`, `1.21`), their pre-releases
+// and release candidates (`1.21.1-pre2`, `1.21.1-rc1`) and weekly snapshots (`24w33a`).
+// Releases and snapshots are ordered among themselves; they don't compare to each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinecraftVersion {
+    Release {
+        major: u32,
+        minor: u32,
+        patch: u32,
+        pre_release: Option<PreRelease>,
+    },
+    Snapshot {
+        year: u32,
+        week: u32,
+        build: char,
+    },
+}
+
+// Ordered the way Mojang ships them: pre-releases, then release candidates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreRelease {
+    Pre(u32),
+    Rc(u32),
+}
+
+impl MinecraftVersion {
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        if let Some((year, rest)) = version.split_once('w') {
+            let build = rest.chars().last()?;
+            let week = &rest[..rest.len() - build.len_utf8()];
+            if year.len() == 2 && week.len() == 2 && build.is_ascii_lowercase() {
+                return Some(MinecraftVersion::Snapshot {
+                    year: year.parse().ok()?,
+                    week: week.parse().ok()?,
+                    build,
+                });
+            }
+            return None;
+        }
+
+        // `1.21.1-pre2`, `1.21.1-rc1` and the older `1.14 Pre-Release 3`
+        let (release, pre_release) = match version.split_once(['-', ' ']) {
+            Some((release, suffix)) => {
+                let suffix = suffix.to_lowercase();
+                let pre_release = if let Some(n) = suffix.strip_prefix("pre-release ") {
+                    PreRelease::Pre(n.parse().ok()?)
+                } else if let Some(n) = suffix.strip_prefix("pre") {
+                    PreRelease::Pre(n.parse().ok()?)
+                } else if let Some(n) = suffix.strip_prefix("rc") {
+                    PreRelease::Rc(n.parse().ok()?)
+                } else {
+                    return None;
+                };
+                (release, Some(pre_release))
+            }
+            None => (version, None),
+        };
+        let mut parts = release.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.parse().ok()?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(MinecraftVersion::Release {
+            major,
+            minor,
+            patch,
+            pre_release,
+        })
+    }
+
+    pub fn is_release(&self) -> bool {
+        matches!(
+            self,
+            MinecraftVersion::Release {
+                pre_release: None,
+                ..
+            }
+        )
+    }
+}
+
+impl PartialOrd for MinecraftVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (
+                MinecraftVersion::Release {
+                    major,
+                    minor,
+                    patch,
+                    pre_release,
+                },
+                MinecraftVersion::Release {
+                    major: other_major,
+                    minor: other_minor,
+                    patch: other_patch,
+                    pre_release: other_pre_release,
+                },
+            ) => {
+                // A release comes after all of its pre-releases
+                let stage = |pre: &Option<PreRelease>| (pre.is_none(), *pre);
+                Some((major, minor, patch, stage(pre_release)).cmp(&(
+                    other_major,
+                    other_minor,
+                    other_patch,
+                    stage(other_pre_release),
+                )))
+            }
+            (
+                MinecraftVersion::Snapshot { year, week, build },
+                MinecraftVersion::Snapshot {
+                    year: other_year,
+                    week: other_week,
+                    build: other_build,
+                },
+            ) => Some((year, week, build).cmp(&(other_year, other_week, other_build))),
+            _ => None,
+        }
+    }
+}
+
+// A range of Minecraft versions. Bounds are inclusive unless noted; versions that can't
+// be compared to a bound (a snapshot against a release range) are outside it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinecraftVersionRange {
+    pub min: Option<MinecraftVersion>,
+    pub max: Option<MinecraftVersion>,
+    pub max_exclusive: bool,
+}
+
+impl MinecraftVersionRange {
+    // `1.21.x` style ranges, or space-separated `>=`, `>`, `<=`, `<` and `=` bounds such
+    // as `>=1.20.5 <1.21.2`
+    pub fn parse(range: &str) -> Option<Self> {
+        let range = range.trim();
+        if let Some(minor) = range
+            .strip_suffix(".x")
+            .or_else(|| range.strip_suffix(".*"))
+        {
+            return Self::same_minor(&MinecraftVersion::parse(minor)?);
+        }
+
+        let mut parsed = MinecraftVersionRange {
+            min: None,
+            max: None,
+            max_exclusive: false,
+        };
+        for bound in range.split_whitespace() {
+            if let Some(version) = bound.strip_prefix(">=") {
+                parsed.min = Some(MinecraftVersion::parse(version)?);
+            } else if let Some(version) = bound.strip_prefix("<=") {
+                parsed.max = Some(MinecraftVersion::parse(version)?);
+                parsed.max_exclusive = false;
+            } else if let Some(version) = bound.strip_prefix('<') {
+                parsed.max = Some(MinecraftVersion::parse(version)?);
+                parsed.max_exclusive = true;
+            } else {
+                let version = bound.strip_prefix('=').unwrap_or(bound);
+                let version = MinecraftVersion::parse(version)?;
+                parsed.min = Some(version.clone());
+                parsed.max = Some(version);
+                parsed.max_exclusive = false;
+            }
+        }
+        if parsed.min.is_none() && parsed.max.is_none() {
+            return None;
+        }
+        Some(parsed)
+    }
+
+    // Every release of the version's minor line, from `1.21` up to (not including) `1.22`.
+    // Snapshots have no minor line, and neither does a minor with no successor.
+    pub fn same_minor(version: &MinecraftVersion) -> Option<Self> {
+        match version {
+            MinecraftVersion::Release { major, minor, .. } => Some(MinecraftVersionRange {
+                min: Some(MinecraftVersion::Release {
+                    major: *major,
+                    minor: *minor,
+                    patch: 0,
+                    pre_release: None,
+                }),
+                max: Some(MinecraftVersion::Release {
+                    major: *major,
+                    minor: minor.checked_add(1)?,
+                    patch: 0,
+                    pre_release: Some(PreRelease::Pre(0)),
+                }),
+                max_exclusive: true,
+            }),
+            MinecraftVersion::Snapshot { .. } => None,
+        }
+    }
+
+    pub fn contains(&self, version: &MinecraftVersion) -> bool {
+        use std::cmp::Ordering;
+        let above_min = match &self.min {
+            Some(min) => matches!(
+                version.partial_cmp(min),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            None => true,
+        };
+        let below_max = match &self.max {
+            Some(max) => match version.partial_cmp(max) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => !self.max_exclusive,
+                _ => false,
+            },
+            None => true,
+        };
+        above_min && below_max
+    }
+}
+
+// The game version tag a version was accepted through when it isn't tagged for the
+// instance's exact Minecraft version: another release of the same minor version.
+// None when it is tagged for the exact version or same-minor matching doesn't apply.
+pub fn minecraft_version_fallback(
+    version_info: &ModrinthVersionResponse,
+    minecraft_version: &str,
+) -> Option<String> {
+    if version_info
+        .game_versions
+        .iter()
+        .any(|g| g == minecraft_version)
+    {
+        return None;
+    }
+    let range = MinecraftVersionRange::same_minor(&MinecraftVersion::parse(minecraft_version)?)?;
+    version_info
+        .game_versions
+        .iter()
+        .find(|g| MinecraftVersion::parse(g).is_some_and(|v| v.is_release() && range.contains(&v)))
+        .cloned()
+}
+
+// Index of the version to install from a project's version list (newest first): compatible
+// with the Minecraft version and loader, within the release channel, featured first if asked.
+// Versions tagged for the same minor Minecraft version are only considered, if accepted,
+// when none is tagged for the exact version.
+fn best_version_index(
+    versions: &[ModrinthVersionResponse],
+    mod_name: &str,
+    minecraft_version: &str,
+    loader: &str,
+    preference: &VersionPreference,
+) -> Result<usize, String> {
+    let for_loader =
+        (0..versions.len()).filter(|&i| versions[i].loaders.iter().any(|l| l == loader));
+    let mut compatible: Vec<usize> = for_loader
+        .clone()
+        .filter(|&i| {
+            versions[i]
+                .game_versions
+                .iter()
+                .any(|g| g == minecraft_version)
+        })
+        .collect();
+    if compatible.is_empty() && preference.accept_same_minor {
+        compatible = for_loader
+            .filter(|&i| minecraft_version_fallback(&versions[i], minecraft_version).is_some())
+            .collect();
+    }
+    let newest = match compatible.first() {
+        Some(&newest) => &versions[newest],
+        None => {
+            return Err(format!(
+                "No compatible version found for mod {} with Minecraft {} and loader {}",
+                mod_name, minecraft_version, loader
+            ))
+        }
+    };
+
+    let channel = match preference.channel {
+        Some(channel) => channel,
+        None => compatible
+            .iter()
+            .map(|&i| versions[i].release_channel())
+            .min()
+            .unwrap_or(ReleaseChannel::Release),
+    };
+    let in_channel = |i: &&usize| versions[**i].release_channel() <= channel;
+    let featured = if preference.prefer_featured {
+        compatible
+            .iter()
+            .find(|i| versions[**i].featured && in_channel(i))
+    } else {
+        None
+    };
+    match featured.or_else(|| compatible.iter().find(in_channel)) {
+        Some(&index) => Ok(index),
+        None => Err(format!(
+            "No {:?} version of mod {} for Minecraft {} and loader {} (newest is {} {})",
+            channel,
+            mod_name,
+            minecraft_version,
+            loader,
+            newest.version_type.as_deref().unwrap_or("release"),
+            newest.version_number
+        )),
+    }
+}
+
+pub fn select_best_version(
+    mut versions: Vec<ModrinthVersionResponse>,
+    mod_name: &str,
+    minecraft_version: &str,
+    loader: &str,
+    preference: &VersionPreference,
+) -> Result<ModrinthVersionResponse, String> {
+    let index = best_version_index(&versions, mod_name, minecraft_version, loader, preference)?;
+    Ok(versions.swap_remove(index))
+}
+
+// Like select_best_version, trying each loader in order (the instance loader first, then
+// its fallbacks). A fallback is only used when no earlier loader has a usable version.
+pub fn select_version_with_fallbacks(
+    mut versions: Vec<ModrinthVersionResponse>,
+    mod_name: &str,
+    minecraft_version: &str,
+    loaders: &[String],
+    preference: &VersionPreference,
+) -> Result<ModrinthVersionResponse, String> {
+    let mut first_error = None;
+    for loader in loaders {
+        match best_version_index(&versions, mod_name, minecraft_version, loader, preference) {
+            Ok(index) => return Ok(versions.swap_remove(index)),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| format!("No loader to find mod {} for", mod_name)))
+}
+
+// The fallback loader a version was matched through, or None if it supports the
+// instance loader itself
+pub fn loader_fallback(
+    version_info: &ModrinthVersionResponse,
+    loaders: &[String],
+) -> Option<String> {
+    let (loader, fallbacks) = loaders.split_first()?;
+    if version_info.loaders.contains(loader) {
+        return None;
+    }
+    fallbacks
+        .iter()
+        .find(|l| version_info.loaders.contains(l))
+        .cloned()
+}
+
+// Loaders whose mods an instance loader can also run, most preferred first
+pub fn default_loader_fallbacks(loader: &str, minecraft_version: &str) -> Vec<String> {
+    match loader {
+        // Quilt loads Fabric mods
+        "quilt" => vec!["fabric".to_string()],
+        // NeoForge for 1.20.1 is still a Forge fork and loads Forge mods
+        "neoforge" if minecraft_version == "1.20.1" => vec!["forge".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+// Function to find the best version for a mod given a Minecraft version and loader
+pub(crate) async fn find_best_mod_version(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    mod_name: &str,
+    minecraft_version: &str,
+    loaders: &[String],
+    preference: &VersionPreference,
+) -> Result<ModrinthVersionResponse, String> {
+    eprintln!(
+        "Finding best version for mod {} with Minecraft {} and loaders {} ({:?})",
+        mod_name,
+        minecraft_version,
+        loaders.join(", "),
+        preference
+    );
+
+    let api_url = candidate_versions_url(api, mod_name, minecraft_version, loaders, preference);
+    let versions = fetch_project_versions(api, cache, &api_url).await?;
+    pick_best_mod_version(versions, mod_name, minecraft_version, loaders, preference)
+}
+
+// The version listing to choose an unpinned mod's version from. Same-minor matching
+// needs every version for the loaders, not just the exact tags.
+pub(crate) fn candidate_versions_url(
+    api: &ModrinthApi,
+    mod_name: &str,
+    minecraft_version: &str,
+    loaders: &[String],
+    preference: &VersionPreference,
+) -> String {
+    let game_version = (!preference.accept_same_minor).then_some(minecraft_version);
+    api.project_versions_url(mod_name, game_version, loaders)
+}
+
+pub(crate) async fn fetch_project_versions(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    api_url: &str,
+) -> Result<Vec<ModrinthVersionResponse>, String> {
+    eprintln!("Fetching versions from: {}", api_url);
+    let response_text = cache
+        .get_text(api.client(), api_url)
+        .await
+        .map_err(|e| format!("Failed to fetch mod versions: {}", e))?;
+    serde_json::from_str(&response_text).map_err(|e| format!("Failed to parse mod versions: {}", e))
+}
+
+pub(crate) fn pick_best_mod_version(
+    versions: Vec<ModrinthVersionResponse>,
+    mod_name: &str,
+    minecraft_version: &str,
+    loaders: &[String],
+    preference: &VersionPreference,
+) -> Result<ModrinthVersionResponse, String> {
+    eprintln!("Found {} versions for mod {}", versions.len(), mod_name);
+
+    let version =
+        select_version_with_fallbacks(versions, mod_name, minecraft_version, loaders, preference)?;
+    eprintln!(
+        "Found compatible version: {} ({}, {}) for MC {} and loaders {}",
+        version.version_number,
+        version.id,
+        version.version_type.as_deref().unwrap_or("release"),
+        minecraft_version,
+        version.loaders.join(",")
+    );
+    if let Some(tag) = minecraft_version_fallback(&version, minecraft_version) {
+        eprintln!(
+            "⚠️ Warning: {} {} is not tagged for Minecraft {}; accepting it for {} (same minor version)",
+            mod_name, version.version_number, minecraft_version, tag
+        );
+    }
+    Ok(version)
+}
+
+pub const MODRINTH_API_BASE: &str = "https://api.modrinth.com/v2";
+pub const DEFAULT_USER_AGENT: &str = concat!("canefe/story-launcher/", env!("CARGO_PKG_VERSION"));
+
+// Where Modrinth API requests go: a staging Labrinth, a mirror or a local mock server
+// instead of api.modrinth.com, with the User-Agent Modrinth asks clients to send and
+// an optional token (a Modrinth personal access token, or whatever the mirror expects).
+// API requests that take longer than `timeout_secs` fail instead of stalling an install.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ModrinthSettings {
+    pub api_base: String,
+    pub user_agent: String,
+    pub token: Option<String>,
+    pub timeout_secs: u64,
+}
+
+impl Default for ModrinthSettings {
+    fn default() -> Self {
+        ModrinthSettings {
+            api_base: MODRINTH_API_BASE.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            token: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+// The Modrinth API as configured. API requests go through `client()`, which carries the
+// token; everything else (CDN downloads, manifests) uses `http()`, which only sends the
+// User-Agent, so the token never reaches other hosts.
+#[derive(Clone)]
+pub struct ModrinthApi {
+    base: String,
+    client: reqwest::Client,
+    http: reqwest::Client,
+}
+
+impl ModrinthApi {
+    pub fn new(settings: &ModrinthSettings) -> Result<Self, String> {
+        let base = settings.api_base.trim_end_matches('/').to_string();
+        if reqwest::Url::parse(&base).is_err() {
+            return Err(format!(
+                "Invalid Modrinth API base URL: {}",
+                settings.api_base
+            ));
+        }
+        let user_agent = match settings.user_agent.trim() {
+            "" => DEFAULT_USER_AGENT,
+            user_agent => user_agent,
+        };
+        let http = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(token) = settings.token.as_deref().filter(|t| !t.is_empty()) {
+            let mut value = reqwest::header::HeaderValue::from_str(token)
+                .map_err(|_| "Invalid Modrinth API token".to_string())?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+        let mut client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .default_headers(headers);
+        if settings.timeout_secs > 0 {
+            client = client.timeout(std::time::Duration::from_secs(settings.timeout_secs));
+        }
+        let client = client
+            .build()
+            .map_err(|e| format!("Failed to create Modrinth API client: {}", e))?;
+
+        Ok(ModrinthApi { base, client, http })
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    // An API URL from a path such as `/version/{id}`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    // Versions of a project filtered server-side to the given loaders and, if given, one
+    // Minecraft version
+    pub fn project_versions_url(
+        &self,
+        mod_name: &str,
+        minecraft_version: Option<&str>,
+        loaders: &[String],
+    ) -> String {
+        let base = self.url(&format!("/project/{}/version", mod_name));
+        let mut params = vec![("loaders", serde_json::json!(loaders).to_string())];
+        if let Some(minecraft_version) = minecraft_version {
+            params.push((
+                "game_versions",
+                serde_json::json!([minecraft_version]).to_string(),
+            ));
+        }
+        match reqwest::Url::parse_with_params(&base, &params) {
+            Ok(url) => url.to_string(),
+            Err(_) => base,
+        }
+    }
+
+    pub fn bulk_versions_url(&self, ids: &[String]) -> String {
+        self.bulk_url("/versions", ids)
+    }
+
+    // Projects by id or slug
+    pub fn bulk_projects_url(&self, ids: &[String]) -> String {
+        self.bulk_url("/projects", ids)
+    }
+
+    fn bulk_url(&self, path: &str, ids: &[String]) -> String {
+        let base = self.url(path);
+        match reqwest::Url::parse_with_params(&base, &[("ids", serde_json::json!(ids).to_string())])
+        {
+            Ok(url) => url.to_string(),
+            Err(_) => base,
+        }
+    }
+}
+
+impl Default for ModrinthApi {
+    fn default() -> Self {
+        ModrinthApi::new(&ModrinthSettings::default()).expect("default Modrinth settings are valid")
+    }
+}
+
+// Read modrinth.index.json out of an .mrpack
+pub fn read_mrpack_index(mrpack_bytes: &[u8]) -> Result<ModrinthIndex, String> {
+    let mut zip = ZipArchive::new(Cursor::new(mrpack_bytes))
+        .map_err(|e| format!("Failed to open mrpack as zip: {}", e))?;
+    let mut index_file = zip
+        .by_name("modrinth.index.json")
+        .map_err(|e| format!("mrpack has no modrinth.index.json: {}", e))?;
+    let mut content = String::new();
+    index_file
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read modrinth.index.json: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse modrinth.index.json: {}", e))
+}
+
+// Projects the modpack already installs, mapped to their version ids. Taken from the
+// Modrinth CDN URLs in the index: https://cdn.modrinth.com/data/{project}/versions/{version}/{file}
+pub fn pack_projects(index: &ModrinthIndex) -> HashMap<String, String> {
+    let mut projects = HashMap::new();
+    for file in &index.files {
+        for url in &file.downloads {
+            let path = match url.strip_prefix("https://cdn.modrinth.com/data/") {
+                Some(path) => path,
+                None => continue,
+            };
+            let parts: Vec<&str> = path.split('/').collect();
+            if parts.len() >= 4 && parts[1] == "versions" {
+                projects.insert(parts[0].to_string(), parts[2].to_string());
+                break;
+            }
+        }
+    }
+    projects
+}
+
+// Modrinth's bulk lookups: many projects or versions by id, or versions by file hash, per
+// request. Ids go out in chunks to keep URLs short. Found versions are also cached under
+// their single-version URL, which is what one-off lookups (and offline installs) read.
+const MODRINTH_BULK_CHUNK: usize = 100;
+
+pub(crate) fn cache_response<T: Serialize>(cache: &ResponseCache, url: &str, value: &T) {
+    if cache.offline {
+        return;
+    }
+    if let Ok(body) = serde_json::to_string(value) {
+        if let Err(e) = cache.store(url, &body) {
+            eprintln!("Warning: Failed to cache response for {}: {}", url, e);
+        }
+    }
+}
+
+// Versions by id; ids Modrinth doesn't know are left out
+pub(crate) async fn fetch_versions_bulk(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    ids: &[String],
+) -> Result<HashMap<String, ModrinthVersionResponse>, String> {
+    let mut found = HashMap::new();
+    for version_info in fetch_version_chunks(api, cache, ids).await? {
+        let single_url = api.url(&format!("/version/{}", version_info.id));
+        cache_response(cache, &single_url, &version_info);
+        found.insert(version_info.id.clone(), version_info);
+    }
+    Ok(found)
+}
+
+async fn fetch_version_chunks(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    ids: &[String],
+) -> Result<Vec<ModrinthVersionResponse>, String> {
+    let mut found = Vec::new();
+    for chunk in ids.chunks(MODRINTH_BULK_CHUNK) {
+        let api_url = api.bulk_versions_url(chunk);
+        eprintln!("Fetching {} versions from: {}", chunk.len(), api_url);
+        let response_text = cache
+            .get_text(api.client(), &api_url)
+            .await
+            .map_err(|e| format!("Failed to fetch versions: {}", e))?;
+        let versions: Vec<ModrinthVersionResponse> = serde_json::from_str(&response_text)
+            .map_err(|e| format!("Failed to parse versions: {}", e))?;
+        found.extend(versions);
+    }
+    Ok(found)
+}
+
+// Every version of each project (by id or slug), newest first like a project's own version
+// list, keyed by the id or slug asked for. Projects Modrinth doesn't know are left out.
+pub(crate) async fn fetch_project_versions_bulk(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    projects: &[String],
+) -> Result<HashMap<String, Vec<ModrinthVersionResponse>>, String> {
+    let mut found: Vec<ModrinthProject> = Vec::new();
+    for chunk in projects.chunks(MODRINTH_BULK_CHUNK) {
+        let api_url = api.bulk_projects_url(chunk);
+        eprintln!("Fetching {} projects from: {}", chunk.len(), api_url);
+        let response_text = cache
+            .get_text(api.client(), &api_url)
+            .await
+            .map_err(|e| format!("Failed to fetch projects: {}", e))?;
+        let chunk_projects: Vec<ModrinthProject> = serde_json::from_str(&response_text)
+            .map_err(|e| format!("Failed to parse projects: {}", e))?;
+        found.extend(chunk_projects);
+    }
+
+    let version_ids: Vec<String> = found.iter().flat_map(|p| p.versions.clone()).collect();
+    let versions: HashMap<String, ModrinthVersionResponse> =
+        fetch_version_chunks(api, cache, &version_ids)
+            .await?
+            .into_iter()
+            .map(|version_info| (version_info.id.clone(), version_info))
+            .collect();
+
+    let mut listings = HashMap::new();
+    for project in projects {
+        let info = found
+            .iter()
+            .find(|p| &p.id == project || p.slug.eq_ignore_ascii_case(project));
+        if let Some(info) = info {
+            let mut listing: Vec<ModrinthVersionResponse> = info
+                .versions
+                .iter()
+                .filter_map(|id| versions.get(id).cloned())
+                .collect();
+            listing.sort_by(|a, b| b.date_published.cmp(&a.date_published));
+            listings.insert(project.clone(), listing);
+        }
+    }
+    Ok(listings)
+}
+
+// The versions that own files with the given sha512 hashes, keyed by hash. With
+// `latest_for` (loaders, Minecraft versions) it is instead the newest version of each
+// hash's project for those, which tells whether an installed file is still current.
+pub(crate) async fn fetch_versions_by_hash(
+    api: &ModrinthApi,
+    cache: &ResponseCache,
+    hashes: &[String],
+    latest_for: Option<(&[String], &[String])>,
+) -> Result<HashMap<String, ModrinthVersionResponse>, String> {
+    let mut found = HashMap::new();
+    for chunk in hashes.chunks(MODRINTH_BULK_CHUNK) {
+        let (api_url, body) = match latest_for {
+            Some((loaders, game_versions)) => (
+                api.url("/version_files/update"),
+                serde_json::json!({
+                    "hashes": chunk,
+                    "algorithm": "sha512",
+                    "loaders": loaders,
+                    "game_versions": game_versions
+                }),
+            ),
+            None => (
+                api.url("/version_files"),
+                serde_json::json!({ "hashes": chunk, "algorithm": "sha512" }),
+            ),
+        };
+        eprintln!("Looking up {} file hashes at {}", chunk.len(), api_url);
+        let response_text = cache
+            .post_text(api.client(), &api_url, &body)
+            .await
+            .map_err(|e| format!("Failed to look up file hashes: {}", e))?;
+        let versions: HashMap<String, ModrinthVersionResponse> =
+            serde_json::from_str(&response_text)
+                .map_err(|e| format!("Failed to parse file hash lookup: {}", e))?;
+        for (sha512, version_info) in versions {
+            if latest_for.is_none() {
+                let single_url = api.url(&format!("/version_file/{}?algorithm=sha512", sha512));
+                cache_response(cache, &single_url, &version_info);
+            }
+            found.insert(sha512.to_lowercase(), version_info);
+        }
+    }
+    Ok(found)
+}
+
+// Whether the newest version Modrinth reports for an installed file (see
+// fetch_versions_by_hash) shows the file is still what selection would pick. Only
+// certain for a release tagged for the exact Minecraft version and the instance loader
+// when featured versions aren't preferred; anything else needs the full version list.
+pub fn is_latest_install(
+    latest: &ModrinthVersionResponse,
+    installed_sha512: &str,
+    minecraft_version: &str,
+    loader: &str,
+    preference: &VersionPreference,
+) -> bool {
+    latest.files.iter().any(|f| {
+        f.hashes
+            .get("sha512")
+            .is_some_and(|sha| sha.eq_ignore_ascii_case(installed_sha512))
+    }) && latest.release_channel() == ReleaseChannel::Release
+        && !preference.prefer_featured
+        && latest.loaders.iter().any(|l| l == loader)
+        && latest.game_versions.iter().any(|g| g == minecraft_version)
+}
@@ -0,0 +1,571 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::cache::{fetch_into_blob_store, gc_blob_store, sha256_hex, ResponseCache};
+use crate::curseforge::curseforge_download_urls;
+use crate::install::LauncherContext;
+use crate::instance::{
+    create_unmanaged_instance_config, record_managed_files, safe_join, ManagedFile, ManagedSource,
+};
+use crate::manifest::{
+    verify_manifest_trust, InstanceConfig, PackwizRef, StoryManifest, MANIFEST_SCHEMA_VERSION,
+};
+
+// pack.toml: the root of a packwiz pack
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizPack {
+    pub name: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(rename = "pack-format", default)]
+    pub pack_format: Option<String>,
+    pub index: PackwizIndexRef,
+    // "minecraft" plus the loader: "fabric", "quilt", "forge" or "neoforge"
+    #[serde(default)]
+    pub versions: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizIndexRef {
+    pub file: String,
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+    pub hash: String,
+}
+
+// index.toml: every file of the pack, relative to the index
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizIndex {
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+    #[serde(default)]
+    pub files: Vec<PackwizIndexFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizIndexFile {
+    pub file: String,
+    pub hash: String,
+    // Overrides the index's hash-format for this file
+    #[serde(rename = "hash-format", default)]
+    pub hash_format: Option<String>,
+    // A .pw.toml describing a file to download, rather than the file itself
+    #[serde(default)]
+    pub metafile: bool,
+    // Install under this path instead of `file`
+    #[serde(default)]
+    pub alias: Option<String>,
+    // Only written when missing, so players can edit it
+    #[serde(default)]
+    pub preserve: bool,
+}
+
+// A .pw.toml metafile
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizMod {
+    pub name: String,
+    pub filename: String,
+    #[serde(default)]
+    pub side: PackwizSide,
+    pub download: PackwizDownload,
+    #[serde(default)]
+    pub update: Option<PackwizUpdate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizDownload {
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+    pub hash: String,
+    // "metadata:curseforge" when there is no URL and the file comes from CurseForge
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizUpdate {
+    #[serde(default)]
+    pub curseforge: Option<PackwizCurseForge>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackwizCurseForge {
+    #[serde(rename = "file-id")]
+    pub file_id: u64,
+    #[serde(rename = "project-id")]
+    pub project_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PackwizSide {
+    #[default]
+    Both,
+    Client,
+    Server,
+}
+
+impl PackwizSide {
+    // Whether a file for this side belongs in an install for `target` (Client or Server)
+    pub fn installs_on(self, target: PackwizSide) -> bool {
+        self == PackwizSide::Both || target == PackwizSide::Both || self == target
+    }
+}
+
+// What installing a packwiz pack placed, for .managed_files.json
+pub(crate) struct PackwizInstall {
+    pub(crate) pack: PackwizPack,
+    pub(crate) files: Vec<ManagedFile>,
+    pub(crate) skipped_other_side: Vec<String>,
+}
+
+// Whether a manifest URL points at a packwiz pack.toml rather than a JSON manifest
+pub fn is_packwiz_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.ends_with(".toml")
+}
+
+// The hash of `bytes` in one of packwiz's hash formats, as packwiz writes it
+pub fn packwiz_hash(format: &str, bytes: &[u8]) -> Result<String, String> {
+    match format {
+        "sha1" => Ok(format!("{:x}", Sha1::digest(bytes))),
+        "sha256" => Ok(format!("{:x}", Sha256::digest(bytes))),
+        "sha512" => Ok(format!("{:x}", Sha512::digest(bytes))),
+        "md5" => Ok(format!("{:x}", Md5::digest(bytes))),
+        "murmur2" => Ok(curseforge_murmur2(bytes).to_string()),
+        other => Err(format!("Unsupported packwiz hash format: {}", other)),
+    }
+}
+
+// CurseForge's fingerprint: 32-bit MurmurHash2 with seed 1 over the file without
+// whitespace bytes
+fn curseforge_murmur2(bytes: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let data: Vec<u8> = bytes
+        .iter()
+        .copied()
+        .filter(|b| !matches!(b, 9 | 10 | 13 | 32))
+        .collect();
+
+    let mut hash = 1 ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        hash = hash.wrapping_mul(M) ^ k;
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            hash ^= (*byte as u32) << (8 * i);
+        }
+        hash = hash.wrapping_mul(M);
+    }
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(M);
+    hash ^ (hash >> 15)
+}
+
+fn check_hash(what: &str, format: &str, expected: &str, bytes: &[u8]) -> Result<(), String> {
+    let actual = packwiz_hash(format, bytes)?;
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} {} mismatch (expected {}, got {})",
+            what, format, expected, actual
+        ))
+    }
+}
+
+fn join_url(base: &str, relative: &str) -> Result<String, String> {
+    reqwest::Url::parse(base)
+        .and_then(|base| base.join(relative))
+        .map(|url| url.to_string())
+        .map_err(|e| {
+            format!(
+                "Invalid packwiz URL {} relative to {}: {}",
+                relative, base, e
+            )
+        })
+}
+
+// A packwiz TOML file, fetched through the response cache and checked against its hash
+async fn fetch_toml<T: serde::de::DeserializeOwned>(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    url: &str,
+    hash: Option<(&str, &str)>,
+) -> Result<T, String> {
+    let text = cache
+        .get_text(ctx.api().http(), url)
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    if let Some((format, expected)) = hash {
+        check_hash(url, format, expected, text.as_bytes())?;
+    }
+    toml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", url, e))
+}
+
+// A pack.toml installed directly stands in for the manifest, so it needs the same
+// signature. The returned reference is pinned to the bytes that were checked.
+pub(crate) async fn fetch_trusted_pack(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    pack_url: &str,
+) -> Result<PackwizRef, String> {
+    let client = ctx.api().http();
+    let text = cache
+        .get_text(client, pack_url)
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", pack_url, e))?;
+    let trust = verify_manifest_trust(ctx, client, cache, pack_url, &text).await?;
+    eprintln!("Packwiz pack trust: {:?}", trust);
+    Ok(PackwizRef {
+        url: pack_url.to_string(),
+        sha256: sha256_hex(text.as_bytes()),
+    })
+}
+
+// pack.toml, refused unless it matches the sha256 it was pinned to
+async fn fetch_pack(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    packwiz: &PackwizRef,
+) -> Result<PackwizPack, String> {
+    fetch_toml(ctx, cache, &packwiz.url, Some(("sha256", &packwiz.sha256))).await
+}
+
+// What a pack.toml installed directly amounts to as a manifest, for update checks
+pub(crate) async fn packwiz_manifest(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    pack_url: &str,
+) -> Result<StoryManifest, String> {
+    let packwiz = fetch_trusted_pack(ctx, cache, pack_url).await?;
+    let pack = fetch_pack(ctx, cache, &packwiz).await?;
+    let loader = ["fabric", "quilt", "forge", "neoforge"]
+        .into_iter()
+        .find(|loader| pack.versions.contains_key(*loader))
+        .map(str::to_string);
+    Ok(StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        instance: InstanceConfig {
+            name: pack.name,
+            version: pack.version.unwrap_or_else(|| "1.0.0".to_string()),
+            minecraft_version: pack.versions.get("minecraft").cloned(),
+            loader,
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
+        },
+        extra_mods: None,
+        overrides: None,
+        lockfile: None,
+        packwiz: Some(packwiz),
+    })
+}
+
+// The mrpack dependencies block for a pack.toml's [versions]
+pub fn packwiz_dependencies(pack: &PackwizPack) -> HashMap<String, String> {
+    let mut dependencies = HashMap::new();
+    for (component, version) in &pack.versions {
+        let dependency = match component.as_str() {
+            "minecraft" => "minecraft",
+            "fabric" => "fabric-loader",
+            "quilt" => "quilt-loader",
+            "forge" => "forge",
+            "neoforge" => "neoforge",
+            other => {
+                eprintln!("⚠️ Warning: Unknown packwiz version component {}", other);
+                continue;
+            }
+        };
+        dependencies.insert(dependency.to_string(), version.clone());
+    }
+    dependencies
+}
+
+// One file to place: where it goes, where it comes from and how to check it
+struct PackwizTarget {
+    path: String,
+    urls: Vec<String>,
+    hash_format: String,
+    hash: String,
+    preserve: bool,
+}
+
+// Install the files of a packwiz pack into `target_dir`, leaving out files for the other
+// side. pack.toml is checked against its pin, files and metafiles against index.toml, and
+// downloads against their .pw.toml, before anything is placed.
+pub(crate) async fn install_packwiz_files(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    packwiz: &PackwizRef,
+    target_dir: &Path,
+    side: PackwizSide,
+) -> Result<PackwizInstall, String> {
+    eprintln!("Reading packwiz pack from: {}", packwiz.url);
+    let pack = fetch_pack(ctx, cache, packwiz).await?;
+    let index_url = join_url(&packwiz.url, &pack.index.file)?;
+    let index: PackwizIndex = fetch_toml(
+        ctx,
+        cache,
+        &index_url,
+        Some((&pack.index.hash_format, &pack.index.hash)),
+    )
+    .await?;
+    eprintln!(
+        "Packwiz pack {} lists {} files",
+        pack.name,
+        index.files.len()
+    );
+
+    // Read every metafile first, so CurseForge-only files resolve in one request
+    let mut targets: Vec<PackwizTarget> = Vec::new();
+    let mut curseforge_targets: Vec<(PackwizTarget, u64, String)> = Vec::new();
+    let mut skipped_other_side = Vec::new();
+    for entry in &index.files {
+        let url = join_url(&index_url, &entry.file)?;
+        let hash_format = entry
+            .hash_format
+            .clone()
+            .unwrap_or_else(|| index.hash_format.clone());
+        if !entry.metafile {
+            targets.push(PackwizTarget {
+                path: entry.alias.clone().unwrap_or_else(|| entry.file.clone()),
+                urls: vec![url],
+                hash_format,
+                hash: entry.hash.clone(),
+                preserve: entry.preserve,
+            });
+            continue;
+        }
+
+        let metafile: PackwizMod =
+            match fetch_toml(ctx, cache, &url, Some((&hash_format, &entry.hash))).await {
+                Ok(metafile) => metafile,
+                Err(e) => {
+                    cache.record_missing(format!("{}: {}", entry.file, e));
+                    continue;
+                }
+            };
+        // The file lands next to its metafile, e.g. mods/sodium.pw.toml -> mods/<filename>
+        let folder = Path::new(entry.alias.as_deref().unwrap_or(&entry.file))
+            .parent()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let path = match folder.as_str() {
+            "" => metafile.filename.clone(),
+            folder => format!("{}/{}", folder, metafile.filename),
+        };
+        if !metafile.side.installs_on(side) {
+            eprintln!("Skipping {} ({:?} only)", path, metafile.side);
+            skipped_other_side.push(path);
+            continue;
+        }
+
+        let target = PackwizTarget {
+            path,
+            urls: metafile.download.url.clone().into_iter().collect(),
+            hash_format: metafile.download.hash_format.clone(),
+            hash: metafile.download.hash.clone(),
+            preserve: entry.preserve,
+        };
+        let curseforge = metafile.update.as_ref().and_then(|u| u.curseforge.as_ref());
+        match (&metafile.download.mode, curseforge) {
+            (Some(mode), Some(curseforge)) if mode == "metadata:curseforge" => {
+                curseforge_targets.push((target, curseforge.file_id, metafile.name.clone()));
+            }
+            _ if target.urls.is_empty() => {
+                cache.record_missing(format!("{}: no download URL", target.path));
+            }
+            _ => targets.push(target),
+        }
+    }
+
+    if !curseforge_targets.is_empty() {
+        let file_ids: Vec<u64> = curseforge_targets.iter().map(|(_, id, _)| *id).collect();
+        let urls = curseforge_download_urls(ctx, cache, &file_ids).await?;
+        for (mut target, file_id, name) in curseforge_targets {
+            match urls.get(&file_id).cloned().flatten() {
+                Some(url) => {
+                    target.urls.push(url);
+                    targets.push(target);
+                }
+                None => cache.record_missing(format!(
+                    "{}: {} does not allow third-party downloads, download it manually from CurseForge",
+                    target.path, name
+                )),
+            }
+        }
+    }
+
+    let client = ctx.api().http();
+    let store = ctx.blob_store();
+    let mut files = Vec::new();
+    for (position, target) in targets.iter().enumerate() {
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": ((position as f64 / targets.len() as f64) * 100.0) as u32,
+                "current": position + 1,
+                "total": targets.len(),
+                "filename": format!("Downloading packwiz file: {}", target.path),
+                "stage": "packwiz"
+            }),
+        );
+
+        let Some(dest) = safe_join(target_dir, &target.path) else {
+            eprintln!(
+                "⚠️ Warning: Skipping file outside the instance: {}",
+                target.path
+            );
+            continue;
+        };
+        if target.preserve && dest.exists() {
+            eprintln!("Keeping player-edited {}", target.path);
+            continue;
+        }
+
+        let known_sha512 = match target.hash_format.as_str() {
+            "sha512" => Some(target.hash.to_lowercase()),
+            _ => target.urls.first().and_then(|url| cache.load_sha512(url)),
+        };
+        let sha512 = match fetch_into_blob_store(
+            client,
+            &store,
+            &target.urls,
+            known_sha512.as_deref(),
+            cache.offline,
+        )
+        .await
+        {
+            Ok(sha512) => sha512,
+            Err(e) => {
+                eprintln!("Warning: Failed to download {}: {}", target.path, e);
+                cache.record_missing(format!("{}: {}", target.path, e));
+                continue;
+            }
+        };
+        if target.hash_format != "sha512" {
+            let bytes = std::fs::read(store.blob_path(&sha512))
+                .map_err(|e| format!("Failed to read {}: {}", target.path, e))?;
+            if let Err(e) = check_hash(&target.path, &target.hash_format, &target.hash, &bytes) {
+                cache.record_missing(e);
+                continue;
+            }
+            if let Some(url) = target.urls.first() {
+                cache.store_sha512(url, &sha512);
+            }
+        }
+
+        // Configs and other files players edit are copied, so an edit can't reach the blob
+        let placed = if target.path.ends_with(".jar") {
+            store.link_to(&sha512, &dest)
+        } else {
+            store.copy_to(&sha512, &dest)
+        };
+        placed.map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+        if let Some(filename) = dest.file_name().and_then(|n| n.to_str()) {
+            if target.path.starts_with("mods/") {
+                ctx.session().track_downloaded_file(filename);
+            }
+        }
+        files.push(ManagedFile {
+            path: target.path.clone(),
+            sha512,
+            source: ManagedSource::Packwiz {
+                urls: target.urls.clone(),
+            },
+        });
+    }
+
+    eprintln!(
+        "✅ Installed {} packwiz files, skipped {} for the other side",
+        files.len(),
+        skipped_other_side.len()
+    );
+    Ok(PackwizInstall {
+        pack,
+        files,
+        skipped_other_side,
+    })
+}
+
+// download_from_manifest for a pack.toml URL: the Story instance is built from the
+// packwiz pack alone, with no mrpack behind it
+pub(crate) async fn install_packwiz_instance(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    pack_url: &str,
+    story_path: &Path,
+) -> Result<String, String> {
+    eprintln!("=== INSTALLING PACKWIZ PACK ===");
+    let minecraft_dir = story_path.join(".minecraft");
+    std::fs::create_dir_all(&minecraft_dir).map_err(|e| {
+        format!(
+            "Failed to create .minecraft directory {}: {}",
+            minecraft_dir.display(),
+            e
+        )
+    })?;
+
+    let packwiz = fetch_trusted_pack(ctx, cache, pack_url).await?;
+    let installed =
+        install_packwiz_files(ctx, cache, &packwiz, &minecraft_dir, PackwizSide::Client).await?;
+    let pack = &installed.pack;
+    create_unmanaged_instance_config(story_path, &pack.name, &packwiz_dependencies(pack))?;
+    cache.ensure_complete()?;
+
+    match record_managed_files(ctx, story_path, installed.files.clone()) {
+        Ok(count) => eprintln!("💾 Recorded {} managed files", count),
+        Err(e) => eprintln!("⚠️ Warning: Failed to record managed files: {}", e),
+    }
+    let version = pack.version.clone().unwrap_or_else(|| "1.0.0".to_string());
+    let version_info = serde_json::json!({
+        "instance_name": pack.name,
+        "instance_version": version,
+        "packwiz": { "url": packwiz.url, "sha256": packwiz.sha256 },
+        "last_updated": chrono::Utc::now().to_rfc3339()
+    });
+    std::fs::write(
+        story_path.join(".current_version.json"),
+        serde_json::to_string_pretty(&version_info).unwrap(),
+    )
+    .map_err(|e| format!("Failed to save version info: {}", e))?;
+
+    if let Err(e) = gc_blob_store(ctx, None).await {
+        eprintln!("⚠️ Warning: Blob cache GC failed: {}", e);
+    }
+
+    let result = format!(
+        "✅ Successfully installed packwiz pack {} v{}: {} files ({} skipped as server-only)",
+        pack.name,
+        version,
+        installed.files.len(),
+        installed.skipped_other_side.len()
+    );
+    let missing = cache.missing();
+    if missing.is_empty() {
+        Ok(result)
+    } else {
+        Ok(format!(
+            "{}\n⚠️ {} item(s) could not be downloaded:\n - {}",
+            result,
+            missing.len(),
+            missing.join("\n - ")
+        ))
+    }
+}
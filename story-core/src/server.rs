@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::Cursor;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::cache::{fetch_into_blob_store, gc_blob_store, ResponseCache};
+use crate::install::{
+    fetch_and_extract_zip, fetch_trusted_manifest, install_locked_mods, LauncherContext,
+};
+use crate::instance::safe_join;
+use crate::lockfile::{load_or_resolve_lock, resolve_pack_file, LockedMod};
+use crate::modrinth::{read_mrpack_index, ModEnv, ModrinthIndex};
+use crate::packwiz::{install_packwiz_files, PackwizSide};
+
+// Where server launchers and installers are downloaded from. Pointed at mirrors, or a
+// local mock server in tests, the same way as the Modrinth API.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LoaderSources {
+    pub fabric_meta: String,
+    pub quilt_installer: String,
+    pub forge_maven: String,
+    pub neoforge_maven: String,
+}
+
+impl Default for LoaderSources {
+    fn default() -> Self {
+        LoaderSources {
+            fabric_meta: "https://meta.fabricmc.net".to_string(),
+            quilt_installer: "https://quiltmc.org/api/v1/download-latest-installer/java-universal"
+                .to_string(),
+            forge_maven: "https://maven.minecraftforge.net".to_string(),
+            neoforge_maven: "https://maven.neoforged.net/releases".to_string(),
+        }
+    }
+}
+
+// What a server install laid out, and what is left to do before the server starts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerInstallReport {
+    pub server_dir: String,
+    pub minecraft_version: String,
+    pub loader: String,
+    pub loader_version: String,
+    // The jar that starts the server, or the installer to run first
+    pub launcher: String,
+    pub next_step: String,
+    pub installed_files: usize,
+    // Pack files and extra mods that only run on the client
+    pub skipped_client_only: Vec<String>,
+    pub eula_accepted: bool,
+    pub missing: Vec<String>,
+}
+
+// The locked mods a server needs: extra mods that run on the server and everything they
+// depend on. Returns those and the names of the mods left out.
+pub fn server_side_mods(mods: &[LockedMod]) -> (Vec<LockedMod>, Vec<String>) {
+    let mut needed: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<&LockedMod> = mods
+        .iter()
+        .filter(|m| {
+            m.requested
+                .as_ref()
+                .is_some_and(|requested| ModEnv::runs_on_server(requested.env.as_ref()))
+        })
+        .collect();
+    while let Some(locked) = queue.pop_front() {
+        if !needed.insert(&locked.file.project_id) {
+            continue;
+        }
+        for dependency in &locked.dependencies {
+            queue.extend(mods.iter().filter(|m| &m.file.project_id == dependency));
+        }
+    }
+
+    let mut server_mods = Vec::new();
+    let mut skipped = Vec::new();
+    for locked in mods {
+        if needed.contains(locked.file.project_id.as_str()) {
+            server_mods.push(locked.clone());
+        } else {
+            skipped.push(locked.name.clone());
+        }
+    }
+    (server_mods, skipped)
+}
+
+// The mod loader an mrpack was built for and its version, from its dependencies block
+fn pack_loader(index: &ModrinthIndex) -> Result<(String, String), String> {
+    for loader in ["fabric-loader", "quilt-loader", "neoforge", "forge"] {
+        if let Some(version) = index.dependencies.get(loader) {
+            return Ok((loader.to_string(), version.clone()));
+        }
+    }
+    Err("The modpack does not name a mod loader in its dependencies".to_string())
+}
+
+// Download a loader file into the server directory unless it is already there
+async fn download_loader_file(
+    client: &reqwest::Client,
+    cache: &ResponseCache,
+    url: &str,
+    target: &Path,
+) -> Result<(), String> {
+    if target.is_file() {
+        eprintln!("Already downloaded: {}", target.display());
+        return Ok(());
+    }
+    if cache.offline {
+        return Err(format!(
+            "{} is not downloaded yet (offline mode)",
+            target.display()
+        ));
+    }
+
+    eprintln!("Downloading {} from {}", target.display(), url);
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+    std::fs::write(target, &bytes)
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))
+}
+
+// Put the loader's server launcher, or its installer where there is no ready-made
+// launcher, in the server directory. Returns the jar and how to start from there.
+async fn install_server_launcher(
+    ctx: &LauncherContext,
+    cache: &ResponseCache,
+    server_dir: &Path,
+    minecraft_version: &str,
+    loader: &str,
+    loader_version: &str,
+) -> Result<(String, String), String> {
+    let sources = ctx.settings().loaders;
+    let client = ctx.api().http();
+
+    let (launcher, url, next_step) = match loader {
+        "fabric-loader" => {
+            let installers_url = format!("{}/v2/versions/installer", sources.fabric_meta);
+            let installers_text = cache
+                .get_text(client, &installers_url)
+                .await
+                .map_err(|e| format!("Failed to fetch Fabric installer versions: {}", e))?;
+            let installers: Vec<serde_json::Value> = serde_json::from_str(&installers_text)
+                .map_err(|e| format!("Failed to parse Fabric installer versions: {}", e))?;
+            let installer = installers
+                .iter()
+                .find(|i| i["stable"].as_bool() == Some(true))
+                .or_else(|| installers.first())
+                .and_then(|i| i["version"].as_str())
+                .ok_or("No Fabric installer version found")?;
+
+            let launcher = format!(
+                "fabric-server-mc.{}-loader.{}-launcher.{}.jar",
+                minecraft_version, loader_version, installer
+            );
+            let url = format!(
+                "{}/v2/versions/loader/{}/{}/{}/server/jar",
+                sources.fabric_meta, minecraft_version, loader_version, installer
+            );
+            let next_step = format!("java -jar {} nogui", launcher);
+            (launcher, url, next_step)
+        }
+        "quilt-loader" => {
+            let launcher = "quilt-installer.jar".to_string();
+            let next_step = format!(
+                "java -jar {} install server {} {} --download-server --install-dir=.",
+                launcher, minecraft_version, loader_version
+            );
+            (launcher, sources.quilt_installer.clone(), next_step)
+        }
+        "forge" => {
+            let launcher = format!(
+                "forge-{}-{}-installer.jar",
+                minecraft_version, loader_version
+            );
+            let url = format!(
+                "{}/net/minecraftforge/forge/{}-{}/{}",
+                sources.forge_maven, minecraft_version, loader_version, launcher
+            );
+            let next_step = format!("java -jar {} --installServer", launcher);
+            (launcher, url, next_step)
+        }
+        "neoforge" => {
+            let launcher = format!("neoforge-{}-installer.jar", loader_version);
+            let url = format!(
+                "{}/net/neoforged/neoforge/{}/{}",
+                sources.neoforge_maven, loader_version, launcher
+            );
+            let next_step = format!("java -jar {} --installServer", launcher);
+            (launcher, url, next_step)
+        }
+        other => return Err(format!("Unsupported server loader: {}", other)),
+    };
+
+    download_loader_file(client, cache, &url, &server_dir.join(&launcher)).await?;
+    Ok((launcher, next_step))
+}
+
+// Extract the pack's `overrides/` and then `server-overrides/` on top; `client-overrides/`
+// never reaches a server
+fn extract_server_overrides(mrpack_bytes: &[u8], server_dir: &Path) -> Result<usize, String> {
+    let mut zip = ZipArchive::new(Cursor::new(mrpack_bytes))
+        .map_err(|e| format!("Failed to open mrpack as zip: {}", e))?;
+
+    let mut extracted = 0;
+    for prefix in ["overrides/", "server-overrides/"] {
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).map_err(|e| e.to_string())?;
+            let relative = match file.name().strip_prefix(prefix) {
+                Some(relative) if !relative.is_empty() && !file.is_dir() => relative.to_string(),
+                _ => continue,
+            };
+            let output_path = safe_join(server_dir, &relative)
+                .ok_or_else(|| format!("Refusing to extract {} outside the server", relative))?;
+
+            if let Some(parent) = output_path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            // Don't write through a hardlink into the blob store
+            if output_path.is_file() {
+                std::fs::remove_file(&output_path).map_err(|e| e.to_string())?;
+            }
+            let mut output_file = File::create(&output_path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut file, &mut output_file).map_err(|e| e.to_string())?;
+            extracted += 1;
+        }
+    }
+    Ok(extracted)
+}
+
+// Lay out a dedicated server from the same manifest a client installs: the pack's
+// server-side files and overrides, the extra mods the server needs, manifest overrides
+// and the loader's server launcher. Downloads go through the same lockfile, blob store
+// and response cache as client installs. The EULA is only accepted when asked to.
+pub async fn install_server_from_manifest(
+    ctx: &LauncherContext,
+    manifest_url: String,
+    server_dir: String,
+    accept_eula: bool,
+    offline: bool,
+) -> Result<ServerInstallReport, String> {
+    eprintln!("=== INSTALL_SERVER START ===");
+    eprintln!("Manifest URL: {}", manifest_url);
+    eprintln!("Server directory: {}", server_dir);
+    eprintln!("Offline mode: {}", offline);
+
+    let cache = ctx.response_cache(offline);
+    let ctx = &ctx.begin_install();
+
+    let server_path = Path::new(&server_dir);
+    std::fs::create_dir_all(server_path).map_err(|e| {
+        let error_msg = format!(
+            "Failed to create server directory {}: {}",
+            server_path.display(),
+            e
+        );
+        eprintln!("ERROR: {}", error_msg);
+        error_msg
+    })?;
+
+    let api = ctx.api();
+    let client = api.http();
+    let manifest = fetch_trusted_manifest(ctx, &cache, &manifest_url).await?;
+    eprintln!(
+        "Installing server for: {} v{}",
+        manifest.instance.name, manifest.instance.version
+    );
+
+    // The server keeps its own lockfile, pinned exactly like a client instance
+    let store = ctx.blob_store();
+    let lock = load_or_resolve_lock(api, &cache, &store, &manifest, server_path, false)
+        .await
+        .map_err(|e| {
+            eprintln!("ERROR: {}", e);
+            e
+        })?;
+
+    // Step 1: the pack's files, minus the client-only ones
+    eprintln!("=== STEP 1: SERVER FILES FROM THE MODPACK ===");
+    let pack = match &lock.pack {
+        Some(pack) => pack.clone(),
+        None => resolve_pack_file(api, &cache, &manifest.instance).await?,
+    };
+    let mrpack_sha512 = fetch_into_blob_store(
+        client,
+        &store,
+        std::slice::from_ref(&pack.url),
+        Some(&pack.sha512),
+        offline,
+    )
+    .await
+    .map_err(|e| format!("Failed to download mrpack {}: {}", pack.filename, e))?;
+    let mrpack_bytes = std::fs::read(store.blob_path(&mrpack_sha512))
+        .map_err(|e| format!("Failed to read mrpack bytes: {}", e))?;
+    let index = read_mrpack_index(&mrpack_bytes)?;
+
+    let mut installed_files = 0;
+    let mut skipped_client_only = Vec::new();
+    for (i, pack_file) in index.files.iter().enumerate() {
+        if !ModEnv::runs_on_server(pack_file.env.as_ref()) {
+            eprintln!("Skipping client-only file: {}", pack_file.path);
+            skipped_client_only.push(pack_file.path.clone());
+            continue;
+        }
+
+        ctx.emit(
+            "download_progress",
+            serde_json::json!({
+                "percent": ((i as f64 / index.files.len() as f64) * 50.0) as u32,
+                "current": i + 1,
+                "total": index.files.len(),
+                "filename": format!("Downloading server file: {}", pack_file.path),
+                "stage": "server"
+            }),
+        );
+
+        let Some(target) = safe_join(server_path, &pack_file.path) else {
+            cache.record_missing(format!("{}: invalid path", pack_file.path));
+            continue;
+        };
+        let linked = match fetch_into_blob_store(
+            client,
+            &store,
+            &pack_file.downloads,
+            pack_file.hashes.get("sha512").map(|h| h.as_str()),
+            offline,
+        )
+        .await
+        {
+            Ok(sha512) => store.link_to(&sha512, &target),
+            Err(e) => Err(e),
+        };
+        match linked {
+            Ok(true) => installed_files += 1,
+            Ok(false) => cache.record_missing(format!("{}: not in the blob store", pack_file.path)),
+            Err(e) => cache.record_missing(format!("{}: {}", pack_file.path, e)),
+        }
+    }
+
+    let extracted = extract_server_overrides(&mrpack_bytes, server_path)?;
+    eprintln!("Extracted {} pack override files", extracted);
+    installed_files += extracted;
+
+    // Step 2: extra mods that run on the server, with their dependencies
+    eprintln!("=== STEP 2: SERVER-SIDE EXTRA MODS ===");
+    let (server_mods, client_only_mods) = server_side_mods(&lock.mods);
+    for name in &client_only_mods {
+        eprintln!("Skipping client-only mod: {}", name);
+    }
+    skipped_client_only.extend(client_only_mods);
+    if !server_mods.is_empty() {
+        let mods_dir = server_path.join("mods");
+        std::fs::create_dir_all(&mods_dir)
+            .map_err(|e| format!("Failed to create mods directory: {}", e))?;
+        install_locked_mods(ctx, client, &cache, &server_mods, &mods_dir).await?;
+        installed_files += server_mods.len();
+    }
+
+    // Packwiz files that run on the server
+    if let Some(packwiz) = &manifest.packwiz {
+        eprintln!("=== STEP 2b: SERVER-SIDE PACKWIZ FILES ===");
+        let installed =
+            install_packwiz_files(ctx, &cache, packwiz, server_path, PackwizSide::Server).await?;
+        installed_files += installed.files.len();
+        skipped_client_only.extend(installed.skipped_other_side);
+    }
+
+    // Step 3: manifest overrides meant for the server
+    eprintln!("=== STEP 3: SERVER OVERRIDES ===");
+    for override_item in manifest.overrides.iter().flatten() {
+        if !ModEnv::runs_on_server(override_item.env.as_ref()) {
+            eprintln!("Skipping client-only override: {}", override_item.name);
+            skipped_client_only.push(override_item.name.clone());
+            continue;
+        }
+        if let Err(e) = fetch_and_extract_zip(
+            ctx,
+            override_item.url.clone(),
+            server_dir.clone(),
+            false,
+            offline,
+            override_item.sha256.clone(),
+            override_item.size,
+        )
+        .await
+        {
+            eprintln!(
+                "Warning: Failed to download override {}: {}",
+                override_item.name, e
+            );
+            cache.record_missing(format!(
+                "override {} ({}): {}",
+                override_item.name, override_item.url, e
+            ));
+        }
+    }
+
+    // Step 4: the loader's server launcher
+    eprintln!("=== STEP 4: SERVER LAUNCHER ===");
+    let minecraft_version = index
+        .dependencies
+        .get("minecraft")
+        .cloned()
+        .unwrap_or_else(|| lock.minecraft_version.clone());
+    let (loader, loader_version) = pack_loader(&index)?;
+    let (launcher, next_step) = install_server_launcher(
+        ctx,
+        &cache,
+        server_path,
+        &minecraft_version,
+        &loader,
+        &loader_version,
+    )
+    .await?;
+    eprintln!("Server launcher: {}", launcher);
+
+    cache.ensure_complete()?;
+
+    // Step 5: the EULA, only on explicit request
+    let eula_path = server_path.join("eula.txt");
+    if accept_eula {
+        let eula = format!(
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA (https://aka.ms/MinecraftEULA).\n#{}\neula=true\n",
+            chrono::Utc::now().to_rfc2822()
+        );
+        std::fs::write(&eula_path, eula)
+            .map_err(|e| format!("Failed to write {}: {}", eula_path.display(), e))?;
+        eprintln!("✅ Accepted the Minecraft EULA");
+    }
+    let eula_accepted = std::fs::read_to_string(&eula_path)
+        .map(|content| content.lines().any(|line| line.trim() == "eula=true"))
+        .unwrap_or(false);
+    if !eula_accepted {
+        eprintln!(
+            "⚠️ The Minecraft EULA has not been accepted; the server won't start until it is"
+        );
+    }
+
+    match gc_blob_store(ctx, None).await {
+        Ok(report) => eprintln!(
+            "✅ Blob cache GC finished: {} blobs removed, {} bytes remaining",
+            report.removed, report.remaining_bytes
+        ),
+        Err(e) => eprintln!("⚠️ Warning: Blob cache GC failed: {}", e),
+    }
+
+    ctx.emit(
+        "download_progress",
+        serde_json::json!({
+            "percent": 100,
+            "current": 1,
+            "total": 1,
+            "filename": "Server install completed",
+            "stage": "complete"
+        }),
+    );
+
+    eprintln!("=== INSTALL_SERVER COMPLETE ===");
+    Ok(ServerInstallReport {
+        server_dir,
+        minecraft_version,
+        loader,
+        loader_version,
+        launcher,
+        next_step,
+        installed_files,
+        skipped_client_only,
+        eula_accepted,
+        missing: cache.missing(),
+    })
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::curseforge::CurseForgeSettings;
+use crate::modrinth::ModrinthSettings;
+use crate::server::LoaderSources;
+
+// Launcher-wide settings stored in the app data directory
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LauncherSettings {
+    pub blob_cache_max_bytes: u64,
+    // Base64 Ed25519 public keys allowed to sign manifests
+    pub trusted_manifest_keys: Vec<String>,
+    // Explicit opt-in to install manifests that have no signature
+    pub allow_unsigned_manifests: bool,
+    pub modrinth: ModrinthSettings,
+    // Where CurseForge pack imports resolve their files
+    pub curseforge: CurseForgeSettings,
+    // Where dedicated server installs get loader launchers and installers
+    pub loaders: LoaderSources,
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        LauncherSettings {
+            blob_cache_max_bytes: 4 * 1024 * 1024 * 1024, // 4 GiB
+            trusted_manifest_keys: Vec::new(),
+            allow_unsigned_manifests: false,
+            modrinth: ModrinthSettings::default(),
+            curseforge: CurseForgeSettings::default(),
+            loaders: LoaderSources::default(),
+        }
+    }
+}
+
+pub fn load_launcher_settings(app_data_dir: &Path) -> LauncherSettings {
+    let settings_path = app_data_dir.join("settings.json");
+    std::fs::read_to_string(&settings_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_launcher_settings(
+    app_data_dir: &Path,
+    settings: &LauncherSettings,
+) -> Result<(), String> {
+    std::fs::create_dir_all(app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    let settings_json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    std::fs::write(app_data_dir.join("settings.json"), settings_json)
+        .map_err(|e| format!("Failed to write settings: {}", e))
+}
@@ -1,4 +1,4 @@
-use story_launcher_lib::*;
+use story_core::*;
 use std::collections::HashMap;
 
 // Tests for API-related functionality and data structures
@@ -46,6 +46,9 @@ fn test_modrinth_version_response_structure() {
                 dependency_type: "optional".to_string(),
             },
         ],
+        version_type: None,
+        featured: false,
+        date_published: None,
     };
     
     // Test serialization
@@ -137,6 +140,8 @@ fn test_modrinth_index_file_structure() {
             "https://cdn.modrinth.com/data/test/versions/1.0.0/test-mod.jar".to_string(),
             "https://backup.example.com/test-mod.jar".to_string(),
         ],
+        env: None,
+        file_size: None,
     };
     
     // Test structure validation
@@ -163,11 +168,18 @@ fn test_modrinth_index_complete_structure() {
     hashes2.insert("sha256".to_string(), "hash2".to_string());
     
     let index = ModrinthIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: "1.0.0".to_string(),
+        name: "test-pack".to_string(),
+        summary: None,
         files: vec![
             ModrinthIndexFile {
                 path: "mods/mod1.jar".to_string(),
                 hashes: hashes1,
                 downloads: vec!["https://example.com/mod1.jar".to_string()],
+                env: None,
+                file_size: None,
             },
             ModrinthIndexFile {
                 path: "mods/mod2.jar".to_string(),
@@ -176,8 +188,11 @@ fn test_modrinth_index_complete_structure() {
                     "https://example.com/mod2.jar".to_string(),
                     "https://backup.example.com/mod2.jar".to_string(),
                 ],
+                env: None,
+                file_size: None,
             },
         ],
+        dependencies: HashMap::new(),
     };
     
     assert_eq!(index.files.len(), 2);
@@ -200,36 +215,73 @@ fn test_modrinth_index_complete_structure() {
 #[test]
 fn test_story_manifest_complete_workflow() {
     let manifest = StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
         instance: InstanceConfig {
             name: "Fabulously Optimized".to_string(),
             version: "6.4.0".to_string(),
             minecraft_version: Some("1.21.1".to_string()),
             loader: Some("fabric".to_string()),
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
         },
         extra_mods: Some(vec![
             ExtraMod {
                 name: "jei".to_string(),
                 version: Some("12.3.0.0".to_string()),
+                version_id: None,
+                sha512: None,
+                filename: None,
+                release_channel: None,
+                prefer_featured: None,
+                env: None,
+                url: None,
+                path: None,
             },
             ExtraMod {
                 name: "modmenu".to_string(),
                 version: None, // Auto-detect version
+                version_id: None,
+                sha512: None,
+                filename: None,
+                release_channel: None,
+                prefer_featured: None,
+                env: None,
+                url: None,
+                path: None,
             },
             ExtraMod {
                 name: "wthit".to_string(),
                 version: Some("7.2.0".to_string()),
+                version_id: None,
+                sha512: None,
+                filename: None,
+                release_channel: None,
+                prefer_featured: None,
+                env: None,
+                url: None,
+                path: None,
             },
         ]),
         overrides: Some(vec![
             Override {
                 name: "config".to_string(),
                 url: "https://example.com/config-override.zip".to_string(),
+                sha256: None,
+                size: None,
+                env: None,
             },
             Override {
                 name: "resourcepacks".to_string(),
                 url: "https://example.com/resourcepacks.zip".to_string(),
+                sha256: None,
+                size: None,
+                env: None,
             },
         ]),
+        lockfile: None,
+        packwiz: None,
     };
     
     // Test serialization
@@ -266,6 +318,7 @@ fn test_file_info_tracking() {
     let file_info = FileInfo {
         hash: "sha256hash1234567890abcdef".to_string(),
         last_modified: "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        etag: None,
     };
     
     // Test serialization
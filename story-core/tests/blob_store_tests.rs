@@ -0,0 +1,104 @@
+use std::fs;
+use story_core::*;
+use tempfile::TempDir;
+
+// Tests for the content-addressed blob store shared between instances
+#[test]
+fn test_blob_store_insert_and_link() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+
+    let sha512 = store.insert_bytes(b"sodium jar contents").unwrap();
+    assert_eq!(sha512, sha512_hex(b"sodium jar contents"));
+    assert!(store.contains(&sha512));
+
+    // Blobs are sharded by the first two hex characters of the hash
+    let blob_path = store.blob_path(&sha512);
+    assert!(blob_path.starts_with(temp_dir.path().join("blobs").join(&sha512[..2])));
+
+    // Linking into two instances yields identical files
+    let dest_a = temp_dir.path().join("InstanceA/.minecraft/mods/sodium.jar");
+    let dest_b = temp_dir.path().join("InstanceB/.minecraft/mods/sodium.jar");
+    assert!(store.link_to(&sha512, &dest_a).unwrap());
+    assert!(store.link_to(&sha512, &dest_b).unwrap());
+    assert_eq!(fs::read(&dest_a).unwrap(), b"sodium jar contents");
+    assert_eq!(fs::read(&dest_b).unwrap(), b"sodium jar contents");
+
+    // Linking over an existing file replaces it
+    assert!(store.link_to(&sha512, &dest_a).unwrap());
+
+    let stats = store.stats(1024);
+    assert_eq!(stats.blobs, 1);
+    assert_eq!(stats.total_bytes, b"sodium jar contents".len() as u64);
+}
+
+#[test]
+fn test_blob_store_missing_blob() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+
+    let missing = sha512_hex(b"never stored");
+    assert!(!store.contains(&missing));
+    assert!(!store
+        .link_to(&missing, &temp_dir.path().join("out.jar"))
+        .unwrap());
+    assert!(!temp_dir.path().join("out.jar").exists());
+}
+
+#[test]
+fn test_blob_store_rejects_hash_mismatch() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+
+    let wrong_hash = sha512_hex(b"expected content");
+    let result = store.insert_bytes_checked(b"tampered content", &wrong_hash);
+    assert!(result.is_err());
+    assert!(!store.contains(&sha512_hex(b"tampered content")));
+
+    let ok = store.insert_bytes_checked(b"expected content", &wrong_hash.to_uppercase());
+    assert!(ok.is_ok());
+}
+
+#[test]
+fn test_blob_store_gc_evicts_least_recently_used() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+
+    let old = store.insert_bytes(&[1u8; 100]).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let recent = store.insert_bytes(&[2u8; 100]).unwrap();
+
+    // Under the limit nothing is removed
+    let report = store.gc(1000).unwrap();
+    assert_eq!(report.removed, 0);
+    assert_eq!(report.remaining_bytes, 200);
+
+    // Over the limit the oldest blob goes first
+    let report = store.gc(150).unwrap();
+    assert_eq!(report.removed, 1);
+    assert_eq!(report.freed_bytes, 100);
+    assert!(!store.contains(&old));
+    assert!(store.contains(&recent));
+}
+
+#[test]
+fn test_launcher_settings_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // Missing settings fall back to defaults
+    let settings = load_launcher_settings(temp_dir.path());
+    assert_eq!(
+        settings.blob_cache_max_bytes,
+        LauncherSettings::default().blob_cache_max_bytes
+    );
+
+    let settings = LauncherSettings {
+        blob_cache_max_bytes: 512,
+        ..Default::default()
+    };
+    save_launcher_settings(temp_dir.path(), &settings).unwrap();
+    assert_eq!(
+        load_launcher_settings(temp_dir.path()).blob_cache_max_bytes,
+        512
+    );
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use common::*;
+use story_core::*;
+use tempfile::TempDir;
+
+const API: &str = "https://api.modrinth.com/v2";
+
+// A version whose dependencies are pinned to exact versions
+fn pinned_version(
+    project_id: &str,
+    version_id: &str,
+    sha512: &str,
+    dependencies: &[(&str, &str)],
+) -> serde_json::Value {
+    let filename = format!("{}.jar", version_id);
+    let mut version = cdn_version_json(project_id, version_id, &filename, sha512, &[]);
+    for (project_id, version_id) in dependencies {
+        version = with_dependency(version, "required", Some(project_id), Some(version_id));
+    }
+    version
+}
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[tokio::test]
+async fn test_manifest_resolves_from_bulk_lookups_only() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().join("offline"), true);
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+    let api = ModrinthApi::default();
+    let put = |key: String, body: serde_json::Value| cache.store(&key, &body.to_string()).unwrap();
+
+    // Two mods pinned by version id, one by hash and one unpinned. The offline cache
+    // only has the bulk responses, so any one-off lookup would fail.
+    put(
+        ModrinthApi::default().bulk_versions_url(&ids(&["iris-1", "lithium-1"])),
+        serde_json::json!([
+            pinned_version("iris", "iris-1", "aa01", &[("fabric-api", "fapi-2")]),
+            pinned_version("lithium", "lithium-1", "aa02", &[("fabric-api", "fapi-2")]),
+        ]),
+    );
+    put(
+        ResponseCache::post_key(
+            &format!("{}/version_files", API),
+            &serde_json::json!({ "hashes": ["aa03"], "algorithm": "sha512" }),
+        ),
+        serde_json::json!({ "aa03": pinned_version("modmenu", "modmenu-1", "aa03", &[]) }),
+    );
+    // The unpinned mod's version list comes from its project's version ids, oldest first,
+    // and those versions in no particular order. The newest one for Fabric wins.
+    put(
+        ModrinthApi::default().bulk_projects_url(&ids(&["sodium"])),
+        serde_json::json!([{
+            "id": "AANobbMI",
+            "slug": "sodium",
+            "versions": ["sodium-1", "sodium-2", "sodium-3"]
+        }]),
+    );
+    let published = |version: serde_json::Value, date: &str| {
+        let mut version = version;
+        version["date_published"] = date.into();
+        version
+    };
+    let mut forge_only = pinned_version("sodium", "sodium-3", "aa06", &[]);
+    forge_only["loaders"] = serde_json::json!(["forge"]);
+    put(
+        ModrinthApi::default().bulk_versions_url(&ids(&["sodium-1", "sodium-2", "sodium-3"])),
+        serde_json::json!([
+            published(forge_only, "2024-09-01T00:00:00Z"),
+            published(
+                pinned_version("sodium", "sodium-1", "aa04", &[]),
+                "2024-01-01T00:00:00Z"
+            ),
+            published(
+                pinned_version("sodium", "sodium-2", "aa07", &[]),
+                "2024-06-01T00:00:00Z"
+            ),
+        ]),
+    );
+    // The dependency pin shared by both mods is fetched once, in the next wave
+    put(
+        ModrinthApi::default().bulk_versions_url(&ids(&["fapi-2"])),
+        serde_json::json!([pinned_version("fabric-api", "fapi-2", "aa05", &[])]),
+    );
+
+    let mut iris = extra_mod("iris");
+    iris.version_id = Some("iris-1".to_string());
+    let mut lithium = extra_mod("lithium");
+    lithium.version_id = Some("lithium-1".to_string());
+    let mut modmenu = extra_mod("modmenu");
+    modmenu.sha512 = Some("AA03".to_string());
+    let manifest = StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        instance: InstanceConfig {
+            name: "story-pack".to_string(),
+            version: "1.0.0".to_string(),
+            minecraft_version: Some("1.21.1".to_string()),
+            loader: Some("fabric".to_string()),
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
+        },
+        extra_mods: Some(vec![iris, lithium, modmenu, extra_mod("sodium")]),
+        overrides: None,
+        lockfile: None,
+        packwiz: None,
+    };
+
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+    // Only the modpack, which isn't cached here, fails
+    assert_eq!(failures.len(), 1, "{:?}", failures);
+    assert!(failures[0].starts_with("modpack"));
+
+    let locked: Vec<(&str, &str)> = lock
+        .mods
+        .iter()
+        .map(|m| (m.name.as_str(), m.file.version_id.as_str()))
+        .collect();
+    assert_eq!(
+        locked,
+        vec![
+            ("iris", "iris-1"),
+            ("lithium", "lithium-1"),
+            ("modmenu", "modmenu-1"),
+            ("sodium", "sodium-2"),
+            ("fabric-api", "fapi-2"),
+        ]
+    );
+    assert_eq!(lock.mods[1].dependencies, vec!["fabric-api"]);
+}
+
+#[test]
+fn test_bulk_urls() {
+    assert_eq!(
+        ModrinthApi::default().bulk_versions_url(&ids(&["a", "b"])),
+        "https://api.modrinth.com/v2/versions?ids=%5B%22a%22%2C%22b%22%5D"
+    );
+    assert_eq!(
+        ModrinthApi::default().bulk_projects_url(&ids(&["sodium"])),
+        "https://api.modrinth.com/v2/projects?ids=%5B%22sodium%22%5D"
+    );
+    assert_eq!(
+        ResponseCache::post_key(
+            "https://api.modrinth.com/v2/version_files",
+            &serde_json::json!({ "hashes": ["aa"] })
+        ),
+        "POST https://api.modrinth.com/v2/version_files {\"hashes\":[\"aa\"]}"
+    );
+}
+
+#[test]
+fn test_latest_install_check() {
+    let latest: ModrinthVersionResponse =
+        serde_json::from_value(pinned_version("sodium", "sodium-2", "AA02", &[])).unwrap();
+    let defaults = VersionPreference::default();
+
+    assert!(is_latest_install(
+        &latest, "aa02", "1.21.1", "fabric", &defaults
+    ));
+    // A newer file exists
+    assert!(!is_latest_install(
+        &latest, "aa01", "1.21.1", "fabric", &defaults
+    ));
+    // Only tagged for another loader or Minecraft version: needs the full version list
+    assert!(!is_latest_install(
+        &latest, "aa02", "1.21.1", "quilt", &defaults
+    ));
+    assert!(!is_latest_install(
+        &latest, "aa02", "1.21", "fabric", &defaults
+    ));
+
+    // Featured preferences and pre-releases can pick something else than the newest
+    let featured = VersionPreference {
+        prefer_featured: true,
+        ..VersionPreference::default()
+    };
+    assert!(!is_latest_install(
+        &latest, "aa02", "1.21.1", "fabric", &featured
+    ));
+    let mut beta = latest.clone();
+    beta.version_type = Some("beta".to_string());
+    assert!(!is_latest_install(
+        &beta, "aa02", "1.21.1", "fabric", &defaults
+    ));
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::*;
+use std::path::Path;
+use story_core::*;
+use tempfile::TempDir;
+
+struct CliOutput {
+    status: i32,
+    json: serde_json::Value,
+    stderr: String,
+}
+
+async fn story_cli(data_dir: &Path, args: &[&str]) -> CliOutput {
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_story-cli"))
+        .arg("--data-dir")
+        .arg(data_dir)
+        .args(args)
+        .output()
+        .await
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    CliOutput {
+        status: output.status.code().unwrap(),
+        // Library logs must not leak into stdout, or this stops parsing
+        json: serde_json::from_str(&stdout).unwrap_or(serde_json::Value::Null),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }
+}
+
+// A mock Modrinth with a one-mod pack and a manifest pointing at it
+async fn published_pack(data_dir: &Path) -> MockServer {
+    let server = MockServer::start().await;
+    save_launcher_settings(data_dir, &test_settings(&server)).unwrap();
+    publish_sodium_modpack(&server, &[]);
+    publish_manifest(&server, serde_json::json!({}));
+    server
+}
+
+#[tokio::test]
+async fn test_cli_install_verify_and_clean() {
+    let data_dir = TempDir::new().unwrap();
+    let instances = TempDir::new().unwrap();
+    let instances_arg = instances.path().to_str().unwrap();
+    let server = published_pack(data_dir.path()).await;
+    let manifest_url = server.url("/story-manifest.json");
+
+    let install = story_cli(
+        data_dir.path(),
+        &["install", &manifest_url, "--instances", instances_arg],
+    )
+    .await;
+    assert_eq!(install.status, 0, "{}", install.stderr);
+    assert_eq!(install.json["command"], "install");
+    assert_eq!(install.json["ok"], true);
+    assert!(install.json["result"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("✅"));
+    let mods_dir = instances.path().join("Story/.minecraft/mods");
+    assert_eq!(
+        std::fs::read(mods_dir.join("sodium-0.6.0.jar")).unwrap(),
+        SODIUM
+    );
+
+    let updates = story_cli(
+        data_dir.path(),
+        &["check-updates", &manifest_url, "--instances", instances_arg],
+    )
+    .await;
+    assert_eq!(updates.status, 0, "{}", updates.stderr);
+    assert_eq!(updates.json["result"]["has_updates"], false);
+
+    // A hand-copied jar shows up in verify and clean removes it
+    std::fs::write(mods_dir.join("unmanaged.jar"), b"dropped in by hand").unwrap();
+    let verify = story_cli(data_dir.path(), &["verify", "--instances", instances_arg]).await;
+    assert_eq!(verify.status, 0, "{}", verify.stderr);
+    assert_eq!(verify.json["result"]["extra"][0], "mods/unmanaged.jar");
+
+    let dry_run = story_cli(
+        data_dir.path(),
+        &["clean", "--instances", instances_arg, "--dry-run"],
+    )
+    .await;
+    assert_eq!(dry_run.json["result"]["removed"][0], "mods/unmanaged.jar");
+    assert!(mods_dir.join("unmanaged.jar").exists());
+
+    let clean = story_cli(data_dir.path(), &["clean", "--instances", instances_arg]).await;
+    assert_eq!(clean.status, 0, "{}", clean.stderr);
+    assert!(!mods_dir.join("unmanaged.jar").exists());
+    assert!(mods_dir.join("sodium-0.6.0.jar").exists());
+
+    // A modified managed file fails verify and repair restores it
+    std::fs::write(mods_dir.join("sodium-0.6.0.jar"), b"corrupted").unwrap();
+    let verify = story_cli(data_dir.path(), &["verify", "--instances", instances_arg]).await;
+    assert_eq!(verify.status, 1);
+    assert_eq!(verify.json["ok"], false);
+    assert_eq!(
+        verify.json["result"]["modified"][0]["path"],
+        "mods/sodium-0.6.0.jar"
+    );
+
+    let repair = story_cli(data_dir.path(), &["repair", "--instances", instances_arg]).await;
+    assert_eq!(repair.status, 0, "{}", repair.stderr);
+    assert_eq!(
+        std::fs::read(mods_dir.join("sodium-0.6.0.jar")).unwrap(),
+        SODIUM
+    );
+}
+
+#[tokio::test]
+async fn test_cli_reports_failures_as_json() {
+    let data_dir = TempDir::new().unwrap();
+    let instances = TempDir::new().unwrap();
+    let server = published_pack(data_dir.path()).await;
+
+    let install = story_cli(
+        data_dir.path(),
+        &[
+            "install",
+            &server.url("/missing-manifest.json"),
+            "--instances",
+            instances.path().to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(install.status, 1);
+    assert_eq!(install.json["ok"], false);
+    assert!(install.json["error"].is_string());
+
+    let verify = story_cli(
+        data_dir.path(),
+        &["verify", "--instances", instances.path().to_str().unwrap()],
+    )
+    .await;
+    assert_eq!(verify.status, 1);
+    assert!(verify.json["error"]
+        .as_str()
+        .unwrap()
+        .contains("Instance not found"));
+}
+
+#[tokio::test]
+async fn test_cli_usage_errors() {
+    let data_dir = TempDir::new().unwrap();
+    for args in [
+        &["frobnicate", "--instances", "x"][..],
+        &["install", "--instances", "x"],
+        &["verify"],
+        &["verify", "--instances", "x", "--bogus"],
+        &["clean", "extra-arg", "--instances", "x"],
+        &["update", "story.json", "--instances", "x", "--offline"],
+    ] {
+        let output = story_cli(data_dir.path(), args).await;
+        assert_eq!(output.status, 2, "{:?}", args);
+        assert!(output.stderr.contains("Usage: story-cli"));
+    }
+}
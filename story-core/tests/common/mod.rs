@@ -0,0 +1,423 @@
+// In-process stand-in for the Modrinth API, its CDN and a manifest host, so installs can be
+// tested end to end without network. Unknown paths answer 404. Also the fixtures that
+// several test files share.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use story_core::*;
+use tempfile::TempDir;
+
+pub const SODIUM: &[u8] = b"sodium 0.6.0 jar";
+
+#[derive(Clone)]
+struct Route {
+    status: u16,
+    body: Vec<u8>,
+    delay: Duration,
+}
+
+#[derive(Default)]
+struct ServerState {
+    routes: HashMap<String, Route>,
+    hits: HashMap<String, usize>,
+}
+
+pub struct MockServer {
+    base: String,
+    state: Arc<Mutex<ServerState>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let server_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(respond(socket, server_state.clone()));
+            }
+        });
+        MockServer { base, state, task }
+    }
+
+    // Absolute URL of a path on this server
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    // Routes are keyed by path and query; absolute URLs on this server work too
+    fn key(&self, url: &str) -> String {
+        url.strip_prefix(&self.base).unwrap_or(url).to_string()
+    }
+
+    pub fn respond(&self, url: &str, status: u16, body: impl Into<Vec<u8>>) {
+        let route = Route {
+            status,
+            body: body.into(),
+            delay: Duration::ZERO,
+        };
+        let key = self.key(url);
+        self.state.lock().unwrap().routes.insert(key, route);
+    }
+
+    pub fn file(&self, url: &str, body: impl Into<Vec<u8>>) {
+        self.respond(url, 200, body);
+    }
+
+    pub fn json(&self, url: &str, body: &serde_json::Value) {
+        self.respond(url, 200, body.to_string());
+    }
+
+    pub fn remove(&self, url: &str) {
+        let key = self.key(url);
+        self.state.lock().unwrap().routes.remove(&key);
+    }
+
+    // Hold the response to an already registered route back for `delay`
+    pub fn delay(&self, url: &str, delay: Duration) {
+        let key = self.key(url);
+        if let Some(route) = self.state.lock().unwrap().routes.get_mut(&key) {
+            route.delay = delay;
+        }
+    }
+
+    pub fn hits(&self, url: &str) -> usize {
+        let key = self.key(url);
+        let state = self.state.lock().unwrap();
+        state.hits.get(&key).copied().unwrap_or(0)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn respond(mut socket: TcpStream, state: Arc<Mutex<ServerState>>) {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        match socket.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+
+    // Drain a request body so the client isn't cut off mid-write
+    let header = |wanted: &str| {
+        head.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.trim().to_string())
+    };
+    let content_length = header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body_read = request.len() - head_end;
+    while body_read < content_length {
+        match socket.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => body_read += read,
+        }
+    }
+
+    let route = {
+        let mut state = state.lock().unwrap();
+        *state.hits.entry(target.clone()).or_default() += 1;
+        state.routes.get(&target).cloned()
+    };
+    let route = route.unwrap_or(Route {
+        status: 404,
+        body: b"not found".to_vec(),
+        delay: Duration::ZERO,
+    });
+    tokio::time::sleep(route.delay).await;
+
+    // Successful responses carry a content ETag and honor If-None-Match, like a CDN
+    let etag = format!("\"{}\"", story_core::sha256_hex(&route.body));
+    let not_modified = route.status == 200 && header("if-none-match").as_ref() == Some(&etag);
+    let (status, body) = if not_modified {
+        (304, &[][..])
+    } else {
+        (route.status, &route.body[..])
+    };
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nETag: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len(),
+        etag
+    )
+    .into_bytes();
+    if method != "HEAD" {
+        response.extend_from_slice(body);
+    }
+    let _ = socket.write_all(&response).await;
+    let _ = socket.shutdown().await;
+}
+
+pub fn zip_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in entries {
+        zip.start_file(*name, zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+// A Modrinth version with one primary file served from `url`
+pub fn version_json(
+    project_id: &str,
+    version_id: &str,
+    version_number: &str,
+    filename: &str,
+    url: &str,
+    content: &[u8],
+    dependencies: &[&str],
+) -> serde_json::Value {
+    serde_json::json!({
+        "game_versions": ["1.21.1"],
+        "loaders": ["fabric"],
+        "id": version_id,
+        "project_id": project_id,
+        "name": format!("{} {}", project_id, version_number),
+        "version_number": version_number,
+        "changelog": null,
+        "version_type": "release",
+        "files": [{
+            "hashes": { "sha512": sha512_hex(content) },
+            "url": url,
+            "filename": filename,
+            "primary": true,
+            "size": content.len()
+        }],
+        "dependencies": dependencies.iter().map(|project_id| serde_json::json!({
+            "version_id": null,
+            "project_id": project_id,
+            "file_name": null,
+            "dependency_type": "required"
+        })).collect::<Vec<_>>()
+    })
+}
+
+// A Modrinth version with one primary file on the Modrinth CDN, known only by its sha512
+pub fn cdn_version_json(
+    project_id: &str,
+    version_id: &str,
+    filename: &str,
+    sha512: &str,
+    dependencies: &[&str],
+) -> serde_json::Value {
+    serde_json::json!({
+        "game_versions": ["1.21.1"],
+        "loaders": ["fabric"],
+        "id": version_id,
+        "project_id": project_id,
+        "name": version_id,
+        "version_number": version_id,
+        "changelog": null,
+        "files": [{
+            "hashes": { "sha512": sha512 },
+            "url": format!("https://cdn.modrinth.com/data/{}/{}", project_id, filename),
+            "filename": filename,
+            "primary": true,
+            "size": 1024
+        }],
+        "dependencies": dependencies.iter().map(|project_id| serde_json::json!({
+            "version_id": null,
+            "project_id": project_id,
+            "file_name": null,
+            "dependency_type": "required"
+        })).collect::<Vec<_>>()
+    })
+}
+
+pub fn with_dependency(
+    mut version: serde_json::Value,
+    dependency_type: &str,
+    project_id: Option<&str>,
+    version_id: Option<&str>,
+) -> serde_json::Value {
+    version["dependencies"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({
+            "version_id": version_id,
+            "project_id": project_id,
+            "file_name": null,
+            "dependency_type": dependency_type
+        }));
+    version
+}
+
+// A Sodium release for Minecraft 1.21.1 on Fabric, without files or dependencies
+pub fn modrinth_version(id: &str) -> ModrinthVersionResponse {
+    ModrinthVersionResponse {
+        game_versions: vec!["1.21.1".to_string()],
+        loaders: vec!["fabric".to_string()],
+        id: id.to_string(),
+        project_id: "sodium".to_string(),
+        name: id.to_string(),
+        version_number: id.to_string(),
+        changelog: None,
+        files: vec![],
+        dependencies: vec![],
+        version_type: Some("release".to_string()),
+        featured: false,
+        date_published: None,
+    }
+}
+
+// An extra mod that asks for the newest version of a Modrinth project
+pub fn extra_mod(name: &str) -> ExtraMod {
+    ExtraMod {
+        name: name.to_string(),
+        version: None,
+        version_id: None,
+        sha512: None,
+        filename: None,
+        release_channel: None,
+        prefer_featured: None,
+        env: None,
+        url: None,
+        path: None,
+    }
+}
+
+#[derive(Default)]
+pub struct RecordedProgress(pub Mutex<Vec<serde_json::Value>>);
+
+impl ProgressSink for RecordedProgress {
+    fn progress(&self, _event: &str, payload: serde_json::Value) {
+        self.0.lock().unwrap().push(payload);
+    }
+}
+
+// Settings for a launcher whose Modrinth API is the mock server. Test manifests are
+// unsigned, so those are allowed.
+pub fn test_settings(server: &MockServer) -> LauncherSettings {
+    LauncherSettings {
+        allow_unsigned_manifests: true,
+        modrinth: ModrinthSettings {
+            api_base: server.url("/v2"),
+            timeout_secs: 1,
+            ..ModrinthSettings::default()
+        },
+        ..LauncherSettings::default()
+    }
+}
+
+// A launcher with test_settings and its own data and instances folders, which installs
+// from the manifest at /story-manifest.json
+pub struct Launcher {
+    pub server: MockServer,
+    pub ctx: LauncherContext,
+    pub progress: Arc<RecordedProgress>,
+    pub data_dir: TempDir,
+    pub instances: TempDir,
+}
+
+impl Launcher {
+    pub fn new(server: MockServer) -> Self {
+        let data_dir = TempDir::new().unwrap();
+        save_launcher_settings(data_dir.path(), &test_settings(&server)).unwrap();
+        let progress = Arc::new(RecordedProgress::default());
+        let ctx = LauncherContext::new(data_dir.path().to_path_buf(), progress.clone()).unwrap();
+        Launcher {
+            server,
+            ctx,
+            progress,
+            data_dir,
+            instances: TempDir::new().unwrap(),
+        }
+    }
+
+    pub fn manifest_url(&self) -> String {
+        self.server.url("/story-manifest.json")
+    }
+
+    pub fn instance_base(&self) -> String {
+        self.instances.path().to_string_lossy().to_string()
+    }
+
+    pub fn story_path(&self) -> PathBuf {
+        self.instances.path().join("Story")
+    }
+
+    pub fn minecraft_dir(&self) -> PathBuf {
+        self.story_path().join(".minecraft")
+    }
+
+    pub async fn install(&self) -> Result<String, String> {
+        install_from_manifest(&self.ctx, self.manifest_url(), self.instance_base(), false).await
+    }
+}
+
+// Serve `mrpack` as version 1.0.0 of story-pack, looked up by version number to resolve
+// and by id once locked
+pub fn publish_modpack(server: &MockServer, mrpack: Vec<u8>) {
+    let pack = version_json(
+        "story-pack",
+        "pack-1",
+        "1.0.0",
+        "story-pack-1.0.0.mrpack",
+        &server.url("/cdn/story-pack-1.0.0.mrpack"),
+        &mrpack,
+        &[],
+    );
+    server.json("/v2/project/story-pack/version/1.0.0", &pack);
+    server.json("/v2/version/pack-1", &pack);
+    server.file("/cdn/story-pack-1.0.0.mrpack", mrpack);
+}
+
+// story-pack with Sodium from the mock CDN in its index, plus `entries` such as overrides
+pub fn publish_sodium_modpack(server: &MockServer, entries: &[(&str, &[u8])]) {
+    let index = serde_json::json!({
+        "files": [{
+            "path": "mods/sodium-0.6.0.jar",
+            "hashes": { "sha512": sha512_hex(SODIUM) },
+            "downloads": [server.url("/cdn/sodium-0.6.0.jar")]
+        }]
+    });
+    let index = index.to_string();
+    let mut mrpack_entries = vec![("modrinth.index.json", index.as_bytes())];
+    mrpack_entries.extend_from_slice(entries);
+    server.file("/cdn/sodium-0.6.0.jar", SODIUM);
+    publish_modpack(server, zip_bytes(&mrpack_entries));
+}
+
+// The manifest for story-pack 1.0.0 on Minecraft 1.21.1 with Fabric, with the top-level
+// `fields` (extra_mods, overrides, ...) added
+pub fn publish_manifest(server: &MockServer, fields: serde_json::Value) {
+    let mut manifest = serde_json::json!({
+        "schema_version": 1,
+        "instance": {
+            "name": "story-pack",
+            "version": "1.0.0",
+            "minecraft_version": "1.21.1",
+            "loader": "fabric"
+        }
+    });
+    if let serde_json::Value::Object(fields) = fields {
+        manifest.as_object_mut().unwrap().extend(fields);
+    }
+    server.json("/story-manifest.json", &manifest);
+}
@@ -0,0 +1,51 @@
+use reqwest::header::{HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use story_core::*;
+
+fn file_info(last_modified: &str, etag: Option<&str>) -> FileInfo {
+    FileInfo {
+        hash: "abc123".to_string(),
+        last_modified: last_modified.to_string(),
+        etag: etag.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_conditional_headers_use_stored_validators() {
+    let info = file_info("Wed, 21 Oct 2015 07:28:00 GMT", Some("\"v1\""));
+    let headers = conditional_headers(Some(&info));
+
+    assert_eq!(headers.get(IF_NONE_MATCH).unwrap(), "\"v1\"");
+    assert_eq!(
+        headers.get(IF_MODIFIED_SINCE).unwrap(),
+        "Wed, 21 Oct 2015 07:28:00 GMT"
+    );
+}
+
+#[test]
+fn test_conditional_headers_empty_without_validators() {
+    assert!(conditional_headers(None).is_empty());
+    assert!(conditional_headers(Some(&file_info("", None))).is_empty());
+
+    let etag_only = conditional_headers(Some(&file_info("", Some("W/\"abc\""))));
+    assert_eq!(etag_only.len(), 1);
+    assert!(etag_only.contains_key(IF_NONE_MATCH));
+}
+
+#[test]
+fn test_missing_validators_count_as_changed() {
+    // Servers that send neither header used to compare "" == "" and never update
+    let remote = RemoteValidators::from_headers(&HeaderMap::new());
+    assert!(remote.changed_since(Some(&file_info("", None))).is_some());
+
+    let remote = RemoteValidators {
+        last_modified: "Thu, 22 Oct 2015 07:28:00 GMT".to_string(),
+        etag: None,
+        size: None,
+    };
+    assert_eq!(
+        remote
+            .changed_since(Some(&file_info("Wed, 21 Oct 2015 07:28:00 GMT", None)))
+            .as_deref(),
+        Some("Last-Modified changed")
+    );
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use common::*;
+use sha1::{Digest, Sha1};
+use std::path::Path;
+use std::sync::Arc;
+use story_core::*;
+use tempfile::TempDir;
+
+const SODIUM: &[u8] = b"sodium 0.6.0 jar";
+const FAITHFUL: &[u8] = b"faithful 32x resource pack";
+
+fn sha1_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(bytes))
+}
+
+fn curseforge_file(
+    server: &MockServer,
+    id: u64,
+    mod_id: u64,
+    file_name: &str,
+    content: &[u8],
+) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "modId": mod_id,
+        "fileName": file_name,
+        "downloadUrl": server.url(&format!("/cdn/{}", file_name)),
+        "hashes": [{ "value": sha1_hex(content), "algo": 1 }, { "value": "0f00", "algo": 2 }],
+        "fileLength": content.len()
+    })
+}
+
+// A stand-in CurseForge API with a mod, a resource pack and a mod whose author turned off
+// third-party downloads, and a pack zip that uses all three plus an optional file
+async fn curseforge_pack() -> (MockServer, LauncherContext, TempDir, Vec<u8>) {
+    let server = MockServer::start().await;
+    let data_dir = TempDir::new().unwrap();
+    save_launcher_settings(
+        data_dir.path(),
+        &LauncherSettings {
+            curseforge: CurseForgeSettings {
+                api_base: server.url("/cf"),
+                api_key: Some("test-key".to_string()),
+                timeout_secs: 5,
+            },
+            ..LauncherSettings::default()
+        },
+    )
+    .unwrap();
+    let ctx = LauncherContext::new(data_dir.path().to_path_buf(), Arc::new(NoProgress)).unwrap();
+
+    let mut restricted = curseforge_file(&server, 300, 30, "optifine.jar", b"optifine");
+    restricted["downloadUrl"] = serde_json::Value::Null;
+    server.json(
+        "/cf/v1/mods/files",
+        &serde_json::json!({ "data": [
+            curseforge_file(&server, 100, 10, "sodium-0.6.0.jar", SODIUM),
+            curseforge_file(&server, 200, 20, "faithful-32x.zip", FAITHFUL),
+            restricted
+        ]}),
+    );
+    server.json(
+        "/cf/v1/mods",
+        &serde_json::json!({ "data": [
+            { "id": 10, "name": "Sodium", "classId": 6 },
+            { "id": 20, "name": "Faithful", "classId": 12 },
+            { "id": 30, "name": "OptiFine", "classId": 6 }
+        ]}),
+    );
+    server.file("/cdn/sodium-0.6.0.jar", SODIUM);
+    server.file("/cdn/faithful-32x.zip", FAITHFUL);
+
+    let manifest = serde_json::json!({
+        "minecraft": {
+            "version": "1.21.1",
+            "modLoaders": [{ "id": "fabric-0.16.10", "primary": true }]
+        },
+        "manifestType": "minecraftModpack",
+        "manifestVersion": 1,
+        "name": "story-pack",
+        "version": "2.0.0",
+        "files": [
+            { "projectID": 10, "fileID": 100, "required": true },
+            { "projectID": 20, "fileID": 200, "required": true },
+            { "projectID": 30, "fileID": 300, "required": true },
+            { "projectID": 40, "fileID": 400, "required": false }
+        ],
+        "overrides": "overrides"
+    });
+    let pack = zip_bytes(&[
+        ("manifest.json", manifest.to_string().as_bytes()),
+        ("overrides/config/story.txt", b"pack config"),
+    ]);
+    (server, ctx, data_dir, pack)
+}
+
+fn assert_installed(instances: &Path) {
+    let story_path = instances.join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+    let read = |path: &str| std::fs::read(minecraft_dir.join(path)).unwrap();
+    assert_eq!(read("mods/sodium-0.6.0.jar"), SODIUM);
+    assert_eq!(read("resourcepacks/faithful-32x.zip"), FAITHFUL);
+    assert_eq!(read("config/story.txt"), b"pack config");
+    assert!(!minecraft_dir.join("mods/optifine.jar").exists());
+
+    // Recorded as a modrinth.index.json so verify and repair know the files
+    let index: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(story_path.join("mrpack/modrinth.index.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(index["versionId"], "2.0.0");
+    assert_eq!(index["dependencies"]["fabric-loader"], "0.16.10");
+    assert_eq!(index["files"].as_array().unwrap().len(), 2);
+    assert_eq!(index["files"][0]["path"], "mods/sodium-0.6.0.jar");
+    assert_eq!(index["files"][0]["hashes"]["sha512"], sha512_hex(SODIUM));
+    assert_eq!(index["files"][0]["hashes"]["sha1"], sha1_hex(SODIUM));
+
+    let instance_cfg = std::fs::read_to_string(story_path.join("instance.cfg")).unwrap();
+    assert!(instance_cfg.contains("ManagedPack=false"));
+    let mmc_pack: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(story_path.join("mmc-pack.json")).unwrap())
+            .unwrap();
+    assert_eq!(mmc_pack["components"][0]["version"], "1.21.1");
+    assert_eq!(mmc_pack["components"][1]["version"], "0.16.10");
+}
+
+#[tokio::test]
+async fn test_import_curseforge_pack() {
+    let (_server, ctx, _data_dir, pack) = curseforge_pack().await;
+    let builds = TempDir::new().unwrap();
+    let pack_path = builds.path().join("story-pack-2.0.0.zip");
+    std::fs::write(&pack_path, &pack).unwrap();
+    let instances = TempDir::new().unwrap();
+
+    let result = install_curseforge_modpack(
+        &ctx,
+        pack_path.to_string_lossy().to_string(),
+        instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(result.contains("story-pack v2.0.0"), "{}", result);
+    // Files without a download URL are reported, not silently dropped
+    assert!(result.contains("mods/optifine.jar"), "{}", result);
+    assert!(result.contains("download it manually"), "{}", result);
+    assert_installed(instances.path());
+}
+
+#[tokio::test]
+async fn test_import_curseforge_pack_from_url_and_offline() {
+    let (server, ctx, _data_dir, pack) = curseforge_pack().await;
+    server.file("/builds/story-pack.zip", pack.clone());
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+
+    install_curseforge_modpack(
+        &ctx,
+        server.url("/builds/story-pack.zip"),
+        instance_base.clone(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert_installed(instances.path());
+
+    // The same pack on disk installs again from the response cache and blob store;
+    // only the file that was never downloadable is still missing
+    std::fs::remove_dir_all(instances.path().join("Story")).unwrap();
+    server.remove("/cf/v1/mods/files");
+    server.remove("/cdn/sodium-0.6.0.jar");
+    let builds = TempDir::new().unwrap();
+    let pack_path = builds.path().join("story-pack.zip");
+    std::fs::write(&pack_path, &pack).unwrap();
+    let error = install_curseforge_modpack(
+        &ctx,
+        pack_path.to_string_lossy().to_string(),
+        instance_base,
+        true,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("mods/optifine.jar"), "{}", error);
+    assert!(!error.contains("sodium"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_import_curseforge_rejects_bad_files() {
+    let (server, ctx, _data_dir, _pack) = curseforge_pack().await;
+    let builds = TempDir::new().unwrap();
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+
+    // A CDN serving different bytes than the API's sha1 never reaches the instance
+    server.file("/cdn/sodium-0.6.0.jar", b"tampered jar".to_vec());
+    let manifest = serde_json::json!({
+        "minecraft": { "version": "1.21.1", "modLoaders": [] },
+        "manifestType": "minecraftModpack",
+        "name": "story-pack",
+        "version": "2.0.1",
+        "files": [{ "projectID": 10, "fileID": 100, "required": true }]
+    });
+    let pack_path = builds.path().join("tampered.zip");
+    std::fs::write(
+        &pack_path,
+        zip_bytes(&[("manifest.json", manifest.to_string().as_bytes())]),
+    )
+    .unwrap();
+    let result = install_curseforge_modpack(
+        &ctx,
+        pack_path.to_string_lossy().to_string(),
+        instance_base.clone(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(result.contains("sha1 mismatch"), "{}", result);
+    assert!(!instances
+        .path()
+        .join("Story/.minecraft/mods/sodium-0.6.0.jar")
+        .exists());
+
+    // Only modpack manifests are accepted
+    let manifest = serde_json::json!({
+        "minecraft": { "version": "1.21.1" },
+        "manifestType": "minecraftWorld"
+    });
+    let world_path = builds.path().join("world.zip");
+    std::fs::write(
+        &world_path,
+        zip_bytes(&[("manifest.json", manifest.to_string().as_bytes())]),
+    )
+    .unwrap();
+    let error = install_curseforge_modpack(
+        &ctx,
+        world_path.to_string_lossy().to_string(),
+        instance_base,
+        false,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("minecraftWorld"), "{}", error);
+}
+
+#[test]
+fn test_curseforge_dependencies_from_mod_loaders() {
+    let manifest: CurseForgeManifest = serde_json::from_value(serde_json::json!({
+        "minecraft": {
+            "version": "1.20.1",
+            "modLoaders": [
+                { "id": "fabric-0.15.0", "primary": false },
+                { "id": "forge-47.2.0", "primary": true }
+            ]
+        },
+        "manifestType": "minecraftModpack"
+    }))
+    .unwrap();
+    let dependencies = curseforge_dependencies(&manifest);
+    assert_eq!(dependencies.len(), 2);
+    assert_eq!(dependencies["minecraft"], "1.20.1");
+    assert_eq!(dependencies["forge"], "47.2.0");
+    assert_eq!(manifest.overrides, "overrides");
+}
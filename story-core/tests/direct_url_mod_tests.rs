@@ -0,0 +1,248 @@
+mod common;
+
+use common::*;
+use story_core::*;
+
+const NPC_PARSER: &[u8] = b"npc parser 1.0 jar";
+const NPC_PARSER_UPDATE: &[u8] = b"npc parser 1.1 jar";
+const SHADERS: &[u8] = b"story shaders zip";
+
+fn npc_parser(launcher: &Launcher, filename: &str, content: &[u8]) -> serde_json::Value {
+    serde_json::json!({
+        "name": "npc-parser",
+        "url": launcher.server.url(&format!("/files/{}", filename)),
+        "sha512": sha512_hex(content)
+    })
+}
+
+fn shaders(launcher: &Launcher) -> serde_json::Value {
+    serde_json::json!({
+        "name": "story-shaders",
+        "url": launcher.server.url("/files/shaders.zip"),
+        "sha512": sha512_hex(SHADERS),
+        "path": "shaderpacks/story-shaders.zip",
+        "env": { "client": "required", "server": "unsupported" }
+    })
+}
+
+fn publish(launcher: &Launcher, extra_mods: serde_json::Value) {
+    publish_manifest(
+        &launcher.server,
+        serde_json::json!({ "extra_mods": extra_mods }),
+    );
+}
+
+// A Modrinth modpack plus two files hosted outside Modrinth: a mod jar and a shader pack
+async fn launcher() -> Launcher {
+    let server = MockServer::start().await;
+    publish_sodium_modpack(&server, &[]);
+    server.file("/files/npc-parser-1.0.jar", NPC_PARSER);
+    server.file("/files/npc-parser-1.1.jar", NPC_PARSER_UPDATE);
+    server.file("/files/shaders.zip", SHADERS);
+
+    let launcher = Launcher::new(server);
+    publish(
+        &launcher,
+        serde_json::json!([
+            npc_parser(&launcher, "npc-parser-1.0.jar", NPC_PARSER),
+            shaders(&launcher)
+        ]),
+    );
+    launcher
+}
+
+#[tokio::test]
+async fn test_install_direct_url_mods() {
+    let launcher = launcher().await;
+
+    let result = launcher.install().await.unwrap();
+    assert!(result.starts_with("✅"), "{}", result);
+    assert!(!result.contains("could not be downloaded"), "{}", result);
+
+    let minecraft_dir = launcher.minecraft_dir();
+    let read = |path: &str| std::fs::read(minecraft_dir.join(path)).unwrap();
+    assert_eq!(read("mods/npc-parser-1.0.jar"), NPC_PARSER);
+    assert_eq!(read("shaderpacks/story-shaders.zip"), SHADERS);
+
+    // Locked as given, without asking Modrinth about them
+    let lock = load_lockfile(&launcher.story_path().join(LOCKFILE_NAME)).unwrap();
+    let locked = lock.mods.iter().find(|m| m.name == "npc-parser").unwrap();
+    assert_eq!(
+        locked.file.url,
+        launcher.server.url("/files/npc-parser-1.0.jar")
+    );
+    assert_eq!(locked.file.sha512, sha512_hex(NPC_PARSER));
+    assert_eq!(locked.file.filename, "npc-parser-1.0.jar");
+    assert_eq!(locked.file.size, NPC_PARSER.len() as u64);
+
+    // Tracked like any other extra mod, so verify, repair and clean cover them
+    let managed = load_managed_files(&launcher.story_path());
+    let shader_pack = managed
+        .files
+        .iter()
+        .find(|f| f.path == "shaderpacks/story-shaders.zip")
+        .unwrap();
+    assert!(matches!(
+        &shader_pack.source,
+        ManagedSource::ExtraMod { name, .. } if name == "story-shaders"
+    ));
+    let report = verify_instance_files(&launcher.story_path()).unwrap();
+    assert!(report.missing.is_empty() && report.modified.is_empty());
+    assert!(report.extra.is_empty(), "{:?}", report.extra);
+
+    // The second install finds both in place and downloads nothing
+    let result = launcher.install().await.unwrap();
+    assert!(result.contains("2 skipped"), "{}", result);
+    assert_eq!(launcher.server.hits("/files/npc-parser-1.0.jar"), 1);
+
+    // A damaged jar is repaired from its URL
+    std::fs::write(minecraft_dir.join("mods/npc-parser-1.0.jar"), b"broken").unwrap();
+    let repair = repair_instance_files(&launcher.ctx, &launcher.instance_base(), false, false)
+        .await
+        .unwrap();
+    assert_eq!(repair.repaired, vec!["mods/npc-parser-1.0.jar"]);
+    assert_eq!(read("mods/npc-parser-1.0.jar"), NPC_PARSER);
+
+    // Unmanaged files count as extra in every folder the launcher installs into
+    std::fs::write(minecraft_dir.join("shaderpacks/other.zip"), b"other").unwrap();
+    std::fs::write(minecraft_dir.join("options.txt"), b"fov:70").unwrap();
+    let report = verify_instance_files(&launcher.story_path()).unwrap();
+    assert_eq!(report.extra, vec!["shaderpacks/other.zip"]);
+}
+
+#[tokio::test]
+async fn test_direct_url_mod_with_wrong_hash_is_rejected() {
+    let launcher = launcher().await;
+    launcher
+        .server
+        .file("/files/npc-parser-1.0.jar", b"tampered jar".to_vec());
+
+    let result = launcher.install().await.unwrap();
+    assert!(result.contains("could not be downloaded"), "{}", result);
+    assert!(result.contains("npc-parser-1.0.jar"), "{}", result);
+    assert!(!launcher
+        .minecraft_dir()
+        .join("mods/npc-parser-1.0.jar")
+        .exists());
+    assert!(launcher
+        .minecraft_dir()
+        .join("shaderpacks/story-shaders.zip")
+        .is_file());
+}
+
+#[tokio::test]
+async fn test_changed_direct_url_mod_is_an_update() {
+    let launcher = launcher().await;
+    launcher.install().await.unwrap();
+
+    let diff = check_instance_updates(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(!diff.has_updates, "{}", diff.summary);
+
+    publish(
+        &launcher,
+        serde_json::json!([
+            npc_parser(&launcher, "npc-parser-1.1.jar", NPC_PARSER_UPDATE),
+            shaders(&launcher)
+        ]),
+    );
+    let diff = check_instance_updates(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(diff.has_updates);
+    assert_eq!(diff.extra_mods.len(), 1);
+    assert_eq!(diff.extra_mods[0].name, "npc-parser");
+    assert_eq!(diff.extra_mods[0].reason.as_deref(), Some("pin changed"));
+
+    launcher.install().await.unwrap();
+    let mods_dir = launcher.minecraft_dir().join("mods");
+    assert_eq!(
+        std::fs::read(mods_dir.join("npc-parser-1.1.jar")).unwrap(),
+        NPC_PARSER_UPDATE
+    );
+
+    // The replaced jar is no longer managed, so clean removes it
+    let report = clean_instance(&launcher.instance_base(), false).unwrap();
+    assert_eq!(report.removed, vec!["mods/npc-parser-1.0.jar"]);
+    assert!(mods_dir.join("npc-parser-1.1.jar").is_file());
+}
+
+#[test]
+fn test_manifest_validator_checks_direct_url_mods() {
+    let manifest = |extra_mod: serde_json::Value| {
+        serde_json::json!({
+            "instance": { "name": "story-pack", "version": "1.0.0" },
+            "extra_mods": [extra_mod]
+        })
+        .to_string()
+    };
+    let sha512 = sha512_hex(NPC_PARSER);
+
+    let valid = validate_manifest_text(&manifest(serde_json::json!({
+        "name": "npc-parser",
+        "url": "https://example.com/npc-parser.jar",
+        "sha512": sha512,
+        "path": "mods/npc-parser.jar"
+    })));
+    assert!(valid.valid, "{:?}", valid.errors);
+
+    let unpinned = validate_manifest_text(&manifest(serde_json::json!({
+        "name": "npc-parser",
+        "url": "https://example.com/npc-parser.jar"
+    })));
+    assert!(unpinned
+        .errors
+        .iter()
+        .any(|e| e.path == "$.extra_mods[0].sha512"));
+
+    let escapes = validate_manifest_text(&manifest(serde_json::json!({
+        "name": "npc-parser",
+        "url": "https://example.com/npc-parser.jar",
+        "sha512": sha512,
+        "path": "../../npc-parser.jar"
+    })));
+    assert!(escapes
+        .errors
+        .iter()
+        .any(|e| e.path == "$.extra_mods[0].path"));
+
+    let path_without_url = validate_manifest_text(&manifest(serde_json::json!({
+        "name": "iris",
+        "path": "mods/iris.jar"
+    })));
+    assert!(path_without_url
+        .errors
+        .iter()
+        .any(|e| e.path == "$.extra_mods[0].path"));
+}
+
+#[test]
+fn test_direct_url_mod_target_path() {
+    let extra_mod: ExtraMod = serde_json::from_value(serde_json::json!({
+        "name": "npc-parser",
+        "url": "https://example.com/files/npc-parser-1.0.jar?download=1",
+        "sha512": sha512_hex(NPC_PARSER)
+    }))
+    .unwrap();
+    assert_eq!(
+        extra_mod.target_path().as_deref(),
+        Some("mods/npc-parser-1.0.jar")
+    );
+
+    let file = LockedFile::direct(&extra_mod, NPC_PARSER.len() as u64).unwrap();
+    assert_eq!(file.project_id, "npc-parser");
+    assert_eq!(file.filename, "npc-parser-1.0.jar");
+
+    // Modrinth mods have no target path of their own
+    let modrinth: ExtraMod = serde_json::from_value(serde_json::json!({ "name": "iris" })).unwrap();
+    assert_eq!(modrinth.target_path(), None);
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use common::*;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use story_core::*;
+use tempfile::TempDir;
+
+const SODIUM: &[u8] = b"sodium 0.6.0 jar";
+const IN_HOUSE: &[u8] = b"npcmessageparser jar";
+
+const MMC_PACK: &str = r#"{
+    "components": [
+        { "uid": "org.lwjgl3", "version": "3.3.3" },
+        { "uid": "net.minecraft", "version": "1.21.1" },
+        { "uid": "net.fabricmc.fabric-loader", "version": "0.16.14" }
+    ],
+    "formatVersion": 1
+}"#;
+
+fn write_file(base: &Path, relative: &str, content: &[u8]) {
+    let path = base.join(relative);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+// An installed Story instance with one Modrinth mod, an in-house jar, a config and a world
+fn installed_instance(instances: &Path) {
+    let story_path = instances.join("Story");
+    write_file(&story_path, "mmc-pack.json", MMC_PACK.as_bytes());
+    write_file(
+        &story_path,
+        ".current_version.json",
+        br#"{ "instance_name": "story-pack", "instance_version": "1.0.0" }"#,
+    );
+    let minecraft_dir = story_path.join(".minecraft");
+    write_file(&minecraft_dir, "mods/sodium-0.6.0.jar", SODIUM);
+    write_file(&minecraft_dir, "mods/npcmessageparser.jar", IN_HOUSE);
+    write_file(&minecraft_dir, "config/story.json", b"{}");
+    write_file(&minecraft_dir, "saves/world/level.dat", b"world");
+    write_file(&minecraft_dir, "logs/latest.log", b"log");
+}
+
+fn read_entry(mrpack: &Path, name: &str) -> Option<Vec<u8>> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(mrpack).unwrap()).unwrap();
+    let mut entry = zip.by_name(name).ok()?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).unwrap();
+    Some(content)
+}
+
+#[tokio::test]
+async fn test_export_mrpack_splits_modrinth_files_and_overrides() {
+    let server = MockServer::start().await;
+    let data_dir = TempDir::new().unwrap();
+    save_launcher_settings(
+        data_dir.path(),
+        &LauncherSettings {
+            modrinth: ModrinthSettings {
+                api_base: server.url("/v2"),
+                timeout_secs: 5,
+                ..ModrinthSettings::default()
+            },
+            ..LauncherSettings::default()
+        },
+    )
+    .unwrap();
+    let ctx = LauncherContext::new(data_dir.path().to_path_buf(), Arc::new(NoProgress)).unwrap();
+
+    // Modrinth knows sodium by its hash, not the in-house jar
+    let mut sodium = version_json(
+        "AANobbMI",
+        "sodium-1",
+        "0.6.0",
+        "sodium-0.6.0.jar",
+        "https://cdn.modrinth.com/data/AANobbMI/versions/sodium-1/sodium-0.6.0.jar",
+        SODIUM,
+        &[],
+    );
+    sodium["files"][0]["hashes"]["sha1"] = "da39a3ee".into();
+    server.json(
+        "/v2/version_files",
+        &serde_json::json!({ sha512_hex(SODIUM): sodium }),
+    );
+
+    let instances = TempDir::new().unwrap();
+    installed_instance(instances.path());
+    let output = TempDir::new().unwrap();
+    let mrpack = output.path().join("story-pack-1.1.0.mrpack");
+
+    let report = export_mrpack(
+        &ctx,
+        &instances.path().to_string_lossy(),
+        &mrpack,
+        Some("1.1.0".to_string()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.name, "story-pack");
+    assert_eq!(report.version_id, "1.1.0");
+    assert_eq!(report.modrinth_files, 1);
+    assert_eq!(report.override_files, 2);
+
+    let index: serde_json::Value =
+        serde_json::from_slice(&read_entry(&mrpack, "modrinth.index.json").unwrap()).unwrap();
+    assert_eq!(index["formatVersion"], 1);
+    assert_eq!(index["game"], "minecraft");
+    assert_eq!(index["versionId"], "1.1.0");
+    assert_eq!(index["dependencies"]["minecraft"], "1.21.1");
+    assert_eq!(index["dependencies"]["fabric-loader"], "0.16.14");
+    assert_eq!(index["files"][0]["path"], "mods/sodium-0.6.0.jar");
+    assert_eq!(index["files"][0]["hashes"]["sha512"], sha512_hex(SODIUM));
+    assert_eq!(index["files"][0]["hashes"]["sha1"], "da39a3ee");
+    assert_eq!(index["files"][0]["fileSize"], SODIUM.len());
+
+    // Everything Modrinth doesn't host travels in overrides/; worlds and logs stay behind
+    assert_eq!(
+        read_entry(&mrpack, "overrides/mods/npcmessageparser.jar").unwrap(),
+        IN_HOUSE
+    );
+    assert_eq!(
+        read_entry(&mrpack, "overrides/config/story.json").unwrap(),
+        b"{}"
+    );
+    assert!(read_entry(&mrpack, "overrides/mods/sodium-0.6.0.jar").is_none());
+    assert!(read_entry(&mrpack, "overrides/saves/world/level.dat").is_none());
+    assert!(read_entry(&mrpack, "overrides/logs/latest.log").is_none());
+
+    // The launcher can read what it exported
+    let index = read_mrpack_index(&std::fs::read(&mrpack).unwrap()).unwrap();
+    assert_eq!(index.files.len(), 1);
+}
+
+#[test]
+fn test_pack_dependencies_from_mmc_pack() {
+    let temp_dir = TempDir::new().unwrap();
+    write_file(temp_dir.path(), "mmc-pack.json", MMC_PACK.as_bytes());
+    let dependencies = pack_dependencies(temp_dir.path()).unwrap();
+    assert_eq!(dependencies.len(), 2);
+    assert_eq!(dependencies["minecraft"], "1.21.1");
+    assert_eq!(dependencies["fabric-loader"], "0.16.14");
+
+    write_file(
+        temp_dir.path(),
+        "mmc-pack.json",
+        br#"{ "components": [], "formatVersion": 1 }"#,
+    );
+    assert!(pack_dependencies(temp_dir.path()).is_err());
+}
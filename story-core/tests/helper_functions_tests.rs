@@ -1,4 +1,4 @@
-use story_launcher_lib::*;
+use story_core::*;
 use std::path::Path;
 use tempfile::TempDir;
 
@@ -0,0 +1,560 @@
+mod common;
+
+use common::*;
+use std::io::Write;
+use std::time::Duration;
+use story_core::*;
+use tempfile::TempDir;
+
+const IRIS: &[u8] = b"iris 1.8.0 jar";
+const IRIS_UPDATE: &[u8] = b"iris 1.8.1 jar";
+const FABRIC_API: &[u8] = b"fabric api 0.100.0 jar";
+
+// A launcher whose Modrinth API is the mock server, with a published pack, two extra
+// mod versions and an override zip
+async fn launcher() -> Launcher {
+    let server = MockServer::start().await;
+
+    // The modpack: one mod from the CDN and a config file in its overrides
+    publish_sodium_modpack(&server, &[("overrides/config/story.txt", b"pack config")]);
+
+    // Iris requires Fabric API, which is picked from its version list
+    for (version, content) in [("1.8.0", IRIS), ("1.8.1", IRIS_UPDATE)] {
+        let filename = format!("iris-{}.jar", version);
+        server.json(
+            &format!("/v2/project/iris/version/{}", version),
+            &version_json(
+                "iris",
+                &format!("iris-{}", version),
+                version,
+                &filename,
+                &server.url(&format!("/cdn/{}", filename)),
+                content,
+                &["fabric-api"],
+            ),
+        );
+        server.file(&format!("/cdn/{}", filename), content);
+    }
+    server.file("/cdn/fabric-api-0.100.0.jar", FABRIC_API);
+
+    server.file(
+        "/cdn/overrides.zip",
+        zip_bytes(&[("config/options.txt", b"renderDistance:12")]),
+    );
+
+    let launcher = Launcher::new(server);
+    launcher.server.json(
+        &launcher.ctx.api().project_versions_url(
+            "fabric-api",
+            Some("1.21.1"),
+            &["fabric".to_string()],
+        ),
+        &serde_json::json!([version_json(
+            "fabric-api",
+            "fapi-1",
+            "0.100.0",
+            "fabric-api-0.100.0.jar",
+            &launcher.server.url("/cdn/fabric-api-0.100.0.jar"),
+            FABRIC_API,
+            &[],
+        )]),
+    );
+    publish_with_iris(&launcher, "1.8.0");
+    launcher
+}
+
+fn publish_with_iris(launcher: &Launcher, iris_version: &str) {
+    publish_manifest(
+        &launcher.server,
+        serde_json::json!({
+            "extra_mods": [{ "name": "iris", "version": iris_version }],
+            "overrides": [{ "name": "options", "url": launcher.server.url("/cdn/overrides.zip") }]
+        }),
+    );
+}
+
+#[tokio::test]
+async fn test_install_from_manifest() {
+    let launcher = launcher().await;
+
+    let result = launcher.install().await.unwrap();
+    assert!(result.starts_with("✅"), "{}", result);
+    assert!(!result.contains("could not be downloaded"), "{}", result);
+
+    let minecraft_dir = launcher.minecraft_dir();
+    let read = |path: &str| std::fs::read(minecraft_dir.join(path)).unwrap();
+    assert_eq!(read("mods/sodium-0.6.0.jar"), SODIUM);
+    assert_eq!(read("mods/iris-1.8.0.jar"), IRIS);
+    assert_eq!(read("mods/fabric-api-0.100.0.jar"), FABRIC_API);
+    assert_eq!(read("config/story.txt"), b"pack config");
+    assert_eq!(read("config/options.txt"), b"renderDistance:12");
+
+    let story_path = launcher.instances.path().join("Story");
+    assert!(story_path.join(LOCKFILE_NAME).is_file());
+    let report = verify_instance_files(&story_path).unwrap();
+    assert!(report.missing.is_empty() && report.modified.is_empty());
+    assert!(report.extra.is_empty(), "{:?}", report.extra);
+
+    // Progress went to the injected sink
+    let progress = launcher.progress.0.lock().unwrap();
+    assert_eq!(progress.last().unwrap()["stage"], "complete");
+    assert!(progress.iter().any(|p| p["stage"] == "extra_mods"));
+}
+
+#[tokio::test]
+async fn test_reinstall_reuses_lock_and_blob_store() {
+    let launcher = launcher().await;
+
+    launcher.install().await.unwrap();
+    let result = launcher.install().await.unwrap();
+    assert!(result.contains("1 skipped"), "{}", result);
+
+    // Mods are downloaded once, and the lockfile saves resolving Iris again
+    assert_eq!(launcher.server.hits("/cdn/iris-1.8.0.jar"), 1);
+    assert_eq!(launcher.server.hits("/cdn/sodium-0.6.0.jar"), 1);
+    assert_eq!(launcher.server.hits("/v2/project/iris/version/1.8.0"), 1);
+}
+
+#[tokio::test]
+async fn test_concurrent_installs_keep_their_own_records() {
+    let launcher = launcher().await;
+    let other_instances = TempDir::new().unwrap();
+    let other_base = other_instances.path().to_string_lossy().to_string();
+
+    // The second install starts while the first, with its mods in place, waits for overrides
+    launcher
+        .server
+        .delay("/cdn/overrides.zip", Duration::from_millis(500));
+    let second = async {
+        while !launcher
+            .progress
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|p| p["stage"] == "overrides")
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        install_from_manifest(&launcher.ctx, launcher.manifest_url(), other_base, false).await
+    };
+    let (first, second) = tokio::join!(launcher.install(), second);
+    first.unwrap();
+    second.unwrap();
+
+    // Each instance recorded the mods it placed, so none of them look unmanaged
+    for instances in [launcher.instances.path(), other_instances.path()] {
+        let report = verify_instance_files(&instances.join("Story")).unwrap();
+        assert!(report.missing.is_empty() && report.modified.is_empty());
+        assert!(report.extra.is_empty(), "{:?}", report.extra);
+    }
+}
+
+#[tokio::test]
+async fn test_edited_jar_does_not_spread_through_the_blob_store() {
+    let launcher = launcher().await;
+    launcher.install().await.unwrap();
+
+    // Editing the hardlinked jar in place also changes its blob
+    let jar = launcher.minecraft_dir().join("mods/iris-1.8.0.jar");
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&jar)
+        .unwrap()
+        .write_all(b"edited")
+        .unwrap();
+    std::fs::remove_dir_all(launcher.instances.path().join("Story")).unwrap();
+
+    let result = launcher.install().await.unwrap();
+    assert!(!result.contains("could not be downloaded"), "{}", result);
+    assert_eq!(std::fs::read(&jar).unwrap(), IRIS);
+    assert_eq!(launcher.server.hits("/cdn/iris-1.8.0.jar"), 2);
+}
+
+#[tokio::test]
+async fn test_update_to_a_new_manifest() {
+    let launcher = launcher().await;
+    launcher.install().await.unwrap();
+
+    let diff = check_instance_updates(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(!diff.has_updates, "{}", diff.summary);
+
+    publish_with_iris(&launcher, "1.8.1");
+    let diff = check_instance_updates(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(diff.has_updates);
+    assert_eq!(diff.extra_mods.len(), 1);
+    assert_eq!(diff.extra_mods[0].name, "iris");
+
+    let result = launcher.install().await.unwrap();
+    assert!(!result.contains("could not be downloaded"), "{}", result);
+    let minecraft_dir = launcher.minecraft_dir();
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("mods/iris-1.8.1.jar")).unwrap(),
+        IRIS_UPDATE
+    );
+    let lock = load_lockfile(&launcher.instances.path().join("Story").join(LOCKFILE_NAME)).unwrap();
+    let iris = lock.mods.iter().find(|m| m.name == "iris").unwrap();
+    assert_eq!(iris.file.version_number, "1.8.1");
+}
+
+#[tokio::test]
+async fn test_update_moves_unpinned_mods_to_newer_versions() {
+    let launcher = launcher().await;
+    let iris_versions =
+        launcher
+            .ctx
+            .api()
+            .project_versions_url("iris", Some("1.21.1"), &["fabric".to_string()]);
+    let iris_version = |version: &str, content: &[u8]| {
+        let filename = format!("iris-{}.jar", version);
+        version_json(
+            "iris",
+            &format!("iris-{}", version),
+            version,
+            &filename,
+            &launcher.server.url(&format!("/cdn/{}", filename)),
+            content,
+            &["fabric-api"],
+        )
+    };
+    launcher.server.json(
+        &iris_versions,
+        &serde_json::json!([iris_version("1.8.0", IRIS)]),
+    );
+    publish_manifest(
+        &launcher.server,
+        serde_json::json!({ "extra_mods": [{ "name": "iris" }] }),
+    );
+    launcher.install().await.unwrap();
+
+    // A newer iris comes out; a plain install keeps the locked one
+    launcher.server.json(
+        &iris_versions,
+        &serde_json::json!([
+            iris_version("1.8.1", IRIS_UPDATE),
+            iris_version("1.8.0", IRIS)
+        ]),
+    );
+    let check = || {
+        check_instance_updates(
+            &launcher.ctx,
+            launcher.manifest_url(),
+            launcher.instance_base(),
+        )
+    };
+    let diff = check().await.unwrap();
+    assert!(diff.has_updates, "{}", diff.summary);
+    assert_eq!(
+        diff.extra_mods[0].reason.as_deref(),
+        Some("newer compatible version 1.8.1")
+    );
+    launcher.install().await.unwrap();
+    assert!(launcher
+        .minecraft_dir()
+        .join("mods/iris-1.8.0.jar")
+        .is_file());
+
+    // Updating resolves again, rewrites the lock and clears the notice
+    let result = update_from_manifest(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(!result.contains("could not be downloaded"), "{}", result);
+    assert_eq!(
+        std::fs::read(launcher.minecraft_dir().join("mods/iris-1.8.1.jar")).unwrap(),
+        IRIS_UPDATE
+    );
+    let lock = load_lockfile(&launcher.story_path().join(LOCKFILE_NAME)).unwrap();
+    let iris = lock.mods.iter().find(|m| m.name == "iris").unwrap();
+    assert_eq!(iris.file.version_number, "1.8.1");
+    let diff = check().await.unwrap();
+    assert!(!diff.has_updates, "{}", diff.summary);
+
+    // A published lockfile decides the versions, so a newer iris isn't an update
+    let lock_bytes = std::fs::read(launcher.story_path().join(LOCKFILE_NAME)).unwrap();
+    launcher.server.file("/story.lock.json", lock_bytes.clone());
+    publish_manifest(
+        &launcher.server,
+        serde_json::json!({
+            "extra_mods": [{ "name": "iris" }],
+            "lockfile": {
+                "url": launcher.server.url("/story.lock.json"),
+                "sha256": sha256_hex(&lock_bytes)
+            }
+        }),
+    );
+    launcher.server.json(
+        &iris_versions,
+        &serde_json::json!([
+            iris_version("1.9.0", b"iris 1.9.0 jar"),
+            iris_version("1.8.1", IRIS_UPDATE)
+        ]),
+    );
+    let diff = check().await.unwrap();
+    assert!(!diff.has_updates, "{}", diff.summary);
+}
+
+#[tokio::test]
+async fn test_missing_modpack_fails_the_install() {
+    let launcher = launcher().await;
+    launcher
+        .server
+        .remove("/v2/project/story-pack/version/1.0.0");
+    launcher.server.remove("/v2/version/pack-1");
+
+    let error = launcher.install().await.err().unwrap();
+    assert!(error.contains("Modpack download failed"), "{}", error);
+    assert!(error.contains("404"), "{}", error);
+    assert!(!launcher
+        .instances
+        .path()
+        .join("Story/.current_version.json")
+        .exists());
+}
+
+#[tokio::test]
+async fn test_missing_extra_mod_is_reported() {
+    let launcher = launcher().await;
+    launcher.server.remove("/v2/project/iris/version/1.8.0");
+
+    let result = launcher.install().await.unwrap();
+    assert!(result.contains("could not be downloaded"), "{}", result);
+    assert!(result.contains("extra mod iris"), "{}", result);
+    assert!(launcher
+        .minecraft_dir()
+        .join("mods/sodium-0.6.0.jar")
+        .is_file());
+
+    // A partial resolution is not pinned, so the next install tries again
+    let story_path = launcher.instances.path().join("Story");
+    assert!(!story_path.join(LOCKFILE_NAME).exists());
+}
+
+#[tokio::test]
+async fn test_incompatible_extra_mods_fail_the_install() {
+    let launcher = launcher().await;
+    let lithium = b"lithium 0.13.0 jar";
+    launcher.server.json(
+        "/v2/project/lithium/version/0.13.0",
+        &with_dependency(
+            version_json(
+                "lithium",
+                "lithium-0130",
+                "0.13.0",
+                "lithium-0.13.0.jar",
+                &launcher.server.url("/cdn/lithium-0.13.0.jar"),
+                lithium,
+                &[],
+            ),
+            "incompatible",
+            Some("iris"),
+            None,
+        ),
+    );
+    launcher.server.file("/cdn/lithium-0.13.0.jar", lithium);
+    publish_manifest(
+        &launcher.server,
+        serde_json::json!({
+            "extra_mods": [
+                { "name": "iris", "version": "1.8.0" },
+                { "name": "lithium", "version": "0.13.0" }
+            ]
+        }),
+    );
+
+    // The clash fails the install instead of installing both and calling it a download
+    let error = launcher.install().await.unwrap_err();
+    assert!(
+        error.contains("lithium is incompatible with iris"),
+        "{}",
+        error
+    );
+    assert!(!error.contains("could not be downloaded"), "{}", error);
+    assert!(!launcher
+        .minecraft_dir()
+        .join("mods/iris-1.8.0.jar")
+        .exists());
+    assert!(!launcher
+        .minecraft_dir()
+        .join("mods/lithium-0.13.0.jar")
+        .exists());
+    assert!(!launcher.story_path().join(LOCKFILE_NAME).exists());
+}
+
+#[tokio::test]
+async fn test_download_with_wrong_hash_is_rejected() {
+    let launcher = launcher().await;
+    launcher
+        .server
+        .file("/cdn/iris-1.8.0.jar", b"tampered jar".to_vec());
+
+    let result = launcher.install().await.unwrap();
+    assert!(result.contains("could not be downloaded"), "{}", result);
+    assert!(result.contains("iris-1.8.0.jar"), "{}", result);
+    assert!(!launcher
+        .minecraft_dir()
+        .join("mods/iris-1.8.0.jar")
+        .exists());
+    assert!(launcher
+        .minecraft_dir()
+        .join("mods/fabric-api-0.100.0.jar")
+        .is_file());
+}
+
+#[tokio::test]
+async fn test_slow_api_times_out() {
+    let launcher = launcher().await;
+    launcher.server.delay(
+        "/v2/project/story-pack/version/1.0.0",
+        Duration::from_secs(5),
+    );
+
+    let started = std::time::Instant::now();
+    let error = launcher.install().await.err().unwrap();
+    assert!(error.contains("timed out"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_repair_after_install() {
+    let launcher = launcher().await;
+    launcher.install().await.unwrap();
+
+    let minecraft_dir = launcher.minecraft_dir();
+    std::fs::remove_file(minecraft_dir.join("mods/iris-1.8.0.jar")).unwrap();
+    std::fs::write(minecraft_dir.join("config/options.txt"), b"changed").unwrap();
+    std::fs::write(minecraft_dir.join("mods/unmanaged.jar"), b"?").unwrap();
+
+    let report = repair_instance_files(&launcher.ctx, &launcher.instance_base(), true, false)
+        .await
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.repaired.len(), 2);
+    assert_eq!(report.removed, vec!["mods/unmanaged.jar"]);
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("mods/iris-1.8.0.jar")).unwrap(),
+        IRIS
+    );
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("config/options.txt")).unwrap(),
+        b"renderDistance:12"
+    );
+}
+
+#[tokio::test]
+async fn test_repair_extracts_only_broken_override_files() {
+    let launcher = launcher().await;
+    launcher.server.file(
+        "/cdn/overrides.zip",
+        zip_bytes(&[
+            ("config/options.txt", b"renderDistance:12"),
+            ("config/keys.txt", b"key.jump:space"),
+        ]),
+    );
+    launcher.install().await.unwrap();
+
+    // The cached zip is gone and upstream has since changed another file in it
+    let override_url = launcher.server.url("/cdn/overrides.zip");
+    std::fs::remove_file(cached_zip_path(
+        &launcher.ctx.zip_cache_dir(),
+        &override_url,
+    ))
+    .unwrap();
+    launcher.server.file(
+        "/cdn/overrides.zip",
+        zip_bytes(&[
+            ("config/options.txt", b"renderDistance:12"),
+            ("config/keys.txt", b"key.jump:shift"),
+        ]),
+    );
+    let minecraft_dir = launcher.minecraft_dir();
+    std::fs::remove_file(minecraft_dir.join("config/options.txt")).unwrap();
+
+    let report = repair_instance_files(&launcher.ctx, &launcher.instance_base(), false, false)
+        .await
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.repaired, vec!["config/options.txt"]);
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("config/options.txt")).unwrap(),
+        b"renderDistance:12"
+    );
+    // The healthy file from the same zip is left as it was
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("config/keys.txt")).unwrap(),
+        b"key.jump:space"
+    );
+}
+
+#[tokio::test]
+async fn test_offline_reinstall_needs_no_server() {
+    let launcher = launcher().await;
+    launcher.install().await.unwrap();
+    std::fs::remove_dir_all(launcher.instances.path().join("Story")).unwrap();
+
+    let manifest_url = launcher.manifest_url();
+    let Launcher {
+        server,
+        ctx,
+        instances,
+        data_dir: _data_dir,
+        ..
+    } = launcher;
+    drop(server);
+
+    let base = instances.path().to_string_lossy().to_string();
+    let result = install_from_manifest(&ctx, manifest_url, base, true)
+        .await
+        .unwrap();
+    assert!(result.starts_with("✅"), "{}", result);
+    assert!(instances
+        .path()
+        .join("Story/.minecraft/mods/iris-1.8.0.jar")
+        .is_file());
+}
+
+#[tokio::test]
+async fn test_install_modpack_and_single_mod() {
+    let launcher = launcher().await;
+
+    install_modpack(
+        &launcher.ctx,
+        "story-pack".to_string(),
+        "1.0.0".to_string(),
+        launcher.instance_base(),
+        false,
+    )
+    .await
+    .unwrap();
+    let mods_dir = launcher.minecraft_dir().join("mods");
+    assert!(mods_dir.join("sodium-0.6.0.jar").is_file());
+
+    let result = install_mod(
+        &launcher.ctx,
+        "iris".to_string(),
+        Some("1.8.0".to_string()),
+        "1.21.1".to_string(),
+        "fabric".to_string(),
+        mods_dir.to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(result.contains("iris 1.8.0"), "{}", result);
+    assert!(mods_dir.join("fabric-api-0.100.0.jar").is_file());
+}
@@ -1,5 +1,4 @@
-use story_launcher_lib::*;
-use std::path::Path;
+use story_core::*;
 use tempfile::TempDir;
 use std::fs;
 
@@ -141,6 +140,7 @@ fn test_file_hash_registry_workflow() {
     let file_info = FileInfo {
         hash: "abc123def456".to_string(),
         last_modified: "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        etag: None,
     };
     registry.files.insert("https://example.com/file.zip".to_string(), file_info);
     
@@ -206,6 +206,9 @@ fn test_instance_config_creation() {
         changelog: None,
         files: vec![],
         dependencies: vec![],
+        version_type: None,
+        featured: false,
+        date_published: None,
     };
     
     // Test instance config creation
@@ -0,0 +1,209 @@
+mod common;
+
+use common::*;
+use story_core::*;
+use tempfile::TempDir;
+
+fn loaders(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+fn version(id: &str, version_loaders: &[&str]) -> ModrinthVersionResponse {
+    ModrinthVersionResponse {
+        loaders: loaders(version_loaders),
+        ..modrinth_version(id)
+    }
+}
+
+fn instance(loader: &str, minecraft_version: &str, fallbacks: Option<&[&str]>) -> InstanceConfig {
+    InstanceConfig {
+        name: "story-pack".to_string(),
+        version: "1.0.0".to_string(),
+        minecraft_version: Some(minecraft_version.to_string()),
+        loader: Some(loader.to_string()),
+        release_channel: None,
+        prefer_featured: None,
+        loader_fallbacks: fallbacks.map(loaders),
+        accept_same_minor: None,
+    }
+}
+
+#[test]
+fn test_default_loader_fallbacks() {
+    assert_eq!(
+        instance("quilt", "1.21.1", None).compatible_loaders(),
+        loaders(&["quilt", "fabric"])
+    );
+    assert_eq!(
+        instance("neoforge", "1.20.1", None).compatible_loaders(),
+        loaders(&["neoforge", "forge"])
+    );
+    // Later NeoForge versions no longer load Forge mods
+    assert_eq!(
+        instance("neoforge", "1.21.1", None).compatible_loaders(),
+        loaders(&["neoforge"])
+    );
+    assert_eq!(
+        instance("fabric", "1.21.1", None).compatible_loaders(),
+        loaders(&["fabric"])
+    );
+}
+
+#[test]
+fn test_configured_fallbacks_replace_the_defaults() {
+    // An empty list turns fallbacks off
+    assert_eq!(
+        instance("quilt", "1.21.1", Some(&[])).compatible_loaders(),
+        loaders(&["quilt"])
+    );
+    // The instance loader always comes first and is never repeated
+    assert_eq!(
+        instance("neoforge", "1.21.1", Some(&["neoforge", "forge", "forge"])).compatible_loaders(),
+        loaders(&["neoforge", "forge"])
+    );
+}
+
+#[test]
+fn test_native_versions_win_over_fallbacks() {
+    let quilt = loaders(&["quilt", "fabric"]);
+    let versions = || {
+        vec![
+            version("0.6.1-fabric", &["fabric"]),
+            version("0.6.0-quilt", &["quilt"]),
+        ]
+    };
+
+    // An older Quilt build is still preferred to a newer Fabric one
+    let picked = select_version_with_fallbacks(
+        versions(),
+        "sodium",
+        "1.21.1",
+        &quilt,
+        &VersionPreference::default(),
+    )
+    .unwrap();
+    assert_eq!(picked.id, "0.6.0-quilt");
+    assert_eq!(loader_fallback(&picked, &quilt), None);
+
+    // Without one, the Fabric build is used and reported as a fallback
+    let fabric_only = vec![version("0.6.1-fabric", &["fabric"])];
+    let picked = select_version_with_fallbacks(
+        fabric_only,
+        "sodium",
+        "1.21.1",
+        &quilt,
+        &VersionPreference::default(),
+    )
+    .unwrap();
+    assert_eq!(loader_fallback(&picked, &quilt), Some("fabric".to_string()));
+
+    // Fallbacks that don't apply still fail with the instance loader's error
+    let error = select_version_with_fallbacks(
+        versions(),
+        "sodium",
+        "1.21.1",
+        &loaders(&["forge"]),
+        &VersionPreference::default(),
+    )
+    .err()
+    .unwrap();
+    assert!(error.contains("loader forge"));
+}
+
+#[tokio::test]
+async fn test_lockfile_records_loader_fallbacks() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().join("offline"), true);
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+    let api = ModrinthApi::default();
+
+    let manifest = StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        instance: instance("quilt", "1.21.1", None),
+        extra_mods: Some(vec![ExtraMod {
+            name: "sodium".to_string(),
+            version: None,
+            version_id: None,
+            sha512: None,
+            filename: None,
+            release_channel: None,
+            prefer_featured: None,
+            env: None,
+            url: None,
+            path: None,
+        }]),
+        overrides: None,
+        lockfile: None,
+        packwiz: None,
+    };
+    let sodium = serde_json::json!([{
+        "game_versions": ["1.21.1"],
+        "loaders": ["fabric"],
+        "id": "sodium-1",
+        "project_id": "sodium",
+        "name": "sodium",
+        "version_number": "0.6.1",
+        "changelog": null,
+        "files": [{
+            "hashes": { "sha512": "aa01" },
+            "url": "https://cdn.modrinth.com/data/sodium/sodium-1.jar",
+            "filename": "sodium-1.jar",
+            "primary": true,
+            "size": 1024
+        }],
+        "dependencies": []
+    }]);
+    cache
+        .store(
+            &ModrinthApi::default().project_versions_url(
+                "sodium",
+                Some("1.21.1"),
+                &loaders(&["quilt", "fabric"]),
+            ),
+            &sodium.to_string(),
+        )
+        .unwrap();
+
+    // The modpack isn't cached, so only the extra mod resolves
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1, "{:?}", failures);
+    assert_eq!(lock.loader, "quilt");
+    assert_eq!(lock.loader_fallbacks, loaders(&["fabric"]));
+    assert_eq!(lock.mods[0].loader_fallback, Some("fabric".to_string()));
+
+    let report = loader_fallback_report(&lock.mods).unwrap();
+    assert!(report.contains("1 mod(s)"));
+    assert!(report.contains("sodium 0.6.1 (fabric)"));
+
+    // Turning the fallback off makes the lock stale
+    let mut lock = lock;
+    lock.pack = Some(lock.mods[0].file.clone());
+    assert!(lock_matches_manifest(&lock, &manifest).is_ok());
+    let mut manifest = manifest;
+    manifest.instance.loader_fallbacks = Some(Vec::new());
+    let error = lock_matches_manifest(&lock, &manifest).err().unwrap();
+    assert!(error.contains("loader fallbacks"));
+}
+
+#[test]
+fn test_loader_fallbacks_must_be_a_list_of_loaders() {
+    let report = validate_manifest_text(
+        r#"{
+            "schema_version": 1,
+            "instance": { "name": "story-pack", "version": "1.0.0", "loader_fallbacks": "fabric" }
+        }"#,
+    );
+    assert!(!report.valid);
+    assert_eq!(report.errors[0].path, "$.instance.loader_fallbacks");
+
+    let report = validate_manifest_text(
+        r#"{
+            "schema_version": 1,
+            "instance": { "name": "story-pack", "version": "1.0.0", "loader_fallbacks": ["fabric", ""] }
+        }"#,
+    );
+    assert!(!report.valid);
+    assert_eq!(report.errors[0].path, "$.instance.loader_fallbacks[1]");
+}
@@ -0,0 +1,397 @@
+mod common;
+
+use common::*;
+use story_core::*;
+use tempfile::TempDir;
+
+const API: &str = "https://api.modrinth.com/v2";
+
+// An .mrpack whose index installs the given (project id, version id) pairs
+fn mrpack(pack_mods: &[(&str, &str)]) -> Vec<u8> {
+    let files: Vec<serde_json::Value> = pack_mods
+        .iter()
+        .map(|(project_id, version_id)| {
+            serde_json::json!({
+                "path": format!("mods/{}.jar", project_id),
+                "hashes": {},
+                "downloads": [format!(
+                    "https://cdn.modrinth.com/data/{}/versions/{}/{}.jar",
+                    project_id, version_id, project_id
+                )]
+            })
+        })
+        .collect();
+    let index = serde_json::json!({ "files": files }).to_string();
+    zip_bytes(&[("modrinth.index.json", index.as_bytes())])
+}
+
+// An offline response cache holding everything the resolver asks the API for, and a
+// blob store holding the modpack, which ships the given mods
+fn fixture(temp_dir: &TempDir, pack_mods: &[(&str, &str)]) -> (ResponseCache, BlobStore) {
+    let cache = ResponseCache::new(temp_dir.path().join("offline"), true);
+    let store = BlobStore::new(temp_dir.path().join("blobs"));
+    let put = |url: String, body: serde_json::Value| cache.store(&url, &body.to_string()).unwrap();
+
+    let pack_sha512 = store.insert_bytes(&mrpack(pack_mods)).unwrap();
+    let mut pack = cdn_version_json(
+        "story-pack",
+        "pack-v1",
+        "story-pack.mrpack",
+        &pack_sha512,
+        &[],
+    );
+    pack["files"][0]["filename"] = "story-pack-1.0.0.mrpack".into();
+    put(format!("{}/project/story-pack/version/1.0.0", API), pack);
+    put(
+        ModrinthApi::default().project_versions_url(
+            "sodium",
+            Some("1.21.1"),
+            &["fabric".to_string()],
+        ),
+        serde_json::json!([
+            cdn_version_json(
+                "sodium",
+                "sodium-2",
+                "sodium-2.jar",
+                "aa02",
+                &["fabric-api"]
+            ),
+            cdn_version_json(
+                "sodium",
+                "sodium-1",
+                "sodium-1.jar",
+                "aa01",
+                &["fabric-api"]
+            ),
+        ]),
+    );
+    put(
+        format!("{}/version/sodium-1", API),
+        cdn_version_json(
+            "sodium",
+            "sodium-1",
+            "sodium-1.jar",
+            "aa01",
+            &["fabric-api"],
+        ),
+    );
+    put(
+        format!("{}/project/iris/version/1.8.0", API),
+        cdn_version_json(
+            "iris",
+            "iris-180",
+            "iris-1.8.0.jar",
+            "bb01",
+            &["fabric-api", "sodium"],
+        ),
+    );
+    put(
+        ModrinthApi::default().project_versions_url(
+            "fabric-api",
+            Some("1.21.1"),
+            &["fabric".to_string()],
+        ),
+        serde_json::json!([cdn_version_json(
+            "fabric-api",
+            "fapi-1",
+            "fabric-api.jar",
+            "cc01",
+            &[]
+        )]),
+    );
+    (cache, store)
+}
+
+fn versioned(name: &str, version: &str) -> ExtraMod {
+    ExtraMod {
+        version: Some(version.to_string()),
+        ..extra_mod(name)
+    }
+}
+
+fn manifest(extra_mods: Vec<ExtraMod>) -> StoryManifest {
+    StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        instance: InstanceConfig {
+            name: "story-pack".to_string(),
+            version: "1.0.0".to_string(),
+            minecraft_version: Some("1.21.1".to_string()),
+            loader: Some("fabric".to_string()),
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
+        },
+        extra_mods: Some(extra_mods),
+        overrides: None,
+        lockfile: None,
+        packwiz: None,
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_lockfile_pins_mods_and_dependencies() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[]);
+    let api = ModrinthApi::default();
+    let manifest = manifest(vec![extra_mod("sodium"), versioned("iris", "1.8.0")]);
+
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(lock.pack.as_ref().unwrap().version_id, "pack-v1");
+
+    // The newest compatible sodium, iris as pinned, and fabric-api locked once for both
+    let names: Vec<&str> = lock.mods.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["sodium", "iris", "fabric-api"]);
+    assert_eq!(lock.mods[0].file.version_id, "sodium-2");
+    assert_eq!(lock.mods[1].dependencies, vec!["fabric-api", "sodium"]);
+    assert!(lock.mods[2].requested.is_none());
+    assert_eq!(lock.mods[2].file.sha512, "cc01");
+
+    assert!(lock_matches_manifest(&lock, &manifest).is_ok());
+
+    // Round trip through the file an instance keeps
+    let lock_path = temp_dir.path().join("Story").join(LOCKFILE_NAME);
+    save_lockfile(&lock_path, &lock).unwrap();
+    assert_eq!(load_lockfile(&lock_path).unwrap(), lock);
+}
+
+#[tokio::test]
+async fn test_resolve_lockfile_keeps_previous_entries() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[]);
+    let api = ModrinthApi::default();
+
+    let (mut previous, _) = resolve_lockfile(
+        &api,
+        &cache,
+        &store,
+        &manifest(vec![extra_mod("sodium")]),
+        None,
+    )
+    .await
+    .unwrap();
+    previous.mods[0].file.version_id = "sodium-1".to_string();
+    previous.mods[0].file.filename = "sodium-1.jar".to_string();
+
+    // Adding a mod keeps sodium where the previous lock had it
+    let manifest = manifest(vec![extra_mod("sodium"), versioned("iris", "1.8.0")]);
+    assert!(lock_matches_manifest(&previous, &manifest).is_err());
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, Some(&previous))
+        .await
+        .unwrap();
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(lock.mods[0].file.version_id, "sodium-1");
+    assert_eq!(lock.mods[1].file.version_id, "iris-180");
+    assert!(lock_matches_manifest(&lock, &manifest).is_ok());
+}
+
+#[tokio::test]
+async fn test_resolve_lockfile_reports_failures() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[]);
+    let api = ModrinthApi::default();
+    let manifest = manifest(vec![extra_mod("sodium"), extra_mod("lithium")]);
+
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].starts_with("extra mod lithium:"));
+    assert!(lock_matches_manifest(&lock, &manifest).is_err());
+}
+
+#[tokio::test]
+async fn test_lock_matches_manifest_detects_stale_locks() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[]);
+    let api = ModrinthApi::default();
+    let manifest = manifest(vec![extra_mod("sodium")]);
+    let (lock, _) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+
+    let mut newer_pack = self::manifest(vec![extra_mod("sodium")]);
+    newer_pack.instance.version = "1.1.0".to_string();
+    assert!(lock_matches_manifest(&lock, &newer_pack)
+        .unwrap_err()
+        .contains("v1.1.0"));
+
+    let repinned = self::manifest(vec![ExtraMod {
+        version_id: Some("sodium-1".to_string()),
+        ..extra_mod("sodium")
+    }]);
+    assert!(lock_matches_manifest(&lock, &repinned).is_err());
+
+    let removed = self::manifest(vec![]);
+    assert!(lock_matches_manifest(&lock, &removed)
+        .unwrap_err()
+        .contains("no longer in the manifest"));
+
+    let mut other_loader = self::manifest(vec![extra_mod("sodium")]);
+    other_loader.instance.loader = Some("quilt".to_string());
+    assert!(lock_matches_manifest(&lock, &other_loader).is_err());
+
+    let mut missing_dependency = lock.clone();
+    missing_dependency.mods.retain(|m| m.name != "fabric-api");
+    assert!(lock_matches_manifest(&missing_dependency, &manifest)
+        .unwrap_err()
+        .contains("dependency fabric-api"));
+}
+
+#[test]
+fn test_manifest_lockfile_reference_is_validated() {
+    let valid = r#"{
+        "schema_version": 1,
+        "instance": { "name": "story-pack", "version": "1.0.0" },
+        "lockfile": {
+            "url": "https://example.com/story.lock.json",
+            "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        }
+    }"#;
+    let manifest = parse_manifest(valid).unwrap();
+    assert_eq!(
+        manifest.lockfile.unwrap().url,
+        "https://example.com/story.lock.json"
+    );
+
+    let missing_hash = r#"{
+        "schema_version": 1,
+        "instance": { "name": "story-pack", "version": "1.0.0" },
+        "lockfile": { "url": "https://example.com/story.lock.json" }
+    }"#;
+    let report = validate_manifest_text(missing_hash);
+    assert!(!report.valid);
+    assert!(report.errors.iter().any(|e| e.path == "$.lockfile.sha256"));
+}
+
+#[tokio::test]
+async fn test_dependencies_shipped_by_the_pack_are_not_locked_again() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[("fabric-api", "fapi-0")]);
+    let api = ModrinthApi::default();
+    let manifest = manifest(vec![extra_mod("sodium"), versioned("iris", "1.8.0")]);
+
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+    assert!(failures.is_empty(), "{:?}", failures);
+    let names: Vec<&str> = lock.mods.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["sodium", "iris"]);
+    assert!(lock.mods[0].dependencies.is_empty());
+    assert_eq!(lock.mods[1].dependencies, vec!["sodium"]);
+    assert!(lock_matches_manifest(&lock, &manifest).is_ok());
+}
+
+#[tokio::test]
+async fn test_pinned_dependency_versions_are_honoured() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[]);
+    let api = ModrinthApi::default();
+    let shader_pack = with_dependency(
+        cdn_version_json("shaders", "shaders-1", "shaders.jar", "dd01", &[]),
+        "required",
+        Some("sodium"),
+        Some("sodium-1"),
+    );
+    cache
+        .store(
+            &format!("{}/project/shaders/version/1.0", API),
+            &shader_pack.to_string(),
+        )
+        .unwrap();
+
+    // Only the pinned dependency wants sodium, so the pin decides the version
+    let manifest = manifest(vec![versioned("shaders", "1.0")]);
+    let (lock, failures) = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap();
+    assert!(failures.is_empty(), "{:?}", failures);
+    let sodium = lock.mods.iter().find(|m| m.name == "sodium").unwrap();
+    assert_eq!(sodium.file.version_id, "sodium-1");
+    assert_eq!(lock.mods[0].dependencies, vec!["sodium"]);
+
+    // The manifest asking for the newest sodium conflicts with the pin
+    let manifest = self::manifest(vec![extra_mod("sodium"), versioned("shaders", "1.0")]);
+    let error = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap_err();
+    assert!(error.contains("1 conflict(s)"), "{}", error);
+    assert!(
+        error.contains("requires sodium version sodium-1"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_incompatible_and_embedded_dependencies() {
+    let api = ModrinthApi::default();
+    let sodium_extra = with_dependency(
+        with_dependency(
+            cdn_version_json("sodium-extra", "se-1", "sodium-extra.jar", "ee01", &[]),
+            "incompatible",
+            Some("optifabric"),
+            None,
+        ),
+        "embedded",
+        Some("reeses-sodium-options"),
+        None,
+    );
+    let manifest = manifest(vec![versioned("sodium-extra", "1.0")]);
+    let resolve = |pack_mods: &[(&str, &str)]| {
+        let temp_dir = TempDir::new().unwrap();
+        let (cache, store) = fixture(&temp_dir, pack_mods);
+        cache
+            .store(
+                &format!("{}/project/sodium-extra/version/1.0", API),
+                &sodium_extra.to_string(),
+            )
+            .unwrap();
+        let (api, manifest) = (&api, &manifest);
+        async move {
+            let result = resolve_lockfile(api, &cache, &store, manifest, None).await;
+            drop(temp_dir);
+            result
+        }
+    };
+
+    // Embedded jars ship inside the mod, and the incompatibility is recorded
+    let (lock, failures) = resolve(&[]).await.unwrap();
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(lock.mods.len(), 1);
+    assert_eq!(
+        lock.mods[0].incompatible,
+        vec![ModRef {
+            project_id: "optifabric".to_string(),
+            version_id: None
+        }]
+    );
+
+    // With the pack's optifabric in the install it's a conflict, not a missing download
+    let error = resolve(&[("optifabric", "of-1")]).await.unwrap_err();
+    assert!(
+        error.contains("sodium-extra is incompatible with optifabric from the modpack"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_extra_mod_conflicting_with_the_pack_is_reported() {
+    let temp_dir = TempDir::new().unwrap();
+    let (cache, store) = fixture(&temp_dir, &[("sodium", "sodium-1")]);
+    let api = ModrinthApi::default();
+
+    let manifest = manifest(vec![extra_mod("sodium")]);
+    let error = resolve_lockfile(&api, &cache, &store, &manifest, None)
+        .await
+        .unwrap_err();
+    assert!(
+        error.contains("already includes sodium as version sodium-1"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,253 @@
+use reqwest::header::{HeaderMap, HeaderValue};
+use story_core::*;
+
+fn manifest() -> StoryManifest {
+    StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        instance: InstanceConfig {
+            name: "story-pack".to_string(),
+            version: "1.1.0".to_string(),
+            minecraft_version: Some("1.21.1".to_string()),
+            loader: Some("fabric".to_string()),
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
+        },
+        extra_mods: Some(vec![
+            ExtraMod {
+                name: "sodium".to_string(),
+                version: Some("0.6.0".to_string()),
+                version_id: None,
+                sha512: None,
+                filename: None,
+                release_channel: None,
+                prefer_featured: None,
+                env: None,
+                url: None,
+                path: None,
+            },
+            ExtraMod {
+                name: "lithium".to_string(),
+                version: None,
+                version_id: None,
+                sha512: None,
+                filename: None,
+                release_channel: None,
+                prefer_featured: None,
+                env: None,
+                url: None,
+                path: None,
+            },
+        ]),
+        overrides: Some(vec![
+            Override {
+                name: "configs".to_string(),
+                url: "https://example.com/configs-v2.zip".to_string(),
+                sha256: None,
+                size: None,
+                env: None,
+            },
+            Override {
+                name: "resources".to_string(),
+                url: "https://example.com/resources.zip".to_string(),
+                sha256: None,
+                size: None,
+                env: None,
+            },
+        ]),
+        lockfile: None,
+        packwiz: None,
+    }
+}
+
+fn installed() -> serde_json::Value {
+    serde_json::json!({
+        "instance_name": "story-pack",
+        "instance_version": "1.0.0",
+        "extra_mods": [
+            { "name": "sodium", "version": "0.5.0" },
+            { "name": "iris", "version": "1.7.0" }
+        ],
+        "overrides": [
+            { "name": "configs", "url": "https://example.com/configs-v1.zip" },
+            { "name": "resources", "url": "https://example.com/resources.zip" },
+            { "name": "shaders", "url": "https://example.com/shaders.zip" }
+        ],
+        "last_updated": "2025-01-01T00:00:00Z"
+    })
+}
+
+#[test]
+fn test_diff_reports_added_removed_and_changed_entries() {
+    let diff = diff_manifest(&manifest(), Some(&installed())).finalize();
+
+    assert!(diff.has_updates);
+    assert!(!diff.untracked);
+
+    let pack = diff.pack.as_ref().unwrap();
+    assert_eq!(pack.kind, ChangeKind::Changed);
+    assert_eq!(pack.from.as_deref(), Some("1.0.0"));
+    assert_eq!(pack.to.as_deref(), Some("1.1.0"));
+
+    let mod_change = |name: &str| diff.extra_mods.iter().find(|c| c.name == name).unwrap();
+    assert_eq!(mod_change("sodium").kind, ChangeKind::Changed);
+    assert_eq!(mod_change("sodium").to.as_deref(), Some("0.6.0"));
+    assert_eq!(mod_change("lithium").kind, ChangeKind::Added);
+    assert_eq!(mod_change("iris").kind, ChangeKind::Removed);
+
+    let override_change = |name: &str| diff.overrides.iter().find(|c| c.name == name).unwrap();
+    assert_eq!(override_change("configs").kind, ChangeKind::Changed);
+    assert_eq!(override_change("shaders").kind, ChangeKind::Removed);
+    assert!(diff.overrides.iter().all(|c| c.name != "resources"));
+
+    assert!(diff.summary.starts_with("Updates available:"));
+}
+
+#[test]
+fn test_diff_is_empty_when_everything_matches() {
+    let mut manifest = manifest();
+    manifest.instance.version = "1.0.0".to_string();
+    manifest.extra_mods = Some(vec![ExtraMod {
+        name: "sodium".to_string(),
+        version: Some("0.5.0".to_string()),
+        version_id: None,
+        sha512: None,
+        filename: None,
+        release_channel: None,
+        prefer_featured: None,
+        env: None,
+        url: None,
+        path: None,
+    }]);
+    manifest.overrides = Some(vec![Override {
+        name: "resources".to_string(),
+        url: "https://example.com/resources.zip".to_string(),
+        sha256: None,
+        size: None,
+        env: None,
+    }]);
+    let installed = serde_json::json!({
+        "instance_name": "story-pack",
+        "instance_version": "1.0.0",
+        "extra_mods": [{ "name": "sodium", "version": "0.5.0" }],
+        "overrides": [{ "name": "resources", "url": "https://example.com/resources.zip" }]
+    });
+
+    let diff = diff_manifest(&manifest, Some(&installed)).finalize();
+    assert!(!diff.has_updates);
+    assert_eq!(
+        diff.summary,
+        "No updates available - everything is up to date"
+    );
+}
+
+#[test]
+fn test_missing_version_file_means_updates_needed() {
+    let diff = diff_manifest(&manifest(), None).finalize();
+
+    assert!(diff.untracked);
+    assert!(diff.has_updates);
+    assert_eq!(diff.pack.as_ref().unwrap().kind, ChangeKind::Added);
+    assert_eq!(diff.extra_mods.len(), 2);
+    assert_eq!(diff.overrides.len(), 2);
+}
+
+#[test]
+fn test_download_size_totals_known_sizes() {
+    let mut diff = diff_manifest(&manifest(), None);
+    diff.pack.as_mut().unwrap().download_size = Some(1024 * 1024);
+    diff.extra_mods[0].download_size = Some(512 * 1024);
+
+    let diff = diff.finalize();
+    assert_eq!(diff.download_size, 1024 * 1024 + 512 * 1024);
+    assert!(diff.summary.contains("1.5 MB"));
+}
+
+#[test]
+fn test_remote_validators_prefer_etag() {
+    let mut headers = HeaderMap::new();
+    headers.insert("ETag", HeaderValue::from_static("\"v2\""));
+    headers.insert(
+        "Last-Modified",
+        HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+    headers.insert("Content-Length", HeaderValue::from_static("4096"));
+    let remote = RemoteValidators::from_headers(&headers);
+    assert_eq!(remote.size, Some(4096));
+
+    let downloaded = FileInfo {
+        hash: "abc".to_string(),
+        last_modified: "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        etag: Some("\"v1\"".to_string()),
+    };
+    assert_eq!(
+        remote.changed_since(Some(&downloaded)).as_deref(),
+        Some("ETag changed")
+    );
+
+    // Without a stored ETag the Last-Modified date decides
+    let legacy = FileInfo {
+        hash: "abc".to_string(),
+        last_modified: "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        etag: None,
+    };
+    assert_eq!(remote.changed_since(Some(&legacy)), None);
+    assert!(remote.changed_since(None).is_some());
+}
+
+#[test]
+fn test_diff_reports_packwiz_changes() {
+    let mut manifest = manifest();
+    manifest.packwiz = Some(PackwizRef {
+        url: "https://example.com/pack/pack.toml".to_string(),
+        sha256: "ab".repeat(32),
+    });
+    let with_packwiz = |manifest: &StoryManifest, packwiz: serde_json::Value| {
+        let mut installed = installed();
+        installed["packwiz"] = packwiz;
+        diff_manifest(manifest, Some(&installed)).finalize()
+    };
+
+    let diff = with_packwiz(
+        &manifest,
+        serde_json::json!({
+            "url": "https://example.com/pack/pack.toml",
+            "sha256": "AB".repeat(32)
+        }),
+    );
+    assert!(diff.packwiz.is_none());
+
+    let diff = with_packwiz(
+        &manifest,
+        serde_json::json!({
+            "url": "https://example.com/pack/pack.toml",
+            "sha256": "cd".repeat(32)
+        }),
+    );
+    let change = diff.packwiz.as_ref().unwrap();
+    assert_eq!(change.kind, ChangeKind::Changed);
+    assert_eq!(change.reason.as_deref(), Some("pin changed"));
+    assert!(diff.summary.contains("Packwiz pack"), "{}", diff.summary);
+
+    // Installs from before the pin tracked only the URL
+    let diff = with_packwiz(
+        &manifest,
+        serde_json::json!("https://example.com/pack/pack.toml"),
+    );
+    assert_eq!(diff.packwiz.unwrap().reason.as_deref(), Some("pin changed"));
+
+    assert_eq!(
+        with_packwiz(&manifest, serde_json::Value::Null)
+            .packwiz
+            .unwrap()
+            .kind,
+        ChangeKind::Added
+    );
+    manifest.packwiz = None;
+    let diff = with_packwiz(
+        &manifest,
+        serde_json::json!({ "url": "https://example.com/pack/pack.toml" }),
+    );
+    assert_eq!(diff.packwiz.unwrap().kind, ChangeKind::Removed);
+}
@@ -0,0 +1,121 @@
+use story_core::*;
+
+const LEGACY_MANIFEST: &str = r#"{
+    "instance": { "name": "fabulously-optimized", "version": "6.4.0" },
+    "extra_mods": [
+        { "name": "sodium", "version": "mc1.21.1-0.6.6-fabric" },
+        { "name": "modmenu", "version": null }
+    ],
+    "overrides": [
+        { "name": "configs", "url": "https://example.com/configs.zip" }
+    ]
+}"#;
+
+fn error_paths(report: &ManifestValidation) -> Vec<&str> {
+    report.errors.iter().map(|e| e.path.as_str()).collect()
+}
+
+#[test]
+fn test_unversioned_manifest_is_migrated() {
+    let report = validate_manifest_text(LEGACY_MANIFEST);
+    assert!(report.valid, "{:?}", report.errors);
+    assert_eq!(report.schema_version, Some(0));
+    assert!(report.migrated);
+
+    let manifest = parse_manifest(LEGACY_MANIFEST).unwrap();
+    assert_eq!(manifest.schema_version, MANIFEST_SCHEMA_VERSION);
+    assert_eq!(manifest.extra_mods.unwrap().len(), 2);
+}
+
+#[test]
+fn test_typo_in_field_name_is_reported_with_suggestion() {
+    let text = r#"{
+        "schema_version": 1,
+        "instance": { "name": "pack", "version": "1.0.0" },
+        "extra_mod": [{ "name": "sodium" }]
+    }"#;
+    let report = validate_manifest_text(text);
+
+    assert!(!report.valid);
+    assert_eq!(error_paths(&report), vec!["$.extra_mod"]);
+    assert!(report.errors[0]
+        .message
+        .contains("did you mean `extra_mods`?"));
+    assert!(parse_manifest(text).is_err());
+}
+
+#[test]
+fn test_nested_errors_have_json_paths() {
+    let text = r#"{
+        "instance": { "name": "pack", "minecraft_version": 1211 },
+        "extra_mods": [
+            { "name": "sodium" },
+            { "name": "sodium", "versoin": "0.6.0" }
+        ],
+        "overrides": [
+            { "name": "configs", "url": "ftp://example.com/configs.zip", "sha256": "abc", "size": -1 }
+        ]
+    }"#;
+    let report = validate_manifest_text(text);
+    let paths = error_paths(&report);
+
+    assert!(paths.contains(&"$.instance.version"));
+    assert!(paths.contains(&"$.instance.minecraft_version"));
+    assert!(paths.contains(&"$.extra_mods[1].versoin"));
+    assert!(paths.contains(&"$.extra_mods[1].name"));
+    assert!(paths.contains(&"$.overrides[0].url"));
+    assert!(paths.contains(&"$.overrides[0].sha256"));
+    assert!(paths.contains(&"$.overrides[0].size"));
+
+    let error = parse_manifest(text).err().unwrap();
+    assert!(error.contains("$.overrides[0].url: expected an http(s) URL"));
+}
+
+#[test]
+fn test_newer_schema_and_invalid_json_are_rejected() {
+    let newer = format!(
+        r#"{{ "schema_version": {}, "instance": {{ "name": "pack", "version": "1.0.0" }} }}"#,
+        MANIFEST_SCHEMA_VERSION + 1
+    );
+    let report = validate_manifest_text(&newer);
+    assert_eq!(error_paths(&report), vec!["$.schema_version"]);
+    assert!(report.errors[0].message.contains("newer"));
+
+    let report = validate_manifest_text("{ \"instance\": ");
+    assert!(!report.valid);
+    assert!(report.errors[0].message.starts_with("invalid JSON"));
+}
+
+#[test]
+fn test_published_schema_is_up_to_date() {
+    let path =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../story-manifest.schema.json");
+    let published = std::fs::read_to_string(path).unwrap();
+    let published: serde_json::Value = serde_json::from_str(&published).unwrap();
+    assert_eq!(
+        published,
+        manifest_schema(),
+        "story-manifest.schema.json is stale, regenerate it from manifest_schema()"
+    );
+}
+
+#[test]
+fn test_errors_inside_optional_objects_are_located() {
+    let text = r#"{
+        "instance": { "name": "pack", "version": "1.0.0", "release_channel": "nightly" },
+        "extra_mods": [{ "name": "sodium", "env": { "client": "sometimes" } }],
+        "lockfile": { "url": "https://example.com/story.lock.json", "sha265": "abc" }
+    }"#;
+    let report = validate_manifest_text(text);
+    let paths = error_paths(&report);
+
+    assert!(paths.contains(&"$.instance.release_channel"));
+    assert!(paths.contains(&"$.extra_mods[0].env.client"));
+    assert!(paths.contains(&"$.lockfile.sha256"));
+    let typo = report
+        .errors
+        .iter()
+        .find(|e| e.path == "$.lockfile.sha265")
+        .unwrap();
+    assert!(typo.message.contains("did you mean `sha256`?"));
+}
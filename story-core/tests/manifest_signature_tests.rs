@@ -0,0 +1,89 @@
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use story_core::*;
+
+const MANIFEST: &[u8] = br#"{"instance":{"name":"story-pack","version":"1.0.0"}}"#;
+
+fn encode(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+fn public_key(key: &SigningKey) -> String {
+    encode(key.verifying_key().as_bytes())
+}
+
+fn sign(key: &SigningKey, message: &[u8]) -> String {
+    encode(&key.sign(message).to_bytes())
+}
+
+#[test]
+fn test_valid_signature_is_accepted() {
+    let key = signing_key(1);
+    let signature = sign(&key, MANIFEST);
+    let trusted = vec!["not a key".to_string(), public_key(&key)];
+
+    let trust = verify_manifest_signature(MANIFEST, Some(&signature), &trusted, false).unwrap();
+    assert_eq!(
+        trust,
+        ManifestTrust::Signed {
+            key: public_key(&key)
+        }
+    );
+}
+
+#[test]
+fn test_tampered_manifest_or_untrusted_key_is_rejected() {
+    let key = signing_key(1);
+    let signature = sign(&key, MANIFEST);
+
+    let tampered = br#"{"instance":{"name":"evil-pack","version":"1.0.0"}}"#;
+    assert!(
+        verify_manifest_signature(tampered, Some(&signature), &[public_key(&key)], true).is_err()
+    );
+
+    let other = signing_key(2);
+    assert!(
+        verify_manifest_signature(MANIFEST, Some(&signature), &[public_key(&other)], true).is_err()
+    );
+
+    // A signature we can't check is never treated as unsigned
+    assert!(verify_manifest_signature(MANIFEST, Some(&signature), &[], true).is_err());
+    assert!(
+        verify_manifest_signature(MANIFEST, Some("garbage"), &[public_key(&key)], true).is_err()
+    );
+}
+
+#[test]
+fn test_unsigned_manifest_requires_opt_in() {
+    let trusted = vec![public_key(&signing_key(1))];
+
+    let error = verify_manifest_signature(MANIFEST, None, &trusted, false).unwrap_err();
+    assert!(error.contains("allow_unsigned_manifests"));
+
+    assert_eq!(
+        verify_manifest_signature(MANIFEST, None, &trusted, true).unwrap(),
+        ManifestTrust::Unsigned
+    );
+}
+
+#[test]
+fn test_signature_url_and_settings_defaults() {
+    assert_eq!(
+        manifest_signature_url("https://example.com/story-manifest.json"),
+        "https://example.com/story-manifest.json.sig"
+    );
+
+    let settings = LauncherSettings::default();
+    assert!(settings.trusted_manifest_keys.is_empty());
+    assert!(!settings.allow_unsigned_manifests);
+
+    // Settings written before signing existed still load, with signing enforced
+    let settings: LauncherSettings =
+        serde_json::from_str(r#"{"blob_cache_max_bytes": 1024}"#).unwrap();
+    assert_eq!(settings.blob_cache_max_bytes, 1024);
+    assert!(!settings.allow_unsigned_manifests);
+}
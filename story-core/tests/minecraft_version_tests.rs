@@ -0,0 +1,165 @@
+mod common;
+
+use common::*;
+use story_core::*;
+
+fn version(id: &str, game_versions: &[&str]) -> ModrinthVersionResponse {
+    ModrinthVersionResponse {
+        game_versions: game_versions.iter().map(|g| g.to_string()).collect(),
+        ..modrinth_version(id)
+    }
+}
+
+fn mc(version: &str) -> MinecraftVersion {
+    MinecraftVersion::parse(version).unwrap()
+}
+
+#[test]
+fn test_parse_minecraft_versions() {
+    assert_eq!(
+        mc("1.21"),
+        MinecraftVersion::Release {
+            major: 1,
+            minor: 21,
+            patch: 0,
+            pre_release: None,
+        }
+    );
+    assert_eq!(
+        mc("1.21.1-rc1"),
+        MinecraftVersion::Release {
+            major: 1,
+            minor: 21,
+            patch: 1,
+            pre_release: Some(PreRelease::Rc(1)),
+        }
+    );
+    assert_eq!(mc("1.14 Pre-Release 3"), mc("1.14-pre3"));
+    assert_eq!(
+        mc("24w33a"),
+        MinecraftVersion::Snapshot {
+            year: 24,
+            week: 33,
+            build: 'a',
+        }
+    );
+    assert!(MinecraftVersion::parse("1.21.1.1").is_none());
+    assert!(MinecraftVersion::parse("b1.7.3").is_none());
+    assert!(MinecraftVersion::parse("1.21-beta").is_none());
+}
+
+#[test]
+fn test_minecraft_version_ordering() {
+    assert!(mc("1.21") < mc("1.21.1"));
+    assert!(mc("1.21.1-pre1") < mc("1.21.1-pre2"));
+    assert!(mc("1.21.1-pre2") < mc("1.21.1-rc1"));
+    assert!(mc("1.21.1-rc1") < mc("1.21.1"));
+    assert!(mc("1.20.6") < mc("1.21-pre1"));
+    assert!(mc("24w33a") < mc("24w33b"));
+    assert!(mc("23w51b") < mc("24w03a"));
+
+    // Snapshots and releases have no order between them
+    assert_eq!(mc("24w33a").partial_cmp(&mc("1.21.1")), None);
+}
+
+#[test]
+fn test_minecraft_version_ranges() {
+    let same_minor = MinecraftVersionRange::parse("1.21.x").unwrap();
+    assert!(same_minor.contains(&mc("1.21")));
+    assert!(same_minor.contains(&mc("1.21.4")));
+    assert!(!same_minor.contains(&mc("1.20.6")));
+    assert!(!same_minor.contains(&mc("1.22-pre1")));
+    assert!(!same_minor.contains(&mc("24w33a")));
+
+    let bounded = MinecraftVersionRange::parse(">=1.20.5 <1.21.2").unwrap();
+    assert!(bounded.contains(&mc("1.20.5")));
+    assert!(bounded.contains(&mc("1.21.1")));
+    assert!(!bounded.contains(&mc("1.21.2")));
+    assert!(!bounded.contains(&mc("1.20.4")));
+
+    let exact = MinecraftVersionRange::parse("1.21.1").unwrap();
+    assert!(exact.contains(&mc("1.21.1")));
+    assert!(!exact.contains(&mc("1.21")));
+
+    assert!(MinecraftVersionRange::parse("").is_none());
+    assert!(MinecraftVersionRange::parse(">=latest").is_none());
+
+    // A minor line with no successor matches nothing rather than overflowing
+    let last_minor = format!("1.{}", u32::MAX);
+    assert!(MinecraftVersionRange::parse(&format!("{}.x", last_minor)).is_none());
+    assert_eq!(
+        minecraft_version_fallback(&version("v1", &["1.21.1"]), &last_minor),
+        None
+    );
+}
+
+#[test]
+fn test_same_minor_matching_is_opt_in() {
+    let versions = || {
+        vec![
+            version("for-1.21", &["1.21"]),
+            version("for-1.20.6", &["1.20.6"]),
+        ]
+    };
+    let exact = VersionPreference::default();
+    let error = select_best_version(versions(), "sodium", "1.21.1", "fabric", &exact)
+        .err()
+        .unwrap();
+    assert!(error.contains("No compatible version"));
+
+    let same_minor = VersionPreference {
+        accept_same_minor: true,
+        ..VersionPreference::default()
+    };
+    let picked =
+        select_best_version(versions(), "sodium", "1.21.1", "fabric", &same_minor).unwrap();
+    assert_eq!(picked.id, "for-1.21");
+    assert_eq!(
+        minecraft_version_fallback(&picked, "1.21.1"),
+        Some("1.21".to_string())
+    );
+}
+
+#[test]
+fn test_exact_tags_win_over_same_minor_matches() {
+    let versions = vec![
+        version("newer-for-1.21", &["1.21"]),
+        version("older-for-1.21.1", &["1.21.1"]),
+    ];
+    let same_minor = VersionPreference {
+        accept_same_minor: true,
+        ..VersionPreference::default()
+    };
+    let picked = select_best_version(versions, "sodium", "1.21.1", "fabric", &same_minor).unwrap();
+    assert_eq!(picked.id, "older-for-1.21.1");
+    assert_eq!(minecraft_version_fallback(&picked, "1.21.1"), None);
+
+    // Pre-releases and snapshots of the minor version don't count
+    let prerelease = version("pre", &["1.21.2-pre1", "24w33a"]);
+    assert_eq!(minecraft_version_fallback(&prerelease, "1.21.1"), None);
+}
+
+#[test]
+fn test_same_minor_manifest_option() {
+    let manifest = parse_manifest(
+        r#"{
+            "schema_version": 1,
+            "instance": { "name": "story-pack", "version": "1.0.0", "accept_same_minor": true },
+            "extra_mods": [{ "name": "sodium" }]
+        }"#,
+    )
+    .unwrap();
+    let defaults = manifest.instance.version_preference();
+    assert!(defaults.accept_same_minor);
+    assert!(
+        manifest.extra_mods.unwrap()[0]
+            .version_preference(&defaults)
+            .accept_same_minor
+    );
+
+    // Without an exact tag to filter on, every version for the loader is listed
+    assert_eq!(
+        ModrinthApi::default().project_versions_url("sodium", None, &["fabric".to_string()]),
+        "https://api.modrinth.com/v2/project/sodium/version?loaders=%5B%22fabric%22%5D"
+    );
+}
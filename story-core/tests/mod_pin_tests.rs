@@ -0,0 +1,166 @@
+mod common;
+
+use common::*;
+use std::collections::HashMap;
+use story_core::*;
+
+fn jar(filename: &str, sha512: &str, primary: bool) -> ModrinthFile {
+    let mut hashes = HashMap::new();
+    hashes.insert("sha512".to_string(), sha512.to_string());
+    ModrinthFile {
+        hashes,
+        url: format!("https://cdn.modrinth.com/data/abc/{}", filename),
+        filename: filename.to_string(),
+        primary,
+        size: 1024,
+    }
+}
+
+fn version() -> ModrinthVersionResponse {
+    ModrinthVersionResponse {
+        id: "AbCdEf12".to_string(),
+        project_id: "AANobbMI".to_string(),
+        name: "Sodium 0.6.6".to_string(),
+        version_number: "mc1.21.1-0.6.6-fabric".to_string(),
+        files: vec![
+            jar("sodium-fabric-0.6.6.jar", "aa11", true),
+            jar("sodium-fabric-0.6.6-sources.jar", "bb22", false),
+        ],
+        version_type: None,
+        ..modrinth_version("AbCdEf12")
+    }
+}
+
+#[test]
+fn test_select_mod_file_honours_pins() {
+    let version = version();
+
+    let unpinned = extra_mod("sodium");
+    assert!(!unpinned.is_pinned());
+    assert_eq!(
+        select_mod_file(&version, &unpinned).unwrap().filename,
+        "sodium-fabric-0.6.6.jar"
+    );
+
+    let by_hash = ExtraMod {
+        sha512: Some("BB22".to_string()),
+        ..extra_mod("sodium")
+    };
+    assert!(by_hash.is_pinned());
+    assert_eq!(
+        select_mod_file(&version, &by_hash).unwrap().filename,
+        "sodium-fabric-0.6.6-sources.jar"
+    );
+
+    let by_name = ExtraMod {
+        filename: Some("sodium-fabric-0.6.6-sources.jar".to_string()),
+        ..extra_mod("sodium")
+    };
+    assert_eq!(
+        select_mod_file(&version, &by_name).unwrap().hashes["sha512"],
+        "bb22"
+    );
+}
+
+#[test]
+fn test_select_mod_file_rejects_mismatched_pins() {
+    let version = version();
+
+    let unknown_hash = ExtraMod {
+        sha512: Some("ff99".to_string()),
+        ..extra_mod("sodium")
+    };
+    assert!(select_mod_file(&version, &unknown_hash).is_err());
+
+    let conflicting = ExtraMod {
+        sha512: Some("aa11".to_string()),
+        filename: Some("sodium-fabric-0.6.6-sources.jar".to_string()),
+        ..extra_mod("sodium")
+    };
+    assert!(select_mod_file(&version, &conflicting).is_err());
+}
+
+#[test]
+fn test_resolution_is_recorded_in_version_tracking() {
+    let pinned = ExtraMod {
+        version_id: Some("AbCdEf12".to_string()),
+        ..extra_mod("sodium")
+    };
+    let resolved = ResolvedMod {
+        name: "sodium".to_string(),
+        project_id: "AANobbMI".to_string(),
+        version_id: "AbCdEf12".to_string(),
+        version_number: "mc1.21.1-0.6.6-fabric".to_string(),
+        filename: "sodium-fabric-0.6.6.jar".to_string(),
+        sha512: "aa11".to_string(),
+        loader_fallback: None,
+    };
+
+    let entry = tracked_extra_mod(&pinned, std::slice::from_ref(&resolved), None);
+    assert_eq!(entry["version_id"], "AbCdEf12");
+    assert_eq!(entry["resolved"]["sha512"], "aa11");
+
+    // A mod skipped this time keeps the resolution from the previous install
+    let previous = serde_json::json!({ "extra_mods": [entry] });
+    let carried = tracked_extra_mod(&pinned, &[], Some(&previous));
+    assert_eq!(carried["resolved"]["filename"], "sodium-fabric-0.6.6.jar");
+
+    // ...unless its manifest entry changed
+    let repinned = ExtraMod {
+        version_id: Some("ZzZz9999".to_string()),
+        ..extra_mod("sodium")
+    };
+    assert!(tracked_extra_mod(&repinned, &[], Some(&previous))
+        .get("resolved")
+        .is_none());
+}
+
+#[test]
+fn test_changed_pin_is_an_update() {
+    let manifest = StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        instance: InstanceConfig {
+            name: "story-pack".to_string(),
+            version: "1.0.0".to_string(),
+            minecraft_version: None,
+            loader: None,
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
+        },
+        extra_mods: Some(vec![ExtraMod {
+            version_id: Some("ZzZz9999".to_string()),
+            ..extra_mod("sodium")
+        }]),
+        overrides: None,
+        lockfile: None,
+        packwiz: None,
+    };
+    let installed = serde_json::json!({
+        "instance_name": "story-pack",
+        "instance_version": "1.0.0",
+        "extra_mods": [{ "name": "sodium", "version": null, "version_id": "AbCdEf12" }]
+    });
+
+    let diff = diff_manifest(&manifest, Some(&installed));
+    assert_eq!(diff.extra_mods.len(), 1);
+    assert_eq!(diff.extra_mods[0].reason.as_deref(), Some("pin changed"));
+}
+
+#[test]
+fn test_manifest_validator_checks_pins() {
+    let manifest = |sha512: &str| {
+        format!(
+            r#"{{
+                "instance": {{ "name": "pack", "version": "1.0.0" }},
+                "extra_mods": [{{ "name": "sodium", "version_id": "AbCdEf12", "sha512": "{}", "filename": "sodium.jar" }}]
+            }}"#,
+            sha512
+        )
+    };
+
+    assert!(validate_manifest_text(&manifest(&"a".repeat(128))).valid);
+    let report = validate_manifest_text(&manifest("abc"));
+    assert_eq!(report.errors[0].path, "$.extra_mods[0].sha512");
+}
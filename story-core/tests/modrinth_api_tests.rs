@@ -0,0 +1,127 @@
+use story_core::*;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// Answer one HTTP request with `body` and hand back the request head, lowercased
+async fn one_shot_server(body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let read = socket.read(&mut buffer).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request).to_lowercase()
+    });
+    (address, handle)
+}
+
+#[test]
+fn test_default_api_settings() {
+    let settings = LauncherSettings::default();
+    assert_eq!(settings.modrinth.api_base, "https://api.modrinth.com/v2");
+    assert!(settings
+        .modrinth
+        .user_agent
+        .starts_with("canefe/story-launcher/"));
+    assert_eq!(settings.modrinth.token, None);
+
+    let api = ModrinthApi::default();
+    assert_eq!(
+        api.url("/version/abc"),
+        "https://api.modrinth.com/v2/version/abc"
+    );
+}
+
+#[test]
+fn test_settings_without_modrinth_section_still_load() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("settings.json"),
+        r#"{ "blob_cache_max_bytes": 1024 }"#,
+    )
+    .unwrap();
+    let settings = load_launcher_settings(temp_dir.path());
+    assert_eq!(settings.blob_cache_max_bytes, 1024);
+    assert_eq!(settings.modrinth, ModrinthSettings::default());
+}
+
+#[test]
+fn test_custom_api_base() {
+    let api = ModrinthApi::new(&ModrinthSettings {
+        api_base: "https://staging-api.modrinth.com/v2/".to_string(),
+        ..ModrinthSettings::default()
+    })
+    .unwrap();
+    assert_eq!(api.base(), "https://staging-api.modrinth.com/v2");
+    assert_eq!(
+        api.project_versions_url("sodium", None, &["fabric".to_string()]),
+        "https://staging-api.modrinth.com/v2/project/sodium/version?loaders=%5B%22fabric%22%5D"
+    );
+    assert_eq!(
+        api.bulk_versions_url(&["a".to_string()]),
+        "https://staging-api.modrinth.com/v2/versions?ids=%5B%22a%22%5D"
+    );
+
+    let invalid_base = ModrinthApi::new(&ModrinthSettings {
+        api_base: "not a url".to_string(),
+        ..ModrinthSettings::default()
+    });
+    assert!(invalid_base
+        .err()
+        .unwrap()
+        .contains("Invalid Modrinth API base URL"));
+
+    let invalid_token = ModrinthApi::new(&ModrinthSettings {
+        token: Some("line\nbreak".to_string()),
+        ..ModrinthSettings::default()
+    });
+    assert!(invalid_token.is_err());
+}
+
+#[tokio::test]
+async fn test_api_requests_send_user_agent_and_token() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().join("offline"), false);
+
+    let (address, server) = one_shot_server("[]").await;
+    let api = ModrinthApi::new(&ModrinthSettings {
+        api_base: address.clone(),
+        user_agent: "example/pack-tools/1.0".to_string(),
+        token: Some("mrp_secret".to_string()),
+        ..ModrinthSettings::default()
+    })
+    .unwrap();
+    let body = cache
+        .get_text(api.client(), &api.url("/versions"))
+        .await
+        .unwrap();
+    assert_eq!(body, "[]");
+    let request = server.await.unwrap();
+    assert!(request.starts_with("get /versions "));
+    assert!(request.contains("user-agent: example/pack-tools/1.0"));
+    assert!(request.contains("authorization: mrp_secret"));
+
+    // Downloads from other hosts get the User-Agent but never the token
+    let (address, server) = one_shot_server("jar").await;
+    let body = cache
+        .get_text(api.http(), &format!("{}/file.jar", address))
+        .await
+        .unwrap();
+    assert_eq!(body, "jar");
+    let request = server.await.unwrap();
+    assert!(request.contains("user-agent: example/pack-tools/1.0"));
+    assert!(!request.contains("authorization"));
+}
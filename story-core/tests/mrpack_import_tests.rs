@@ -0,0 +1,265 @@
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::sync::Arc;
+use story_core::*;
+use tempfile::TempDir;
+
+const SODIUM: &[u8] = b"sodium 0.6.0 jar";
+
+// A launcher with an empty Modrinth API, and a private pack build whose mod is on a CDN
+async fn private_pack() -> (MockServer, LauncherContext, TempDir, Vec<u8>) {
+    let server = MockServer::start().await;
+    let data_dir = TempDir::new().unwrap();
+    save_launcher_settings(
+        data_dir.path(),
+        &LauncherSettings {
+            modrinth: ModrinthSettings {
+                api_base: server.url("/v2"),
+                timeout_secs: 5,
+                ..ModrinthSettings::default()
+            },
+            ..LauncherSettings::default()
+        },
+    )
+    .unwrap();
+    let ctx = LauncherContext::new(data_dir.path().to_path_buf(), Arc::new(NoProgress)).unwrap();
+
+    let index = serde_json::json!({
+        "formatVersion": 1,
+        "game": "minecraft",
+        "versionId": "1.1.0-rc1",
+        "name": "story-pack",
+        "files": [{
+            "path": "mods/sodium-0.6.0.jar",
+            "hashes": { "sha512": sha512_hex(SODIUM) },
+            "downloads": [server.url("/cdn/sodium-0.6.0.jar")]
+        }],
+        "dependencies": { "minecraft": "1.21.1", "fabric-loader": "0.16.10" }
+    });
+    let mrpack = zip_bytes(&[
+        ("modrinth.index.json", index.to_string().as_bytes()),
+        ("overrides/config/story.txt", b"pack config"),
+    ]);
+    server.file("/cdn/sodium-0.6.0.jar", SODIUM);
+    (server, ctx, data_dir, mrpack)
+}
+
+fn assert_installed(instances: &Path) {
+    let story_path = instances.join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("mods/sodium-0.6.0.jar")).unwrap(),
+        SODIUM
+    );
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("config/story.txt")).unwrap(),
+        b"pack config"
+    );
+    assert!(story_path.join("mrpack/modrinth.index.json").is_file());
+
+    // Not linked to a Modrinth project, and on the pack's own loader version
+    let instance_cfg = std::fs::read_to_string(story_path.join("instance.cfg")).unwrap();
+    assert!(instance_cfg.contains("ManagedPack=false"));
+    let mmc_pack: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(story_path.join("mmc-pack.json")).unwrap())
+            .unwrap();
+    assert_eq!(mmc_pack["components"][0]["version"], "1.21.1");
+    assert_eq!(mmc_pack["components"][1]["version"], "0.16.10");
+}
+
+#[tokio::test]
+async fn test_import_local_mrpack() {
+    let (_server, ctx, _data_dir, mrpack) = private_pack().await;
+    let builds = TempDir::new().unwrap();
+    let mrpack_path = builds.path().join("story-pack-1.1.0-rc1.mrpack");
+    std::fs::write(&mrpack_path, &mrpack).unwrap();
+    let instances = TempDir::new().unwrap();
+
+    let result = install_mrpack(
+        &ctx,
+        mrpack_path.to_string_lossy().to_string(),
+        instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(result.contains("story-pack v1.1.0-rc1"), "{}", result);
+    assert_installed(instances.path());
+}
+
+#[tokio::test]
+async fn test_import_mrpack_from_url_and_offline() {
+    let (server, ctx, _data_dir, mrpack) = private_pack().await;
+    server.file("/builds/story-pack.mrpack", mrpack.clone());
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+
+    install_mrpack(
+        &ctx,
+        server.url("/builds/story-pack.mrpack"),
+        instance_base.clone(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert_installed(instances.path());
+
+    // The same build on disk installs again from the blob store alone
+    std::fs::remove_dir_all(instances.path().join("Story")).unwrap();
+    server.remove("/cdn/sodium-0.6.0.jar");
+    let builds = TempDir::new().unwrap();
+    let mrpack_path = builds.path().join("story-pack.mrpack");
+    std::fs::write(&mrpack_path, &mrpack).unwrap();
+    install_mrpack(
+        &ctx,
+        mrpack_path.to_string_lossy().to_string(),
+        instance_base.clone(),
+        true,
+    )
+    .await
+    .unwrap();
+    assert_installed(instances.path());
+
+    // So does the URL, from the build it served last
+    std::fs::remove_dir_all(instances.path().join("Story")).unwrap();
+    server.remove("/builds/story-pack.mrpack");
+    install_mrpack(
+        &ctx,
+        server.url("/builds/story-pack.mrpack"),
+        instance_base,
+        true,
+    )
+    .await
+    .unwrap();
+    assert_installed(instances.path());
+}
+
+#[tokio::test]
+async fn test_import_rejects_invalid_mrpack() {
+    let (_server, ctx, _data_dir, _mrpack) = private_pack().await;
+    let builds = TempDir::new().unwrap();
+    let not_a_pack = builds.path().join("broken.mrpack");
+    std::fs::write(&not_a_pack, b"not a zip").unwrap();
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+
+    let error = install_mrpack(
+        &ctx,
+        not_a_pack.to_string_lossy().to_string(),
+        instance_base.clone(),
+        false,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("zip"), "{}", error);
+    assert!(!instances.path().join("Story").exists());
+
+    let missing = builds.path().join("missing.mrpack");
+    assert!(install_mrpack(
+        &ctx,
+        missing.to_string_lossy().to_string(),
+        instance_base,
+        false
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_import_replaces_previous_install_records() {
+    let (_server, ctx, _data_dir, mrpack) = private_pack().await;
+    let builds = TempDir::new().unwrap();
+    let mrpack_path = builds.path().join("story-pack.mrpack");
+    std::fs::write(&mrpack_path, &mrpack).unwrap();
+    let instances = TempDir::new().unwrap();
+
+    // Left behind by an earlier manifest install
+    let story_path = instances.path().join("Story");
+    std::fs::create_dir_all(&story_path).unwrap();
+    std::fs::write(
+        story_path.join(".current_version.json"),
+        r#"{ "instance_name": "story-pack", "instance_version": "1.0.0",
+             "extra_mods": [{ "name": "iris", "version": "1.8.0" }] }"#,
+    )
+    .unwrap();
+    save_managed_files(
+        &story_path,
+        &ManagedFiles {
+            files: vec![ManagedFile {
+                path: "mods/iris-1.8.0.jar".to_string(),
+                sha512: sha512_hex(b"iris"),
+                source: ManagedSource::ExtraMod {
+                    name: "iris".to_string(),
+                    urls: vec![],
+                },
+            }],
+        },
+    )
+    .unwrap();
+
+    install_mrpack(
+        &ctx,
+        mrpack_path.to_string_lossy().to_string(),
+        instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+
+    let version: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(story_path.join(".current_version.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(version["instance_version"], "1.1.0-rc1");
+    assert!(version.get("extra_mods").is_none());
+    assert!(load_managed_files(&story_path).files.is_empty());
+
+    let report = verify_instance_files(&story_path).unwrap();
+    assert!(report.is_clean(), "{:?}", report.missing);
+}
+
+#[tokio::test]
+async fn test_import_skips_paths_outside_the_instance() {
+    let (server, ctx, _data_dir, _mrpack) = private_pack().await;
+    let index = serde_json::json!({
+        "formatVersion": 1,
+        "game": "minecraft",
+        "versionId": "1.0.0",
+        "name": "evil-pack",
+        "files": [{
+            "path": "../../escaped.jar",
+            "hashes": { "sha512": sha512_hex(SODIUM) },
+            "downloads": [server.url("/cdn/sodium-0.6.0.jar")]
+        }],
+        "dependencies": { "minecraft": "1.21.1" }
+    });
+    let mrpack = zip_bytes(&[
+        ("modrinth.index.json", index.to_string().as_bytes()),
+        ("overrides/../../escaped.txt", b"outside"),
+        ("overrides/config/story.txt", b"pack config"),
+    ]);
+    let builds = TempDir::new().unwrap();
+    let mrpack_path = builds.path().join("evil.mrpack");
+    std::fs::write(&mrpack_path, &mrpack).unwrap();
+    let root = TempDir::new().unwrap();
+    let instances = root.path().join("instances");
+    std::fs::create_dir_all(&instances).unwrap();
+
+    install_mrpack(
+        &ctx,
+        mrpack_path.to_string_lossy().to_string(),
+        instances.to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+
+    assert!(instances
+        .join("Story/.minecraft/config/story.txt")
+        .is_file());
+    assert!(!instances.join("escaped.txt").exists());
+    assert!(!instances.join("Story/escaped.jar").exists());
+    assert!(!instances.join("escaped.jar").exists());
+}
@@ -0,0 +1,67 @@
+use story_core::*;
+use tempfile::TempDir;
+
+// Tests for the response cache that backs offline installs
+#[test]
+fn test_response_cache_store_and_load() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().join("offline"), false);
+
+    let url = "https://api.modrinth.com/v2/project/sodium/version";
+    assert!(cache.load(url).is_none());
+
+    cache.store(url, r#"[{"id":"abc"}]"#).unwrap();
+    assert_eq!(cache.load(url).unwrap(), r#"[{"id":"abc"}]"#);
+
+    // Different URLs never share an entry
+    assert!(cache
+        .load("https://api.modrinth.com/v2/project/iris/version")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_response_cache_offline_reads_only_from_disk() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache_dir = temp_dir.path().join("offline");
+    let client = reqwest::Client::new();
+
+    // Seed the cache as a previous online install would have
+    ResponseCache::new(cache_dir.clone(), false)
+        .store("https://example.invalid/manifest.json", "{}")
+        .unwrap();
+
+    let cache = ResponseCache::new(cache_dir, true);
+    let cached = cache
+        .get_text(&client, "https://example.invalid/manifest.json")
+        .await
+        .unwrap();
+    assert_eq!(cached, "{}");
+
+    // Uncached URLs fail without touching the network
+    let missing = cache
+        .get_text(&client, "https://example.invalid/other.json")
+        .await;
+    assert!(missing.unwrap_err().contains("offline mode"));
+}
+
+#[test]
+fn test_response_cache_reports_missing_items() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // Online installs tolerate failures; they're only reported
+    let online = ResponseCache::new(temp_dir.path().to_path_buf(), false);
+    online.record_missing("mods/sodium.jar: timeout".to_string());
+    assert!(online.ensure_complete().is_ok());
+    assert_eq!(online.missing().len(), 1);
+
+    // Offline installs refuse to finish and list every missing item
+    let offline = ResponseCache::new(temp_dir.path().to_path_buf(), true);
+    assert!(offline.ensure_complete().is_ok());
+    offline.record_missing("mods/sodium.jar: not in the local blob cache".to_string());
+    offline.record_missing("override config (https://example.com/config.zip)".to_string());
+
+    let error = offline.ensure_complete().unwrap_err();
+    assert!(error.contains("2 item(s) missing"));
+    assert!(error.contains("mods/sodium.jar"));
+    assert!(error.contains("https://example.com/config.zip"));
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use common::*;
+use std::fs;
+use story_core::*;
+use tempfile::TempDir;
+
+#[test]
+fn test_pinned_zip_must_match_hash_and_size() {
+    let hash = sha256_hex(b"zip bytes");
+
+    assert!(check_pinned_zip(&hash, 9, None, None).is_ok());
+    assert!(check_pinned_zip(&hash, 9, Some(&hash), Some(9)).is_ok());
+    assert!(check_pinned_zip(&hash, 9, Some(&hash.to_uppercase()), None).is_ok());
+
+    let error = check_pinned_zip(&hash, 9, Some(&sha256_hex(b"other")), None).unwrap_err();
+    assert!(error.contains("sha256 mismatch"));
+    let error = check_pinned_zip(&hash, 9, Some(&hash), Some(10)).unwrap_err();
+    assert!(error.contains("size mismatch"));
+}
+
+#[test]
+fn test_sha256_file_matches_sha256_hex() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("override.zip");
+    fs::write(&path, b"zip bytes").unwrap();
+
+    assert_eq!(sha256_file(&path).unwrap(), sha256_hex(b"zip bytes"));
+}
+
+#[test]
+fn test_override_pins_are_optional() {
+    let manifest: StoryManifest = serde_json::from_str(
+        r#"{
+            "instance": { "name": "story-pack", "version": "1.0.0" },
+            "overrides": [
+                { "name": "configs", "url": "https://example.com/configs.zip" },
+                {
+                    "name": "resources",
+                    "url": "https://example.com/resources.zip",
+                    "sha256": "abc123",
+                    "size": 2048
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let overrides = manifest.overrides.unwrap();
+    assert_eq!(overrides[0].sha256, None);
+    assert_eq!(overrides[0].size, None);
+    assert_eq!(overrides[1].sha256.as_deref(), Some("abc123"));
+    assert_eq!(overrides[1].size, Some(2048));
+}
+
+#[test]
+fn test_managed_override_source_keeps_pin() {
+    // Records written before pinning existed still load
+    let source: ManagedSource = serde_json::from_str(
+        r#"{ "type": "override", "name": "configs", "url": "https://example.com/configs.zip" }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        source,
+        ManagedSource::Override {
+            name: "configs".to_string(),
+            url: "https://example.com/configs.zip".to_string(),
+            sha256: None,
+        }
+    );
+    assert!(!serde_json::to_string(&source).unwrap().contains("sha256"));
+
+    let pinned = ManagedSource::Override {
+        name: "configs".to_string(),
+        url: "https://example.com/configs.zip".to_string(),
+        sha256: Some("abc123".to_string()),
+    };
+    let json = serde_json::to_string(&pinned).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ManagedSource>(&json).unwrap(),
+        pinned
+    );
+}
+
+#[test]
+fn test_changed_override_pin_is_an_update() {
+    let manifest = |sha256: &str| -> StoryManifest {
+        serde_json::from_value(serde_json::json!({
+            "instance": { "name": "story-pack", "version": "1.0.0" },
+            "overrides": [{
+                "name": "configs",
+                "url": "https://example.com/configs.zip",
+                "sha256": sha256,
+                "size": 2048
+            }]
+        }))
+        .unwrap()
+    };
+    let installed = serde_json::json!({
+        "instance_name": "story-pack",
+        "instance_version": "1.0.0",
+        "overrides": [{
+            "name": "configs",
+            "url": "https://example.com/configs.zip",
+            "sha256": "abc123",
+            "size": 2048
+        }]
+    });
+
+    let diff = diff_manifest(&manifest("abc123"), Some(&installed)).finalize();
+    assert!(!diff.has_updates, "{}", diff.summary);
+
+    // Same URL, new contents
+    let diff = diff_manifest(&manifest("def456"), Some(&installed)).finalize();
+    assert!(diff.has_updates);
+    assert_eq!(diff.overrides.len(), 1);
+    assert_eq!(diff.overrides[0].kind, ChangeKind::Changed);
+    assert_eq!(diff.overrides[0].reason.as_deref(), Some("pin changed"));
+}
+
+#[tokio::test]
+async fn test_override_failing_its_pin_is_not_tracked() {
+    let launcher = Launcher::new(MockServer::start().await);
+    publish_sodium_modpack(&launcher.server, &[]);
+    let configs = zip_bytes(&[("config/options.txt", b"renderDistance:12")]);
+    launcher.server.file("/cdn/configs.zip", configs.clone());
+    let publish = |sha256: String| {
+        publish_manifest(
+            &launcher.server,
+            serde_json::json!({
+                "overrides": [{
+                    "name": "configs",
+                    "url": launcher.server.url("/cdn/configs.zip"),
+                    "sha256": sha256
+                }]
+            }),
+        )
+    };
+
+    // The download doesn't match its pin, so it's neither extracted nor recorded
+    publish(sha256_hex(b"something else"));
+    launcher.install().await.unwrap();
+    assert!(!launcher.minecraft_dir().join("config/options.txt").exists());
+    let installed: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(launcher.story_path().join(".current_version.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(installed["overrides"], serde_json::json!([]));
+
+    // It's still offered as an update, and installs once the pin is right
+    let diff = check_instance_updates(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(diff.has_updates, "{}", diff.summary);
+    assert_eq!(diff.overrides[0].kind, ChangeKind::Added);
+
+    publish(sha256_hex(&configs));
+    launcher.install().await.unwrap();
+    assert!(launcher.minecraft_dir().join("config/options.txt").exists());
+    let diff = check_instance_updates(
+        &launcher.ctx,
+        launcher.manifest_url(),
+        launcher.instance_base(),
+    )
+    .await
+    .unwrap();
+    assert!(!diff.has_updates, "{}", diff.summary);
+}
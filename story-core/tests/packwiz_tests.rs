@@ -0,0 +1,410 @@
+mod common;
+
+use base64::Engine;
+use common::*;
+use ed25519_dalek::{Signer, SigningKey};
+use std::path::Path;
+use std::sync::Arc;
+use story_core::*;
+use tempfile::TempDir;
+
+const LITHIUM: &[u8] = b"lithium 0.14.0 jar";
+const LEDGER: &[u8] = b"ledger 1.3.0 jar";
+const STORY_CONFIG: &[u8] = b"pack config";
+const OPTIONS: &[u8] = b"fov:70";
+
+fn metafile(name: &str, filename: &str, side: &str, url: &str, format: &str, hash: &str) -> String {
+    format!(
+        "name = \"{}\"\nfilename = \"{}\"\nside = \"{}\"\n\n[download]\nurl = \"{}\"\nhash-format = \"{}\"\nhash = \"{}\"\n",
+        name, filename, side, url, format, hash
+    )
+}
+
+// A packwiz repo served under /pack: a client-only mod hashed with sha512, a mod for both
+// sides hashed with md5, a server-only mod hashed with murmur2, a config and a
+// player-editable options file. Returns the pack.toml URL and its sha256 pin
+fn packwiz_repo(server: &MockServer) -> PackwizRef {
+    let hash = |format: &str, bytes: &[u8]| packwiz_hash(format, bytes).unwrap();
+    let sodium = metafile(
+        "Sodium",
+        "sodium-0.6.0.jar",
+        "client",
+        &server.url("/cdn/sodium-0.6.0.jar"),
+        "sha512",
+        &hash("sha512", SODIUM),
+    );
+    let lithium = metafile(
+        "Lithium",
+        "lithium-0.14.0.jar",
+        "both",
+        &server.url("/cdn/lithium-0.14.0.jar"),
+        "md5",
+        &hash("md5", LITHIUM),
+    );
+    let ledger = metafile(
+        "Ledger",
+        "ledger-1.3.0.jar",
+        "server",
+        &server.url("/cdn/ledger-1.3.0.jar"),
+        "murmur2",
+        &hash("murmur2", LEDGER),
+    );
+    server.file("/pack/mods/sodium.pw.toml", sodium.as_bytes());
+    server.file("/pack/mods/lithium.pw.toml", lithium.as_bytes());
+    server.file("/pack/mods/ledger.pw.toml", ledger.as_bytes());
+    server.file("/pack/config/story.txt", STORY_CONFIG);
+    server.file("/pack/options.txt", OPTIONS);
+    server.file("/cdn/sodium-0.6.0.jar", SODIUM);
+    server.file("/cdn/lithium-0.14.0.jar", LITHIUM);
+    server.file("/cdn/ledger-1.3.0.jar", LEDGER);
+
+    let index = format!(
+        r#"hash-format = "sha256"
+
+[[files]]
+file = "config/story.txt"
+hash = "{}"
+
+[[files]]
+file = "mods/ledger.pw.toml"
+hash = "{}"
+metafile = true
+
+[[files]]
+file = "mods/lithium.pw.toml"
+hash = "{}"
+hash-format = "sha1"
+metafile = true
+
+[[files]]
+file = "mods/sodium.pw.toml"
+hash = "{}"
+metafile = true
+
+[[files]]
+file = "options.txt"
+hash = "{}"
+preserve = true
+"#,
+        hash("sha256", STORY_CONFIG),
+        hash("sha256", ledger.as_bytes()),
+        hash("sha1", lithium.as_bytes()),
+        hash("sha256", sodium.as_bytes()),
+        hash("sha256", OPTIONS),
+    );
+    server.file("/pack/index.toml", index.as_bytes());
+    let pack = format!(
+        r#"name = "story-pack"
+author = "Story"
+version = "1.2.0"
+pack-format = "packwiz:1.1.0"
+
+[index]
+file = "index.toml"
+hash-format = "sha256"
+hash = "{}"
+
+[versions]
+fabric = "0.16.10"
+minecraft = "1.21.1"
+"#,
+        hash("sha256", index.as_bytes())
+    );
+    server.file("/pack/pack.toml", pack.as_bytes());
+    PackwizRef {
+        url: server.url("/pack/pack.toml"),
+        sha256: sha256_hex(pack.as_bytes()),
+    }
+}
+
+// A story manifest at /story-manifest.json for an mrpack with its own config/story.txt,
+// plus the given packwiz pack
+fn serve_story_manifest(server: &MockServer, packwiz: PackwizRef) {
+    let index = serde_json::json!({
+        "files": [],
+        "dependencies": { "minecraft": "1.21.1", "fabric-loader": "0.16.14" }
+    });
+    publish_modpack(
+        server,
+        zip_bytes(&[
+            ("modrinth.index.json", index.to_string().as_bytes()),
+            ("overrides/config/story.txt", b"mrpack config"),
+        ]),
+    );
+    publish_manifest(server, serde_json::json!({ "packwiz": packwiz }));
+}
+
+async fn launcher(server: &MockServer) -> (LauncherContext, TempDir) {
+    let data_dir = TempDir::new().unwrap();
+    save_launcher_settings(data_dir.path(), &test_settings(server)).unwrap();
+    let ctx = LauncherContext::new(data_dir.path().to_path_buf(), Arc::new(NoProgress)).unwrap();
+    (ctx, data_dir)
+}
+
+fn assert_client_files(minecraft_dir: &Path) {
+    let read = |path: &str| std::fs::read(minecraft_dir.join(path)).unwrap();
+    assert_eq!(read("mods/sodium-0.6.0.jar"), SODIUM);
+    assert_eq!(read("mods/lithium-0.14.0.jar"), LITHIUM);
+    assert_eq!(read("config/story.txt"), STORY_CONFIG);
+    assert!(!minecraft_dir.join("mods/ledger-1.3.0.jar").exists());
+}
+
+#[tokio::test]
+async fn test_install_packwiz_pack_toml() {
+    let server = MockServer::start().await;
+    let pack_url = packwiz_repo(&server).url;
+    let (ctx, _data_dir) = launcher(&server).await;
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+    let story_path = instances.path().join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+
+    let result = install_from_manifest(&ctx, pack_url.clone(), instance_base.clone(), false)
+        .await
+        .unwrap();
+    assert!(result.contains("story-pack v1.2.0"), "{}", result);
+    assert_client_files(&minecraft_dir);
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("options.txt")).unwrap(),
+        OPTIONS
+    );
+    assert_eq!(server.hits("/cdn/ledger-1.3.0.jar"), 0);
+
+    let mmc_pack: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(story_path.join("mmc-pack.json")).unwrap())
+            .unwrap();
+    assert_eq!(mmc_pack["components"][0]["version"], "1.21.1");
+    assert_eq!(mmc_pack["components"][1]["version"], "0.16.10");
+
+    // Every file is managed, so verify and repair cover the pack
+    let managed = load_managed_files(&story_path);
+    assert_eq!(managed.files.len(), 4);
+    assert!(managed
+        .files
+        .iter()
+        .all(|f| matches!(f.source, ManagedSource::Packwiz { .. })));
+    std::fs::write(minecraft_dir.join("mods/sodium-0.6.0.jar"), b"broken").unwrap();
+    let report = verify_instance(&instance_base).await.unwrap();
+    assert_eq!(report.modified.len(), 1);
+    repair_instance_files(&ctx, &instance_base, false, false)
+        .await
+        .unwrap();
+    assert_client_files(&minecraft_dir);
+
+    // Player edits to preserved files survive a reinstall, which works offline too
+    std::fs::write(minecraft_dir.join("options.txt"), b"fov:110").unwrap();
+    server.remove("/cdn/sodium-0.6.0.jar");
+    server.remove("/cdn/lithium-0.14.0.jar");
+    std::fs::remove_file(minecraft_dir.join("mods/lithium-0.14.0.jar")).unwrap();
+    install_from_manifest(&ctx, pack_url, instance_base, true)
+        .await
+        .unwrap();
+    assert_client_files(&minecraft_dir);
+    assert_eq!(
+        std::fs::read(minecraft_dir.join("options.txt")).unwrap(),
+        b"fov:110"
+    );
+}
+
+#[tokio::test]
+async fn test_packwiz_rejects_hash_mismatches() {
+    let server = MockServer::start().await;
+    let pack_url = packwiz_repo(&server).url;
+    let (ctx, _data_dir) = launcher(&server).await;
+    let instances = TempDir::new().unwrap();
+    let minecraft_dir = instances.path().join("Story/.minecraft");
+
+    // A jar that doesn't match its .pw.toml, and a config that doesn't match the index
+    server.file("/cdn/lithium-0.14.0.jar", b"tampered jar".to_vec());
+    server.file("/pack/config/story.txt", b"tampered config".to_vec());
+    let result = install_from_manifest(
+        &ctx,
+        pack_url,
+        instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(
+        result.contains("mods/lithium-0.14.0.jar md5 mismatch"),
+        "{}",
+        result
+    );
+    assert!(
+        result.contains("config/story.txt sha256 mismatch"),
+        "{}",
+        result
+    );
+    assert!(!minecraft_dir.join("mods/lithium-0.14.0.jar").exists());
+    assert!(!minecraft_dir.join("config/story.txt").exists());
+    assert!(minecraft_dir.join("mods/sodium-0.6.0.jar").is_file());
+
+    // An index that doesn't match pack.toml stops the install
+    server.file("/pack/index.toml", b"hash-format = \"sha256\"\n".to_vec());
+    let other_instances = TempDir::new().unwrap();
+    let error = install_from_manifest(
+        &ctx,
+        server.url("/pack/pack.toml"),
+        other_instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("index.toml sha256 mismatch"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_manifest_installs_packwiz_alongside_mrpack() {
+    let server = MockServer::start().await;
+    let packwiz = packwiz_repo(&server);
+    let (ctx, _data_dir) = launcher(&server).await;
+
+    serve_story_manifest(&server, packwiz);
+
+    let instances = TempDir::new().unwrap();
+    install_from_manifest(
+        &ctx,
+        server.url("/story-manifest.json"),
+        instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+    let story_path = instances.path().join("Story");
+    let minecraft_dir = story_path.join(".minecraft");
+    let read = |path: &str| std::fs::read(minecraft_dir.join(path)).unwrap();
+    assert_eq!(read("mods/sodium-0.6.0.jar"), SODIUM);
+    assert_eq!(read("mods/lithium-0.14.0.jar"), LITHIUM);
+    assert!(!minecraft_dir.join("mods/ledger-1.3.0.jar").exists());
+    // Packwiz files go in after the mrpack's
+    assert_eq!(read("config/story.txt"), STORY_CONFIG);
+    // The instance still comes from the mrpack
+    let instance_cfg = std::fs::read_to_string(story_path.join("instance.cfg")).unwrap();
+    assert!(instance_cfg.contains("ManagedPackID=story-pack"));
+    let installed: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(story_path.join(".current_version.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(installed["packwiz"]["url"], server.url("/pack/pack.toml"));
+}
+
+#[tokio::test]
+async fn test_direct_pack_toml_must_be_signed() {
+    let server = MockServer::start().await;
+    let packwiz = packwiz_repo(&server);
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let encode = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+    let data_dir = TempDir::new().unwrap();
+    save_launcher_settings(
+        data_dir.path(),
+        &LauncherSettings {
+            trusted_manifest_keys: vec![encode(key.verifying_key().as_bytes())],
+            ..LauncherSettings::default()
+        },
+    )
+    .unwrap();
+    let ctx = LauncherContext::new(data_dir.path().to_path_buf(), Arc::new(NoProgress)).unwrap();
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+
+    // Nothing is downloaded for an unsigned pack
+    let error = install_from_manifest(&ctx, packwiz.url.clone(), instance_base.clone(), false)
+        .await
+        .unwrap_err();
+    assert!(error.contains("allow_unsigned_manifests"), "{}", error);
+    assert_eq!(server.hits("/pack/index.toml"), 0);
+
+    let pack = reqwest::get(&packwiz.url)
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    server.file(
+        "/pack/pack.toml.sig",
+        encode(&key.sign(&pack).to_bytes()).into_bytes(),
+    );
+    install_from_manifest(&ctx, packwiz.url, instance_base, false)
+        .await
+        .unwrap();
+    assert_client_files(&instances.path().join("Story/.minecraft"));
+}
+
+#[tokio::test]
+async fn test_manifest_packwiz_pin_is_checked_first() {
+    let server = MockServer::start().await;
+    let mut packwiz = packwiz_repo(&server);
+    let (ctx, _data_dir) = launcher(&server).await;
+    packwiz.sha256 = sha256_hex(b"some other pack.toml");
+    serve_story_manifest(&server, packwiz);
+
+    let instances = TempDir::new().unwrap();
+    let error = install_from_manifest(
+        &ctx,
+        server.url("/story-manifest.json"),
+        instances.path().to_string_lossy().to_string(),
+        false,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("pack.toml sha256 mismatch"), "{}", error);
+    assert_eq!(server.hits("/pack/index.toml"), 0);
+    assert_eq!(server.hits("/cdn/sodium-0.6.0.jar"), 0);
+}
+
+#[tokio::test]
+async fn test_packwiz_update_checks_and_editable_files() {
+    let server = MockServer::start().await;
+    let pack_url = packwiz_repo(&server).url;
+    let (ctx, _data_dir) = launcher(&server).await;
+    let instances = TempDir::new().unwrap();
+    let instance_base = instances.path().to_string_lossy().to_string();
+    let minecraft_dir = instances.path().join("Story/.minecraft");
+
+    install_from_manifest(&ctx, pack_url.clone(), instance_base.clone(), false)
+        .await
+        .unwrap();
+    let diff = check_instance_updates(&ctx, pack_url.clone(), instance_base.clone())
+        .await
+        .unwrap();
+    assert!(!diff.has_updates, "{}", diff.summary);
+
+    // Configs are copies, so editing one in place leaves the cached blob alone
+    std::fs::write(minecraft_dir.join("config/story.txt"), b"edited").unwrap();
+    let blob = ctx.blob_store().blob_path(&sha512_hex(STORY_CONFIG));
+    assert_eq!(std::fs::read(blob).unwrap(), STORY_CONFIG);
+
+    // Any change to pack.toml is an update, even with the same version
+    let pack = reqwest::get(&pack_url).await.unwrap().text().await.unwrap();
+    server.file(
+        "/pack/pack.toml",
+        pack.replace("author = \"Story\"", "author = \"Story Team\""),
+    );
+    let diff = check_instance_updates(&ctx, pack_url, instance_base)
+        .await
+        .unwrap();
+    assert!(diff.has_updates);
+    let change = diff.packwiz.unwrap();
+    assert_eq!(change.kind, ChangeKind::Changed);
+    assert_eq!(change.reason.as_deref(), Some("pin changed"));
+}
+
+#[test]
+fn test_packwiz_side_filtering() {
+    assert!(PackwizSide::Both.installs_on(PackwizSide::Client));
+    assert!(PackwizSide::Client.installs_on(PackwizSide::Client));
+    assert!(!PackwizSide::Server.installs_on(PackwizSide::Client));
+    assert!(!PackwizSide::Client.installs_on(PackwizSide::Server));
+    assert!(is_packwiz_url(
+        "https://example.com/pack/pack.toml?ref=main"
+    ));
+    assert!(!is_packwiz_url("https://example.com/story-manifest.json"));
+
+    let pack: PackwizPack = toml::from_str(
+        "name = \"p\"\n[index]\nfile = \"index.toml\"\nhash-format = \"sha256\"\nhash = \"00\"\n[versions]\nminecraft = \"1.20.1\"\nneoforge = \"47.1.0\"\n",
+    )
+    .unwrap();
+    let dependencies = packwiz_dependencies(&pack);
+    assert_eq!(dependencies["minecraft"], "1.20.1");
+    assert_eq!(dependencies["neoforge"], "47.1.0");
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use common::*;
+use story_core::*;
+
+fn version(id: &str, version_type: &str, featured: bool) -> ModrinthVersionResponse {
+    ModrinthVersionResponse {
+        version_type: Some(version_type.to_string()),
+        featured,
+        date_published: None,
+        ..modrinth_version(id)
+    }
+}
+
+fn pick(versions: Vec<ModrinthVersionResponse>, preference: VersionPreference) -> String {
+    select_best_version(versions, "sodium", "1.21.1", "fabric", &preference)
+        .unwrap()
+        .id
+}
+
+#[test]
+fn test_releases_win_over_newer_prereleases() {
+    let versions = || {
+        vec![
+            version("0.7.0-alpha", "alpha", false),
+            version("0.6.1-beta", "beta", false),
+            version("0.6.0", "release", false),
+        ]
+    };
+    assert_eq!(pick(versions(), VersionPreference::default()), "0.6.0");
+
+    let beta = VersionPreference {
+        channel: Some(ReleaseChannel::Beta),
+        prefer_featured: false,
+        accept_same_minor: false,
+    };
+    assert_eq!(pick(versions(), beta), "0.6.1-beta");
+
+    let alpha = VersionPreference {
+        channel: Some(ReleaseChannel::Alpha),
+        prefer_featured: false,
+        accept_same_minor: false,
+    };
+    assert_eq!(pick(versions(), alpha), "0.7.0-alpha");
+}
+
+#[test]
+fn test_channel_fallback_only_without_explicit_channel() {
+    let versions = || {
+        vec![
+            version("2.0-alpha", "alpha", false),
+            version("1.0-beta", "beta", false),
+        ]
+    };
+
+    // A mod without releases still installs its most stable build
+    assert_eq!(pick(versions(), VersionPreference::default()), "1.0-beta");
+
+    // ...unless the manifest explicitly asked for releases
+    let release = VersionPreference {
+        channel: Some(ReleaseChannel::Release),
+        prefer_featured: false,
+        accept_same_minor: false,
+    };
+    let error = select_best_version(versions(), "sodium", "1.21.1", "fabric", &release)
+        .err()
+        .unwrap();
+    assert!(error.contains("No Release version of mod sodium"));
+}
+
+#[test]
+fn test_featured_preference_and_compatibility() {
+    let mut other_loader = version("0.6.2", "release", true);
+    other_loader.loaders = vec!["neoforge".to_string()];
+    let versions = || {
+        vec![
+            version("0.6.1", "release", false),
+            version("0.6.0", "release", true),
+        ]
+    };
+
+    assert_eq!(pick(versions(), VersionPreference::default()), "0.6.1");
+    let featured = VersionPreference {
+        channel: None,
+        prefer_featured: true,
+        accept_same_minor: false,
+    };
+    assert_eq!(pick(versions(), featured), "0.6.0");
+
+    // Versions for other loaders are never picked, featured or not
+    let mut with_other = versions();
+    with_other.insert(0, other_loader);
+    assert_eq!(pick(with_other, featured), "0.6.0");
+}
+
+#[test]
+fn test_per_mod_preferences_override_the_instance() {
+    let manifest = parse_manifest(
+        r#"{
+            "schema_version": 1,
+            "instance": {
+                "name": "story-pack",
+                "version": "1.0.0",
+                "release_channel": "beta",
+                "prefer_featured": true
+            },
+            "extra_mods": [
+                { "name": "sodium" },
+                { "name": "iris", "release_channel": "release", "prefer_featured": false }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let defaults = manifest.instance.version_preference();
+    assert_eq!(defaults.channel, Some(ReleaseChannel::Beta));
+    assert!(defaults.prefer_featured);
+
+    let mods = manifest.extra_mods.unwrap();
+    assert_eq!(mods[0].version_preference(&defaults), defaults);
+    assert_eq!(
+        mods[1].version_preference(&defaults),
+        VersionPreference {
+            channel: Some(ReleaseChannel::Release),
+            prefer_featured: false,
+            accept_same_minor: false,
+        }
+    );
+}
+
+#[test]
+fn test_invalid_channel_is_rejected() {
+    let report = validate_manifest_text(
+        r#"{
+            "schema_version": 1,
+            "instance": { "name": "story-pack", "version": "1.0.0", "release_channel": "stable" },
+            "extra_mods": [{ "name": "sodium", "prefer_featured": "yes" }]
+        }"#,
+    );
+    assert!(!report.valid);
+    let paths: Vec<&str> = report.errors.iter().map(|e| e.path.as_str()).collect();
+    assert!(paths.contains(&"$.instance.release_channel"));
+    assert!(paths.contains(&"$.extra_mods[0].prefer_featured"));
+}
+
+#[test]
+fn test_project_versions_url_filters_server_side() {
+    assert_eq!(
+        ModrinthApi::default().project_versions_url("sodium", Some("1.21.1"), &["fabric".to_string()]),
+        "https://api.modrinth.com/v2/project/sodium/version?loaders=%5B%22fabric%22%5D&game_versions=%5B%221.21.1%22%5D"
+    );
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use common::*;
+use std::path::Path;
+use story_core::*;
+use tempfile::TempDir;
+
+const LITHIUM: &[u8] = b"lithium 0.14.0 jar";
+const IRIS: &[u8] = b"iris 1.8.0 jar";
+const LEDGER: &[u8] = b"ledger 1.3.0 jar";
+const FABRIC_API: &[u8] = b"fabric api 0.100.0 jar";
+const SERVER_LAUNCHER: &[u8] = b"fabric server launcher jar";
+
+const LAUNCHER_PATH: &str = "/fabric-meta/v2/versions/loader/1.21.1/0.16.14/1.0.1/server/jar";
+const LAUNCHER_JAR: &str = "fabric-server-mc.1.21.1-loader.0.16.14-launcher.1.0.1.jar";
+
+// A mock Modrinth and Fabric meta with a pack that has client-only files, a client-only
+// and a server-side extra mod, and a client-only manifest override
+async fn published_pack(data_dir: &Path) -> MockServer {
+    let server = MockServer::start().await;
+    save_launcher_settings(
+        data_dir,
+        &LauncherSettings {
+            loaders: LoaderSources {
+                fabric_meta: server.url("/fabric-meta"),
+                ..LoaderSources::default()
+            },
+            ..test_settings(&server)
+        },
+    )
+    .unwrap();
+
+    let index = serde_json::json!({
+        "files": [
+            {
+                "path": "mods/sodium-0.6.0.jar",
+                "hashes": { "sha512": sha512_hex(SODIUM) },
+                "env": { "client": "required", "server": "unsupported" },
+                "downloads": [server.url("/cdn/sodium-0.6.0.jar")]
+            },
+            {
+                "path": "mods/lithium-0.14.0.jar",
+                "hashes": { "sha512": sha512_hex(LITHIUM) },
+                "env": { "client": "optional", "server": "optional" },
+                "downloads": [server.url("/cdn/lithium-0.14.0.jar")]
+            }
+        ],
+        "dependencies": { "minecraft": "1.21.1", "fabric-loader": "0.16.14" }
+    });
+    let mrpack = zip_bytes(&[
+        ("modrinth.index.json", index.to_string().as_bytes()),
+        ("overrides/config/shared.txt", b"pack"),
+        ("overrides/config/tuned.txt", b"client tuning"),
+        ("server-overrides/config/tuned.txt", b"server tuning"),
+        ("server-overrides/server.properties", b"motd=Story"),
+        ("client-overrides/options.txt", b"fov:90"),
+    ]);
+    server.file("/cdn/sodium-0.6.0.jar", SODIUM);
+    server.file("/cdn/lithium-0.14.0.jar", LITHIUM);
+    publish_modpack(&server, mrpack);
+
+    // Iris is client-only and pulls in Fabric API only through Ledger, which runs on the server
+    for (project, version, content, dependencies) in [
+        ("iris", "1.8.0", IRIS, &[][..]),
+        ("ledger", "1.3.0", LEDGER, &["fabric-api"][..]),
+    ] {
+        let filename = format!("{}-{}.jar", project, version);
+        server.json(
+            &format!("/v2/project/{}/version/{}", project, version),
+            &version_json(
+                project,
+                &format!("{}-{}", project, version),
+                version,
+                &filename,
+                &server.url(&format!("/cdn/{}", filename)),
+                content,
+                dependencies,
+            ),
+        );
+        server.file(&format!("/cdn/{}", filename), content);
+    }
+    server.json(
+        &ModrinthApi::new(&ModrinthSettings {
+            api_base: server.url("/v2"),
+            ..ModrinthSettings::default()
+        })
+        .unwrap()
+        .project_versions_url("fabric-api", Some("1.21.1"), &["fabric".to_string()]),
+        &serde_json::json!([version_json(
+            "fabric-api",
+            "fapi-1",
+            "0.100.0",
+            "fabric-api-0.100.0.jar",
+            &server.url("/cdn/fabric-api-0.100.0.jar"),
+            FABRIC_API,
+            &[],
+        )]),
+    );
+    server.file("/cdn/fabric-api-0.100.0.jar", FABRIC_API);
+
+    server.file(
+        "/cdn/server-config.zip",
+        zip_bytes(&[("config/ledger.toml", b"database = \"sqlite\"")]),
+    );
+    server.file(
+        "/cdn/shaders.zip",
+        zip_bytes(&[("shaderpacks/story.txt", b"shader")]),
+    );
+
+    server.json(
+        "/fabric-meta/v2/versions/installer",
+        &serde_json::json!([
+            { "version": "1.1.0", "stable": false },
+            { "version": "1.0.1", "stable": true }
+        ]),
+    );
+    server.file(LAUNCHER_PATH, SERVER_LAUNCHER);
+
+    publish_manifest(
+        &server,
+        serde_json::json!({
+            "extra_mods": [
+                { "name": "iris", "version": "1.8.0", "env": { "server": "unsupported" } },
+                { "name": "ledger", "version": "1.3.0", "env": { "client": "optional" } }
+            ],
+            "overrides": [
+                { "name": "server-config", "url": server.url("/cdn/server-config.zip") },
+                {
+                    "name": "shaders",
+                    "url": server.url("/cdn/shaders.zip"),
+                    "env": { "server": "unsupported" }
+                }
+            ]
+        }),
+    );
+    server
+}
+
+#[tokio::test]
+async fn test_install_server_skips_client_only_files() {
+    let data_dir = TempDir::new().unwrap();
+    let server_dir = TempDir::new().unwrap();
+    let server = published_pack(data_dir.path()).await;
+    let ctx = LauncherContext::new(
+        data_dir.path().to_path_buf(),
+        std::sync::Arc::new(NoProgress),
+    )
+    .unwrap();
+
+    let report = install_server_from_manifest(
+        &ctx,
+        server.url("/story-manifest.json"),
+        server_dir.path().to_string_lossy().to_string(),
+        false,
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(report.missing.is_empty(), "{:?}", report.missing);
+    assert_eq!(report.minecraft_version, "1.21.1");
+    assert_eq!(report.loader, "fabric-loader");
+    assert_eq!(report.loader_version, "0.16.14");
+    assert_eq!(report.launcher, LAUNCHER_JAR);
+    assert_eq!(
+        report.next_step,
+        format!("java -jar {} nogui", LAUNCHER_JAR)
+    );
+
+    let dir = server_dir.path();
+    let read = |path: &str| std::fs::read(dir.join(path)).unwrap();
+    assert_eq!(read("mods/lithium-0.14.0.jar"), LITHIUM);
+    assert_eq!(read("mods/ledger-1.3.0.jar"), LEDGER);
+    assert_eq!(read("mods/fabric-api-0.100.0.jar"), FABRIC_API);
+    assert_eq!(read(LAUNCHER_JAR), SERVER_LAUNCHER);
+    assert!(dir.join(LOCKFILE_NAME).is_file());
+
+    // server-overrides/ wins over overrides/, and client-overrides/ never lands
+    assert_eq!(read("config/shared.txt"), b"pack");
+    assert_eq!(read("config/tuned.txt"), b"server tuning");
+    assert_eq!(read("server.properties"), b"motd=Story");
+    assert!(!dir.join("options.txt").exists());
+
+    // Client-only pack files, extra mods and overrides are left out
+    assert_eq!(read("config/ledger.toml"), b"database = \"sqlite\"");
+    assert!(!dir.join("shaderpacks").exists());
+    assert!(!dir.join("mods/sodium-0.6.0.jar").exists());
+    assert!(!dir.join("mods/iris-1.8.0.jar").exists());
+    assert_eq!(server.hits("/cdn/sodium-0.6.0.jar"), 0);
+    assert_eq!(server.hits("/cdn/iris-1.8.0.jar"), 0);
+    for skipped in ["mods/sodium-0.6.0.jar", "iris", "shaders"] {
+        assert!(
+            report.skipped_client_only.iter().any(|s| s == skipped),
+            "{:?}",
+            report.skipped_client_only
+        );
+    }
+
+    // The EULA is never accepted implicitly
+    assert!(!report.eula_accepted);
+    assert!(!dir.join("eula.txt").exists());
+}
+
+#[tokio::test]
+async fn test_install_server_accepts_eula_when_asked() {
+    let data_dir = TempDir::new().unwrap();
+    let server_dir = TempDir::new().unwrap();
+    let server = published_pack(data_dir.path()).await;
+    let ctx = LauncherContext::new(
+        data_dir.path().to_path_buf(),
+        std::sync::Arc::new(NoProgress),
+    )
+    .unwrap();
+    let install = |accept_eula: bool| {
+        install_server_from_manifest(
+            &ctx,
+            server.url("/story-manifest.json"),
+            server_dir.path().to_string_lossy().to_string(),
+            accept_eula,
+            false,
+        )
+    };
+
+    assert!(!install(false).await.unwrap().eula_accepted);
+    let report = install(true).await.unwrap();
+    assert!(report.eula_accepted);
+    let eula = std::fs::read_to_string(server_dir.path().join("eula.txt")).unwrap();
+    assert!(eula.lines().any(|line| line == "eula=true"));
+
+    // A reinstall keeps the launcher already in place
+    assert_eq!(server.hits(LAUNCHER_PATH), 1);
+}
+
+#[test]
+fn test_server_side_mods_follow_dependencies() {
+    let locked = |name: &str, env: Option<ModEnv>, dependencies: &[&str]| LockedMod {
+        name: name.to_string(),
+        file: LockedFile {
+            project_id: name.to_string(),
+            version_id: format!("{}-1", name),
+            version_number: "1.0.0".to_string(),
+            filename: format!("{}.jar", name),
+            url: format!("https://cdn.example.com/{}.jar", name),
+            sha512: "aa".to_string(),
+            size: 1,
+        },
+        requested: env.map(|env| ExtraMod {
+            name: name.to_string(),
+            version: None,
+            version_id: None,
+            sha512: None,
+            filename: None,
+            release_channel: None,
+            prefer_featured: None,
+            env: Some(env),
+            url: None,
+            path: None,
+        }),
+        dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        incompatible: Vec::new(),
+        loader_fallback: None,
+        minecraft_version_fallback: None,
+    };
+    let client_only = ModEnv {
+        client: EnvSupport::Required,
+        server: EnvSupport::Unsupported,
+    };
+
+    let mods = vec![
+        locked("iris", Some(client_only), &["iris-lib", "fabric-api"]),
+        locked("ledger", Some(ModEnv::default()), &["fabric-api"]),
+        locked("iris-lib", None, &[]),
+        locked("fabric-api", None, &[]),
+    ];
+    let (server_mods, skipped) = server_side_mods(&mods);
+    let names: Vec<&str> = server_mods.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["ledger", "fabric-api"]);
+    assert_eq!(skipped, vec!["iris".to_string(), "iris-lib".to_string()]);
+}
+
+#[test]
+fn test_manifest_validator_checks_env() {
+    let manifest = |server: &str| {
+        format!(
+            r#"{{
+                "instance": {{ "name": "pack", "version": "1.0.0" }},
+                "extra_mods": [{{ "name": "iris", "env": {{ "client": "required", "server": "{}" }} }}]
+            }}"#,
+            server
+        )
+    };
+
+    assert!(validate_manifest_text(&manifest("unsupported")).valid);
+    let report = validate_manifest_text(&manifest("sometimes"));
+    assert_eq!(report.errors[0].path, "$.extra_mods[0].env.server");
+}
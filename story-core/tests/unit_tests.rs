@@ -1,7 +1,5 @@
-use story_launcher_lib::*;
+use story_core::*;
 use std::collections::HashMap;
-use std::path::Path;
-use tempfile::TempDir;
 
 // Test the struct serialization/deserialization
 #[test]
@@ -44,24 +42,42 @@ fn test_modrinth_dependency_serialization() {
 #[test]
 fn test_story_manifest_serialization() {
     let manifest = StoryManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
         instance: InstanceConfig {
             name: "Test Instance".to_string(),
             version: "1.0.0".to_string(),
             minecraft_version: Some("1.21.1".to_string()),
             loader: Some("fabric".to_string()),
+            release_channel: None,
+            prefer_featured: None,
+            loader_fallbacks: None,
+            accept_same_minor: None,
         },
         extra_mods: Some(vec![
             ExtraMod {
                 name: "test-mod".to_string(),
                 version: Some("1.0.0".to_string()),
+                version_id: None,
+                sha512: None,
+                filename: None,
+                release_channel: None,
+                prefer_featured: None,
+                env: None,
+                url: None,
+                path: None,
             }
         ]),
         overrides: Some(vec![
             Override {
                 name: "config".to_string(),
                 url: "https://example.com/config.zip".to_string(),
+                sha256: None,
+                size: None,
+                env: None,
             }
         ]),
+        lockfile: None,
+        packwiz: None,
     };
     
     let json = serde_json::to_string(&manifest).unwrap();
@@ -95,6 +111,7 @@ fn test_file_hash_registry_serialization() {
     file_info.insert("https://example.com/file.zip".to_string(), FileInfo {
         hash: "abc123".to_string(),
         last_modified: "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        etag: None,
     });
     registry.files = file_info;
     
@@ -111,11 +128,19 @@ fn test_modrinth_index_serialization() {
     hashes.insert("sha256".to_string(), "def456".to_string());
     
     let index = ModrinthIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: "1.0.0".to_string(),
+        name: "test-pack".to_string(),
+        summary: None,
         files: vec![ModrinthIndexFile {
             path: "mods/test-mod.jar".to_string(),
             hashes,
             downloads: vec!["https://example.com/download".to_string()],
+            env: None,
+            file_size: None,
         }],
+        dependencies: HashMap::new(),
     };
     
     let json = serde_json::to_string(&index).unwrap();
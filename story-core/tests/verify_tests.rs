@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use story_core::*;
+use tempfile::TempDir;
+
+// Tests for instance verification against the mrpack index and managed files
+fn write_index(story_path: &Path, files: &[(&str, &[u8])]) {
+    let index = ModrinthIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: "1.0.0".to_string(),
+        name: "test-pack".to_string(),
+        summary: None,
+        files: files
+            .iter()
+            .map(|(path, content)| {
+                let mut hashes = HashMap::new();
+                hashes.insert("sha512".to_string(), sha512_hex(content));
+                ModrinthIndexFile {
+                    path: path.to_string(),
+                    hashes,
+                    downloads: vec![format!("https://cdn.example.com/{}", path)],
+                    env: None,
+                    file_size: None,
+                }
+            })
+            .collect(),
+        dependencies: HashMap::new(),
+    };
+    fs::create_dir_all(story_path.join("mrpack")).unwrap();
+    fs::write(
+        story_path.join("mrpack/modrinth.index.json"),
+        serde_json::to_string(&index).unwrap(),
+    )
+    .unwrap();
+}
+
+fn write_file(story_path: &Path, relative: &str, content: &[u8]) {
+    let path = story_path.join(".minecraft").join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_verify_reports_missing_modified_and_extra() {
+    let temp_dir = TempDir::new().unwrap();
+    let story_path = temp_dir.path().join("Story");
+
+    write_index(
+        &story_path,
+        &[
+            ("mods/sodium.jar", b"sodium"),
+            ("mods/lithium.jar", b"lithium"),
+            ("mods/iris.jar", b"iris"),
+        ],
+    );
+    write_file(&story_path, "mods/sodium.jar", b"sodium");
+    write_file(&story_path, "mods/lithium.jar", b"lithium but patched");
+    write_file(&story_path, "mods/dropped-in.jar", b"user mod");
+
+    let report = verify_instance_files(&story_path).unwrap();
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.missing.len(), 1);
+    assert_eq!(report.missing[0].path, "mods/iris.jar");
+    assert_eq!(report.modified.len(), 1);
+    assert_eq!(report.modified[0].path, "mods/lithium.jar");
+    assert_eq!(report.extra, vec!["mods/dropped-in.jar".to_string()]);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn test_extra_files_only_come_from_mods_and_pack_folders() {
+    let temp_dir = TempDir::new().unwrap();
+    let story_path = temp_dir.path().join("Story");
+
+    write_index(
+        &story_path,
+        &[
+            ("shaderpacks/story.zip", b"shaders"),
+            ("config/bundled.zip", b"bundled config"),
+            ("saves/tutorial.zip", b"tutorial world"),
+        ],
+    );
+    write_file(&story_path, "shaderpacks/story.zip", b"shaders");
+    write_file(&story_path, "shaderpacks/mine.zip", b"player shaders");
+    write_file(&story_path, "config/bundled.zip", b"bundled config");
+    write_file(&story_path, "config/game-written.json", b"{}");
+    write_file(&story_path, "saves/tutorial.zip", b"tutorial world");
+    write_file(&story_path, "saves/backup.zip", b"player backup");
+    write_file(&story_path, "resourcepacks/mine.zip", b"player pack");
+
+    let report = verify_instance_files(&story_path).unwrap();
+    assert_eq!(report.extra, vec!["shaderpacks/mine.zip".to_string()]);
+}
+
+#[test]
+fn test_verify_includes_extra_mods_and_overrides() {
+    let temp_dir = TempDir::new().unwrap();
+    let story_path = temp_dir.path().join("Story");
+
+    write_index(&story_path, &[("mods/sodium.jar", b"sodium")]);
+    write_file(&story_path, "mods/sodium.jar", b"sodium");
+    write_file(&story_path, "mods/jei.jar", b"jei");
+    write_file(&story_path, "config/story.json", b"{}");
+
+    let managed = ManagedFiles {
+        files: vec![
+            ManagedFile {
+                path: "mods/jei.jar".to_string(),
+                sha512: sha512_hex(b"jei"),
+                source: ManagedSource::ExtraMod {
+                    name: "jei".to_string(),
+                    urls: vec!["https://cdn.example.com/jei.jar".to_string()],
+                },
+            },
+            ManagedFile {
+                path: "config/story.json".to_string(),
+                sha512: sha512_hex(b"{\"edited\":true}"),
+                source: ManagedSource::Override {
+                    name: "config".to_string(),
+                    url: "https://example.com/config.zip".to_string(),
+                    sha256: None,
+                },
+            },
+        ],
+    };
+    save_managed_files(&story_path, &managed).unwrap();
+
+    let report = verify_instance_files(&story_path).unwrap();
+    assert_eq!(report.checked, 3);
+    assert!(report.missing.is_empty());
+    assert!(report.extra.is_empty());
+    assert_eq!(report.modified.len(), 1);
+    assert_eq!(report.modified[0].path, "config/story.json");
+    assert!(matches!(
+        report.modified[0].source,
+        ManagedSource::Override { .. }
+    ));
+}
+
+#[test]
+fn test_managed_files_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // Missing registry loads as empty
+    assert!(load_managed_files(temp_dir.path()).files.is_empty());
+
+    let managed = ManagedFiles {
+        files: vec![ManagedFile {
+            path: "mods/jei.jar".to_string(),
+            sha512: "abc".to_string(),
+            source: ManagedSource::Mrpack {
+                urls: vec!["https://cdn.example.com/jei.jar".to_string()],
+            },
+        }],
+    };
+    save_managed_files(temp_dir.path(), &managed).unwrap();
+
+    let content = fs::read_to_string(temp_dir.path().join(".managed_files.json")).unwrap();
+    assert!(content.contains("\"type\": \"mrpack\""));
+    assert_eq!(load_managed_files(temp_dir.path()).files, managed.files);
+}
+
+#[test]
+fn test_zip_entry_hashes_and_cache_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let zip_path = cached_zip_path(temp_dir.path(), "https://example.com/packs/config.zip?v=2");
+    assert_eq!(zip_path, temp_dir.path().join("config.zip"));
+
+    let mut writer = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+    let options = zip::write::FileOptions::default();
+    writer.add_directory("config/", options).unwrap();
+    writer.start_file("config/story.json", options).unwrap();
+    writer.write_all(b"{}").unwrap();
+    writer.start_file("manifest.json", options).unwrap();
+    writer.write_all(b"{\"notes\":\"skip me\"}").unwrap();
+    writer.finish().unwrap();
+
+    let entries = zip_entry_hashes(&zip_path).unwrap();
+    assert_eq!(
+        entries,
+        vec![("config/story.json".to_string(), sha512_hex(b"{}"))]
+    );
+
+    // URLs without a filename fall back to a hash-based name
+    let fallback = cached_zip_path(temp_dir.path(), "https://example.com/");
+    assert!(fallback
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("download-"));
+}